@@ -19,6 +19,7 @@ async fn run() {
 async fn show_brightness(dev: &BrightnessDevice) -> Result<(), brightness::Error> {
     println!("Display {}", dev.device_name().await?);
     println!("\tBrightness = {}%", dev.get().await?);
+    println!("\tKind = {:?}", dev.kind().await?);
     show_platform_specific_info(dev).await?;
     Ok(())
 }
@@ -0,0 +1,170 @@
+// Copyright (C) 2022 The brightness project authors. Distributed under the 0BSD license.
+
+//! Restoration of the brightness of devices that reconnect.
+
+use crate::{brightness_devices, fingerprint::ConfigFingerprint, Brightness, Error};
+use futures::StreamExt;
+
+/// Remembers the brightness set on devices to apply it again when they reappear.
+///
+/// Some monitors reset their brightness when they reconnect, e.g. after the computer wakes from
+/// sleep. Brightness set through [`AutoRestore::set`] is remembered by [`ConfigFingerprint`], and
+/// [`AutoRestore::restore`] or [`AutoRestore::restore_all`] apply it again to devices found later.
+///
+/// Devices are identified by the monitor described in their EDID, so a monitor is recognized even
+/// if it comes back with a different device name, e.g. on another connector. Monitors without an
+/// EDID, and identical monitors whose EDID has no serial number, are also identified by device
+/// name.
+///
+/// Restoring is not automatic: the caller must call [`AutoRestore::restore_all`] when monitors may
+/// have reconnected, e.g. on resume from sleep, or when [`crate::diff_config`] reports added or
+/// replaced devices between two calls to [`crate::config_fingerprints`].
+///
+/// With the `serde` feature, this can be serialized to keep the remembered brightness across
+/// restarts.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct AutoRestore {
+    values: Vec<(ConfigFingerprint, u32)>,
+}
+
+impl AutoRestore {
+    /// Creates an instance that does not remember any brightness yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the brightness of a device as a percentage and remembers it.
+    pub async fn set(
+        &mut self,
+        device: &mut (dyn Brightness + Send + Sync),
+        percentage: u32,
+    ) -> Result<(), Error> {
+        let percentage = percentage.min(100);
+        device.set(percentage).await?;
+        let fingerprint = ConfigFingerprint::of(device).await?;
+        self.forget(&fingerprint);
+        self.values.push((fingerprint, percentage));
+        Ok(())
+    }
+
+    /// Returns the brightness remembered for the device with the given fingerprint.
+    pub fn remembered(&self, fingerprint: &ConfigFingerprint) -> Option<u32> {
+        self.values
+            .iter()
+            .find(|(remembered, _)| same_device(remembered, fingerprint))
+            .map(|&(_, percentage)| percentage)
+    }
+
+    /// Forgets the brightness of the device with the given fingerprint, returning it.
+    pub fn forget(&mut self, fingerprint: &ConfigFingerprint) -> Option<u32> {
+        let index = self
+            .values
+            .iter()
+            .position(|(remembered, _)| same_device(remembered, fingerprint))?;
+        Some(self.values.remove(index).1)
+    }
+
+    /// Applies the remembered brightness to a device, returning whether it was changed.
+    ///
+    /// Nothing is done if no brightness is remembered for the device or if it already has the
+    /// remembered brightness.
+    pub async fn restore(
+        &self,
+        device: &mut (dyn Brightness + Send + Sync),
+    ) -> Result<bool, Error> {
+        let percentage = match self.remembered(&ConfigFingerprint::of(device).await?) {
+            Some(percentage) => percentage,
+            None => return Ok(false),
+        };
+        if device.get().await? == percentage {
+            return Ok(false);
+        }
+        device.set(percentage).await?;
+        Ok(true)
+    }
+
+    /// Applies the remembered brightness to all devices of the running system.
+    ///
+    /// This is meant to be called when monitors may have reconnected, e.g. after the computer
+    /// wakes from sleep. The result of each device is returned in the order devices are found. A
+    /// device failing does not prevent the other devices from being restored.
+    pub async fn restore_all(&self) -> Vec<Result<bool, Error>> {
+        brightness_devices()
+            .then(|device| async move { self.restore(&mut device?).await })
+            .collect()
+            .await
+    }
+}
+
+/// Returns whether two fingerprints identify the same device, see [`AutoRestore`].
+fn same_device(a: &ConfigFingerprint, b: &ConfigFingerprint) -> bool {
+    match (&a.monitor, &b.monitor) {
+        (Some(monitor), Some(other)) if monitor == other => {
+            let has_serial = monitor.serial_number != 0 || monitor.serial_string.is_some();
+            has_serial || a.connector == b.connector
+        }
+        (None, None) => a.connector == b.connector,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{edid::EdidInfo, mock::MockDevice};
+    use futures::executor::block_on;
+
+    fn monitor(name: &str, serial_number: u32) -> MockDevice {
+        MockDevice::new(name).with_edid(EdidInfo {
+            manufacturer_id: "DEL".to_owned(),
+            product_code: 0x40A0,
+            serial_number,
+            native_mode: None,
+            monitor_names: Vec::new(),
+            serial_strings: Vec::new(),
+            text_strings: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn restores_monitor_on_another_connector() {
+        block_on(async {
+            let mut restore = AutoRestore::new();
+            restore.set(&mut monitor("DP-1", 1), 30).await.unwrap();
+            let mut moved = monitor("DP-2", 1).with_percentage(100);
+            assert!(restore.restore(&mut moved).await.unwrap());
+            assert_eq!(moved.percentage(), 30);
+            assert!(!restore.restore(&mut moved).await.unwrap());
+            let mut other = monitor("DP-1", 2).with_percentage(100);
+            assert!(!restore.restore(&mut other).await.unwrap());
+        })
+    }
+
+    #[test]
+    fn tells_apart_monitors_without_serial_number_by_connector() {
+        block_on(async {
+            let mut restore = AutoRestore::new();
+            restore.set(&mut monitor("DP-1", 0), 30).await.unwrap();
+            restore.set(&mut monitor("DP-2", 0), 70).await.unwrap();
+            restore
+                .set(&mut MockDevice::new("eDP-1"), 50)
+                .await
+                .unwrap();
+            for (device, remembered) in [
+                (monitor("DP-1", 0), Some(30)),
+                (monitor("DP-2", 0), Some(70)),
+                (monitor("DP-3", 0), None),
+                (MockDevice::new("eDP-1"), Some(50)),
+            ] {
+                let fingerprint = ConfigFingerprint::of(&device).await.unwrap();
+                assert_eq!(restore.remembered(&fingerprint), remembered);
+            }
+            let fingerprint = ConfigFingerprint::of(&MockDevice::new("eDP-1"))
+                .await
+                .unwrap();
+            assert_eq!(restore.forget(&fingerprint), Some(50));
+            assert_eq!(restore.remembered(&fingerprint), None);
+        })
+    }
+}
@@ -0,0 +1,153 @@
+// Copyright (C) 2022 The brightness project authors. Distributed under the 0BSD license.
+
+//! Operations on several devices at once.
+
+use crate::{brightness_devices, Brightness, BrightnessDevice, DeviceInfo, Error};
+use async_io::Timer;
+use futures::{
+    future::{join, join_all, ready, select, try_join, Either},
+    Stream, StreamExt,
+};
+use std::{
+    future::Future,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+/// Returns the name and brightness of all devices of the running system, as a percentage.
+///
+/// Devices are handled one at a time, in the order they are found. A device failing does not
+/// prevent the other devices from being read.
+///
+/// If `cancel` is set, the operation stops before the next device and the results gathered so far
+/// are returned, followed by [`Error::Cancelled`]. A device being read is never interrupted.
+pub async fn get_all(cancel: Option<&AtomicBool>) -> Vec<Result<(String, u32), Error>> {
+    for_each_device(cancel, |device| async move {
+        Ok((device.device_name().await?, device.get().await?))
+    })
+    .await
+}
+
+/// Sets the brightness of all devices of the running system as a percentage, returning their
+/// names.
+///
+/// See [`get_all`] for the handling of failures and cancellation.
+pub async fn set_all(percentage: u32, cancel: Option<&AtomicBool>) -> Vec<Result<String, Error>> {
+    for_each_device(cancel, |mut device| async move {
+        device.set(percentage).await?;
+        device.device_name().await
+    })
+    .await
+}
+
+/// Returns the brightness of the primary display as a percentage.
+///
+/// See [`Brightness::is_primary`] for which display is primary. On Linux, this is the built-in
+/// panel. [`Error::NoPrimaryDevice`] is returned if there is none, e.g. on a desktop or on Windows
+/// when the primary monitor does not support brightness control.
+pub async fn get_primary_brightness() -> Result<u32, Error> {
+    primary_device().await?.get().await
+}
+
+/// Sets the brightness of the primary display as a percentage.
+///
+/// See [`get_primary_brightness`] for which display is primary and when
+/// [`Error::NoPrimaryDevice`] is returned.
+pub async fn set_primary_brightness(percentage: u32) -> Result<(), Error> {
+    primary_device().await?.set(percentage).await
+}
+
+/// Returns the first primary display. If none is found, the first error encountered is returned,
+/// as it may have hidden the primary display.
+async fn primary_device() -> Result<BrightnessDevice, Error> {
+    let mut devices = Box::pin(brightness_devices());
+    let mut first_error = None;
+    while let Some(device) = devices.next().await {
+        let found = match device {
+            Ok(device) => device
+                .is_primary()
+                .await
+                .map(|primary| primary.then_some(device)),
+            Err(e) => Err(e),
+        };
+        match found {
+            Ok(Some(device)) => return Ok(device),
+            Ok(None) => {}
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    Err(first_error.unwrap_or(Error::NoPrimaryDevice))
+}
+
+/// Returns a stream of the identity and brightness of all devices of the running system, as a
+/// percentage.
+///
+/// The brightness of the devices is read concurrently, and each device is yielded as soon as it is
+/// read, so the order may differ from [`brightness_devices`]. A device failing yields an error
+/// without ending the stream.
+pub fn devices_with_brightness() -> impl Stream<Item = Result<(DeviceInfo, u32), Error>> {
+    brightness_devices()
+        .map(|device| async move {
+            let device = device?;
+            Ok((device.0.info()?, device.get().await?))
+        })
+        .buffer_unordered(usize::MAX)
+}
+
+/// Returns the devices of the running system that respond to brightness requests.
+///
+/// The brightness of all devices is read concurrently, and devices whose read fails or does not
+/// complete within `timeout` are left out. This filters out ghost monitors, monitors with DDC/CI
+/// disabled and virtual displays. Devices that cannot be listed are left out too.
+pub async fn working_devices(timeout: Duration) -> Vec<BrightnessDevice> {
+    let devices = brightness_devices()
+        .filter_map(|device| ready(device.ok()))
+        .collect::<Vec<_>>()
+        .await;
+    let probes = devices.into_iter().map(|device| async move {
+        let responded = matches!(
+            select(device.get(), Timer::after(timeout)).await,
+            Either::Left((Ok(_), _))
+        );
+        responded.then_some(device)
+    });
+    join_all(probes).await.into_iter().flatten().collect()
+}
+
+/// Swaps the brightness of two devices.
+///
+/// Both devices are read, then each is set to the brightness of the other. Reads and writes are
+/// done concurrently. If a read fails, nothing is written and the error is returned. Otherwise,
+/// the results of the writes to `a` and `b` are returned, so that a failed write can be handled
+/// while knowing whether the other device was changed.
+pub async fn swap_brightness(
+    a: &mut (dyn Brightness + Send + Sync),
+    b: &mut (dyn Brightness + Send + Sync),
+) -> Result<(Result<(), Error>, Result<(), Error>), Error> {
+    let (a_percentage, b_percentage) = try_join(a.get(), b.get()).await?;
+    Ok(join(a.set(b_percentage), b.set(a_percentage)).await)
+}
+
+async fn for_each_device<F, Fut, T>(cancel: Option<&AtomicBool>, mut f: F) -> Vec<Result<T, Error>>
+where
+    F: FnMut(BrightnessDevice) -> Fut,
+    Fut: Future<Output = Result<T, Error>>,
+{
+    let cancelled = || cancel.is_some_and(|c| c.load(Ordering::SeqCst));
+    let mut results = Vec::new();
+    let mut devices = Box::pin(brightness_devices());
+    loop {
+        if cancelled() {
+            results.push(Err(Error::Cancelled));
+            break;
+        }
+        match devices.next().await {
+            Some(Ok(device)) => results.push(f(device).await),
+            Some(Err(e)) => results.push(Err(e)),
+            None => break,
+        }
+    }
+    results
+}
@@ -2,11 +2,16 @@
 
 //! The blocking API.
 
-use crate::Error;
+use crate::{
+    capabilities::Capabilities,
+    edid::{DisplayMode, EdidInfo},
+    BrightnessCurve, DeviceInfo, DeviceKind, Error, ErrorKind,
+};
+use std::{error::Error as StdError, thread, time::Duration};
 
 cfg_if::cfg_if! {
     if #[cfg(target_os = "linux")] {
-        pub(crate) mod linux;
+        pub mod linux;
         use self::linux as platform;
     } else if #[cfg(windows)] {
         pub mod windows;
@@ -21,9 +26,25 @@ cfg_if::cfg_if! {
 #[allow(private_interfaces)]
 pub struct BrightnessDevice(pub platform::BlockingDeviceImpl);
 
+impl BrightnessDevice {
+    /// Resets all options of the device to their defaults.
+    ///
+    /// The options configured with [`crate::DeviceOptions`] or the platform `BrightnessExt` traits
+    /// (e.g. clamps and custom mappings) are dropped, so that the device behaves like a freshly
+    /// listed one. The system bus connection set on Linux is kept. The device itself is not
+    /// accessed.
+    pub fn reset_options(&mut self) -> Result<(), Error> {
+        crate::DeviceOptions::new().apply_blocking(self)
+    }
+}
+
 /// Blocking interface to get and set brightness.
 pub trait Brightness {
     /// Returns the device name.
+    ///
+    /// This identifies the device on the running system: the backlight device name on Linux (e.g.
+    /// `intel_backlight`) and the GDI name of the monitor device on Windows (e.g.
+    /// `\\.\DISPLAY1\Monitor0`). Windows also provides a friendly name to show to users.
     fn device_name(&self) -> Result<String, Error>;
 
     /// Returns the current brightness as a percentage.
@@ -31,6 +52,165 @@ pub trait Brightness {
 
     /// Sets the brightness as a percentage.
     fn set(&self, percentage: u32) -> Result<(), Error>;
+
+    /// Returns the EDID information reported by the monitor, if available.
+    ///
+    /// The default implementation returns `None`.
+    fn edid(&self) -> Result<Option<EdidInfo>, Error> {
+        Ok(None)
+    }
+
+    /// Returns whether the device is the primary display.
+    ///
+    /// On Linux, there is no notion of a primary display outside of the display server, so the
+    /// built-in panel of a laptop is considered to be the primary display. If the panel has several
+    /// devices, the raw device of its eDP, LVDS or DSI connector is primary (e.g. `intel_backlight`
+    /// rather than `acpi_video0`). No device is primary without a built-in panel (e.g. on a desktop
+    /// with ddcci-backlight monitors) or if the choice is ambiguous. The primary display is found
+    /// once per enumeration of devices.
+    ///
+    /// The default implementation returns `false`.
+    fn is_primary(&self) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    /// Returns the kind of device.
+    ///
+    /// The default implementation returns [`DeviceKind::Other`].
+    fn kind(&self) -> Result<DeviceKind, Error> {
+        Ok(DeviceKind::Other)
+    }
+
+    /// Returns the DDC/CI capabilities string reported by the monitor, if available.
+    ///
+    /// Internal displays have no capabilities string. On Linux, it is only available for devices of
+    /// the ddcci-backlight driver. On Windows, it is read from the monitor on first use and cached
+    /// until the device is reopened, so that checking supported features is cheap afterwards.
+    ///
+    /// The default implementation returns `None`.
+    fn raw_capabilities(&self) -> Result<Option<String>, Error> {
+        Ok(None)
+    }
+
+    /// Returns the raw value of the highest brightness, in the native units of the device.
+    ///
+    /// On Linux, this is `max_brightness`. On Windows, this is the maximum reported by DDC/CI
+    /// monitors for VCP code 0x10, and 100 for internal displays whose brightness levels are
+    /// percentages.
+    ///
+    /// The default implementation returns 100, i.e. raw values are assumed to be percentages.
+    fn brightness_raw_max(&self) -> Result<u32, Error> {
+        Ok(100)
+    }
+
+    /// Returns the parsed DDC/CI capabilities of the monitor, if available.
+    fn capabilities(&self) -> Result<Option<Capabilities>, Error> {
+        Ok(self
+            .raw_capabilities()?
+            .map(|raw| Capabilities::parse(&raw)))
+    }
+
+    /// Returns the preferred resolution and refresh rate of the monitor, as described by its EDID.
+    fn native_mode(&self) -> Result<Option<DisplayMode>, Error> {
+        Ok(self.edid()?.and_then(|edid| edid.native_mode))
+    }
+
+    /// Toggles between two brightness percentages and returns the one that was applied.
+    ///
+    /// If the current brightness is closer to `low` than to `high`, the brightness is set to
+    /// `high`, otherwise it is set to `low`. A brightness exactly at the midpoint is considered
+    /// closer to `low`.
+    fn toggle(&self, low: u32, high: u32) -> Result<u32, Error> {
+        let target = crate::toggle_target(self.get()?, low, high);
+        self.set(target)?;
+        Ok(target)
+    }
+
+    /// Sets the brightness for an ambient light level in lux according to a curve, and returns the
+    /// resulting brightness as a percentage.
+    ///
+    /// The brightness is left unchanged if the curve has no points, or if the change would be
+    /// smaller than the hysteresis of the curve.
+    fn auto_adjust(&self, lux: f64, curve: &BrightnessCurve) -> Result<u32, Error> {
+        let current = self.get()?;
+        match curve.target(current, lux) {
+            Some(target) => {
+                self.set(target)?;
+                Ok(target)
+            }
+            None => Ok(current),
+        }
+    }
+
+    /// Sets the brightness to reach a luminance in nits, given the maximum luminance of the
+    /// display, and returns whether the target is achievable.
+    ///
+    /// See [`crate::percentage_for_nits`] for how the percentage is computed. If `nits` exceeds
+    /// `max_nits`, the brightness is set to 100% and `false` is returned.
+    fn set_nits(&self, nits: f64, max_nits: f64) -> Result<bool, Error> {
+        self.set(crate::percentage_for_nits(nits, max_nits))?;
+        Ok(nits <= max_nits)
+    }
+
+    /// Sets the brightness to `level` as a percentage for `duration`, then restores the brightness
+    /// it had before.
+    ///
+    /// The brightness read before the boost is restored even if it was changed in the meantime.
+    /// The calling thread sleeps during the boost.
+    fn boost(&self, level: u32, duration: Duration) -> Result<(), Error> {
+        let previous = self.get()?;
+        self.set(level)?;
+        thread::sleep(duration);
+        self.set(previous)
+    }
+
+    /// Flashes the device for `duration` so that the user can tell which screen it is, then
+    /// restores its brightness.
+    ///
+    /// The brightness alternates a couple of times between its current value and a distant one.
+    /// [`Error::BrightnessNotAdjustable`] is returned if the device still has its previous
+    /// brightness once the first flash has lasted its share of `duration`. The brightness is
+    /// restored if flashing fails midway. The calling thread sleeps while flashing.
+    fn identify(&self, duration: Duration) -> Result<(), Error> {
+        let previous = self.get()?;
+        let flash = identify_level(previous);
+        let step = duration / (2 * IDENTIFY_FLASHES);
+        let flash_all = || {
+            for i in 0..IDENTIFY_FLASHES {
+                self.set(flash)?;
+                thread::sleep(step);
+                // Monitors may apply writes with a delay, so the brightness is read back after
+                // waiting.
+                if i == 0 && self.get()? == previous {
+                    return Err(Error::BrightnessNotAdjustable {
+                        device: self.device_name()?,
+                    });
+                }
+                self.set(previous)?;
+                thread::sleep(step);
+            }
+            Ok(())
+        };
+        let flashed = flash_all();
+        if flashed.is_err() {
+            // Best effort, the error that interrupted flashing is the one reported.
+            let _ = self.set(previous);
+        }
+        flashed
+    }
+}
+
+/// Number of times `identify` flashes a device.
+pub(crate) const IDENTIFY_FLASHES: u32 = 2;
+
+/// Returns the percentage `identify` flashes a device to, far enough from its current brightness
+/// to be noticed.
+pub(crate) fn identify_level(previous: u32) -> u32 {
+    if previous > 50 {
+        10
+    } else {
+        100
+    }
 }
 
 impl Brightness for BrightnessDevice {
@@ -45,9 +225,72 @@ impl Brightness for BrightnessDevice {
     fn set(&self, percentage: u32) -> Result<(), Error> {
         self.0.set(percentage)
     }
+
+    fn edid(&self) -> Result<Option<EdidInfo>, Error> {
+        self.0.edid()
+    }
+
+    fn is_primary(&self) -> Result<bool, Error> {
+        self.0.is_primary()
+    }
+
+    fn kind(&self) -> Result<DeviceKind, Error> {
+        self.0.kind()
+    }
+
+    fn raw_capabilities(&self) -> Result<Option<String>, Error> {
+        self.0.raw_capabilities()
+    }
+
+    fn brightness_raw_max(&self) -> Result<u32, Error> {
+        self.0.brightness_raw_max()
+    }
 }
 
 /// Blocking function that returns all brightness devices on the running system.
+///
+/// On Windows, some drivers fail the enumeration of monitors after reporting some of them. The
+/// devices found are then followed by an error of kind [`ErrorKind::Transient`], so that an
+/// incomplete list can be told apart from a complete one.
 pub fn brightness_devices() -> impl Iterator<Item = Result<BrightnessDevice, Error>> {
     platform::brightness_devices().map(|r| r.map(BrightnessDevice).map_err(Into::into))
 }
+
+/// Blocking function that returns all brightness devices on the running system, listing them again
+/// if monitors are connected or disconnected while they are listed.
+///
+/// Devices are listed at most `attempts` times (at least once), waiting `delay` between attempts.
+/// The first complete list of devices is returned, or the last error if all attempts failed. Other
+/// failures are returned without retrying.
+///
+/// On Windows, the display devices of a monitor are also looked up again after a short delay if
+/// none are found, which can happen while the display configuration changes. [`brightness_devices`]
+/// does not wait for this.
+pub fn brightness_devices_retry(
+    attempts: usize,
+    delay: Duration,
+) -> Result<Vec<BrightnessDevice>, Error> {
+    let mut attempt = 1;
+    loop {
+        match platform::brightness_devices_for_retry().collect::<Result<Vec<_>, _>>() {
+            Ok(devices) => return Ok(devices.into_iter().map(BrightnessDevice).collect()),
+            Err(e) if e.is_transient() && attempt < attempts => {
+                attempt += 1;
+                thread::sleep(delay);
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Returns the category of the platform error causing an [`Error`].
+pub(crate) fn error_kind(source: &(dyn StdError + Send + Sync + 'static)) -> ErrorKind {
+    source
+        .downcast_ref::<platform::SysError>()
+        .map_or(ErrorKind::Other, platform::SysError::kind)
+}
+
+/// Returns the identity of all brightness devices without opening them.
+pub(crate) fn list_devices() -> Result<Vec<DeviceInfo>, Error> {
+    Ok(platform::list_devices()?)
+}
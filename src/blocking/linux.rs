@@ -2,19 +2,201 @@
 
 //! Platform-specific implementation for Linux.
 
-use crate::Error;
+use crate::{
+    blocking::BrightnessDevice, edid::EdidInfo, BrightnessMapping, DeviceInfo, DeviceKind, Error,
+    ErrorKind,
+};
 use itertools::Either;
-use std::{fs, io, iter::once, path::PathBuf};
+use std::{
+    collections::{HashMap, VecDeque},
+    error::Error as StdError,
+    ffi::CString,
+    fmt, fs,
+    io::{self, Read, Write},
+    iter::once,
+    mem::size_of,
+    os::unix::{
+        ffi::OsStrExt,
+        io::{AsRawFd, FromRawFd, RawFd},
+    },
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    ptr,
+    sync::{mpsc, Arc, Mutex, OnceLock},
+    thread,
+    time::{Duration, Instant},
+};
 
 pub(crate) const BACKLIGHT_DIR: &str = "/sys/class/backlight";
+const HW_CHANGED: &str = "brightness_hw_changed";
+const SYS_DEVICES_DIR: &str = "/sys/devices";
 pub(crate) const USER_DBUS_NAME: &str = "org.freedesktop.login1";
 pub(crate) const SESSION_OBJECT_PATH: &str = "/org/freedesktop/login1/session/auto";
 pub(crate) const SESSION_INTERFACE: &str = "org.freedesktop.login1.Session";
 pub(crate) const SET_BRIGHTNESS_METHOD: &str = "SetBrightness";
+const INTROSPECTABLE_INTERFACE: &str = "org.freedesktop.DBus.Introspectable";
+const INTROSPECT_METHOD: &str = "Introspect";
+pub(crate) const GSD_POWER_DBUS_NAME: &str = "org.gnome.SettingsDaemon.Power";
+pub(crate) const GSD_POWER_OBJECT_PATH: &str = "/org/gnome/SettingsDaemon/Power";
+pub(crate) const GSD_SCREEN_INTERFACE: &str = "org.gnome.SettingsDaemon.Power.Screen";
+pub(crate) const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+pub(crate) const SET_PROPERTY_METHOD: &str = "Set";
+pub(crate) const BRIGHTNESS_PROPERTY: &str = "Brightness";
+const PKEXEC: &str = "pkexec";
+/// Default of [`BrightnessExt::set_logind_timeout`].
+pub(crate) const LOGIND_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Linux-specific brightness functionality.
+pub trait BrightnessExt {
+    /// Sets whether `get` returns the brightness last set through this device instead of reading
+    /// `actual_brightness`.
+    ///
+    /// Some drivers do not update `actual_brightness` after a write. When enabled, the value
+    /// written by `set` is returned by `get` until a brightness change made by someone else is
+    /// detected using inotify. This is disabled by default.
+    fn set_trust_last_write(&mut self, trust: bool) -> Result<(), Error>;
+
+    /// Sets whether `set` writes the brightness through `pkexec` when writing to the brightness
+    /// file is not permitted.
+    ///
+    /// When enabled and neither logind nor a direct write can set the brightness, a privileged
+    /// `tee` process is spawned with `pkexec`, which asks the user to authenticate with polkit.
+    /// This is meant for desktop applications on systems without udev rules granting write access
+    /// to backlight devices. This is disabled by default.
+    fn set_use_pkexec(&mut self, enabled: bool);
+
+    /// Sets whether `set` goes through the GNOME settings daemon for built-in panels.
+    ///
+    /// When enabled, the `Brightness` property of the `org.gnome.SettingsDaemon.Power.Screen`
+    /// interface is set on the session bus, so that the brightness indicator of GNOME stays in
+    /// sync. If the daemon is not running or does not provide this property, brightness is set
+    /// through logind as usual. This is disabled by default.
+    fn set_use_gnome_settings_daemon(&mut self, enabled: bool);
+
+    /// Sets whether the device is read-only.
+    ///
+    /// When enabled, `set` fails without connecting to D-Bus or writing anything, which prevents
+    /// accidental writes from tools that only monitor brightness. Reading brightness never needs
+    /// write access nor a D-Bus connection, whether this is enabled or not. This is disabled by
+    /// default.
+    fn set_read_only(&mut self, read_only: bool);
+
+    /// Sets whether `set` reads `actual_brightness` back after writing the brightness file.
+    ///
+    /// When logind cannot set the brightness, `set` falls back to writing the brightness file,
+    /// which some drivers accept while ignoring or clamping the value. When enabled,
+    /// [`Error::SettingClamped`] is returned if the value read back after such a write differs from
+    /// the value written. Brightness set through logind or the GNOME settings daemon is not read
+    /// back. This is disabled by default, as it costs an additional read.
+    fn set_verify_writes(&mut self, enabled: bool);
+
+    /// Sets the system bus connection used by `set` to call logind.
+    ///
+    /// By default, a new connection to the system bus is opened for each call. Applications that
+    /// already maintain a connection can share it with the device instead.
+    fn set_system_connection(&mut self, connection: zbus::Connection);
+
+    /// Sets how long `set` waits for logind to answer a `SetBrightness` call.
+    ///
+    /// A hung system bus or logind (e.g. under heavy load) would otherwise block `set`
+    /// indefinitely. When the timeout expires, `set` fails with an error whose kind is
+    /// [`crate::ErrorKind::Transient`], and the brightness file is not written, as logind may still
+    /// apply the request later. The blocking API makes the calls of all devices on a single worker
+    /// thread, so while logind does not answer, later calls time out as well. The default is 5
+    /// seconds.
+    fn set_logind_timeout(&mut self, timeout: Duration);
+
+    /// Sets a custom mapping between percentages and raw values used by `get` and `set`, or
+    /// restores the default linear mapping with `None`.
+    ///
+    /// See [`BrightnessMapping`].
+    fn set_mapping(&mut self, mapping: Option<BrightnessMapping>);
+
+    /// Returns the percentage that `set(requested)` applies, without accessing the device.
+    ///
+    /// Percentages are clamped to 100 and the custom mapping is applied, which lets a UI show where
+    /// a slider snaps before setting brightness. With a custom mapping, the raw range of the device
+    /// is assumed to be that of the mapping.
+    fn preview(&self, requested: u32) -> u32;
+
+    /// Returns whether the panel adjusts its backlight to the displayed content.
+    ///
+    /// With content-adaptive backlight, the brightness perceived by the user differs from the
+    /// reported percentage, which should then be considered approximate. This is currently
+    /// detected for panels driven by amdgpu with Adaptive Backlight Management enabled
+    /// (`panel_power_savings` attribute of the connector). `false` is returned for other devices.
+    fn is_adaptive(&self) -> Result<bool, Error>;
+
+    /// Returns whether the display of the device is powered on, or `None` if this is unknown.
+    ///
+    /// This reads the `enabled` and `dpms` attributes of the DRM connector the device is associated
+    /// with, so that brightness changes can be skipped while the screen is blanked (e.g. by a
+    /// screensaver), when writes may be ignored and `actual_brightness` may read 0 or a stale value.
+    /// `None` is returned for devices not associated with a connector, such as firmware devices.
+    fn is_powered(&self) -> Result<Option<bool>, Error>;
+
+    /// Returns how raw brightness values relate to the light emitted by the panel, as reported by
+    /// the kernel (`scale` attribute).
+    ///
+    /// Percentages are linear in raw values, so with a linear scale `set(50)` emits about half the
+    /// light but looks brighter than half. A [`crate::BrightnessMapping`] can be used to adjust for
+    /// this. [`BacklightScale::Unknown`] is returned if the driver does not report the scale.
+    fn scale(&self) -> Result<BacklightScale, Error>;
+
+    /// Returns how `set` changes the brightness of the device.
+    ///
+    /// This follows the decisions made by `set`, using the options of the device. If it is not
+    /// known yet whether logind provides the `SetBrightness` method, logind is introspected on the
+    /// system bus, which blocks. [`SetMechanism::Logind`] is returned when the method is available,
+    /// even though `set` still falls back to writing the brightness file if logind rejects the
+    /// request.
+    fn set_mechanism(&self) -> Result<SetMechanism, Error>;
+}
+
+/// Mechanism used by `set` to change the brightness of a backlight device.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum SetMechanism {
+    /// The GNOME settings daemon, see [`BrightnessExt::set_use_gnome_settings_daemon`]
+    GnomeSettingsDaemon,
+    /// The `SetBrightness` method of logind
+    Logind,
+    /// A direct write to the brightness file of the device
+    DirectWrite,
+    /// A write to the brightness file through `pkexec`, see [`BrightnessExt::set_use_pkexec`]
+    Pkexec,
+    /// No mechanism: the device is read-only, see [`BrightnessExt::set_read_only`]
+    ReadOnly,
+    /// No mechanism: writing to the brightness file is not permitted, so `set` fails unless
+    /// access is granted (e.g. with a udev rule)
+    PermissionDenied,
+}
+
+/// Relationship between the raw brightness values of a backlight device and the emitted light.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum BacklightScale {
+    /// The scale is not reported by the driver
+    Unknown,
+    /// Raw values are proportional to the emitted light
+    Linear,
+    /// Raw values are not proportional to the emitted light, usually so that they are closer to
+    /// perceived brightness
+    NonLinear,
+}
 
 #[derive(Debug)]
 pub(crate) struct BlockingDeviceImpl {
     device: String,
+    last_write: Mutex<Option<LastWrite>>,
+    use_pkexec: bool,
+    use_gnome_settings_daemon: bool,
+    read_only: bool,
+    verify_writes: bool,
+    logind_timeout: Duration,
+    system_connection: Option<zbus::blocking::Connection>,
+    mapping: Option<BrightnessMapping>,
+    primary: SharedPrimary,
 }
 
 impl crate::blocking::Brightness for BlockingDeviceImpl {
@@ -24,63 +206,99 @@ impl crate::blocking::Brightness for BlockingDeviceImpl {
 
     fn get(&self) -> Result<u32, Error> {
         let max = read_value(&self.device, Value::Max)?;
-        let actual = read_value(&self.device, Value::Actual)?;
-        let percentage = if max == 0 {
-            0
-        } else {
-            (actual * 100 / max) as u32
+        let actual = match trusted_value(&self.last_write) {
+            Some(value) => value,
+            None => read_value(&self.device, Value::Actual)?,
         };
-        Ok(percentage)
+        Ok(raw_to_percentage(actual, max, self.mapping.as_ref()))
     }
 
     fn set(&self, percentage: u32) -> Result<(), Error> {
+        if self.read_only {
+            return Err(SysError::DeviceReadOnly {
+                device: self.device.clone(),
+            }
+            .into());
+        }
         let percentage = percentage.min(100);
         let max = read_value(&self.device, Value::Max)?;
-        let desired_value = (u64::from(percentage) * u64::from(max) / 100) as u32;
-        let desired = ("backlight", &self.device, desired_value);
-        let bus =
-            zbus::blocking::Connection::system().map_err(|e| Error::SettingBrightnessFailed {
-                device: self.device.clone(),
-                source: e.into(),
-            })?;
-        let response = bus.call_method(
-            Some(USER_DBUS_NAME),
-            SESSION_OBJECT_PATH,
-            Some(SESSION_INTERFACE),
-            SET_BRIGHTNESS_METHOD,
-            &desired,
-        );
-        match response {
-            Ok(_) => Ok(()),
-            Err(zbus::Error::MethodError(..)) => {
-                // Setting brightness through dbus may not work on older systems that don't have
-                // the `SetBrightness` method. Fall back to writing to the brightness file (which
-                // requires permission).
-                set_value(&self.device, desired_value)?;
-                Ok(())
+        let desired_value = percentage_to_raw(percentage, max, self.mapping.as_ref());
+        if self.use_gnome_settings_daemon
+            && is_internal_panel(&self.device)?
+            && set_with_gnome_settings_daemon(gsd_percentage(
+                percentage,
+                desired_value,
+                max,
+                self.mapping.as_ref(),
+            ))
+            .is_ok()
+        {
+            record_write(&self.last_write, desired_value);
+            return Ok(());
+        }
+        if !set_with_logind(
+            &self.device,
+            desired_value,
+            self.system_connection.as_ref(),
+            self.logind_timeout,
+        )? {
+            // Setting brightness through dbus may not work on older systems that don't have the
+            // `SetBrightness` method. Fall back to writing to the brightness file (which requires
+            // permission).
+            set_value(&self.device, desired_value, self.use_pkexec)?;
+            if self.verify_writes {
+                verify_value(&self.device, desired_value)?;
             }
-            Err(e) => Err(Error::SettingBrightnessFailed {
-                device: self.device.clone(),
-                source: e.into(),
-            }),
         }
+        record_write(&self.last_write, desired_value);
+        Ok(())
+    }
+
+    fn edid(&self) -> Result<Option<EdidInfo>, Error> {
+        Ok(read_edid(&self.device)?.and_then(|edid| EdidInfo::parse(&edid)))
+    }
+
+    fn is_primary(&self) -> Result<bool, Error> {
+        Ok(is_primary(&self.device, &self.primary)?)
+    }
+
+    fn kind(&self) -> Result<DeviceKind, Error> {
+        Ok(device_kind(&self.device)?)
+    }
+
+    fn raw_capabilities(&self) -> Result<Option<String>, Error> {
+        Ok(read_capabilities(&self.device)?)
+    }
+
+    fn brightness_raw_max(&self) -> Result<u32, Error> {
+        Ok(read_value(&self.device, Value::Max)?)
     }
 }
 
 pub(crate) fn brightness_devices() -> impl Iterator<Item = Result<BlockingDeviceImpl, SysError>> {
+    let primary = SharedPrimary::default();
     match fs::read_dir(BACKLIGHT_DIR) {
         Ok(devices) => Either::Left(
             devices
-                .map(|device| {
+                .map(move |device| {
                     let device = device.map_err(SysError::ReadingBacklightDirFailed)?;
-                    let path = device.path();
-                    let keep = path.join(Value::Actual.as_str()).exists()
-                        && path.join(Value::Max.as_str()).exists();
+                    let keep = missing_values(&device.path()).is_empty();
                     Ok(device
                         .file_name()
                         .into_string()
                         .ok()
-                        .map(|device| BlockingDeviceImpl { device })
+                        .map(|device| BlockingDeviceImpl {
+                            device,
+                            last_write: Default::default(),
+                            use_pkexec: false,
+                            use_gnome_settings_daemon: false,
+                            read_only: false,
+                            verify_writes: false,
+                            logind_timeout: LOGIND_TIMEOUT,
+                            system_connection: None,
+                            mapping: None,
+                            primary: primary.clone(),
+                        })
                         .filter(|_| keep))
                 })
                 .filter_map(Result::transpose),
@@ -89,6 +307,108 @@ pub(crate) fn brightness_devices() -> impl Iterator<Item = Result<BlockingDevice
     }
 }
 
+/// Returns the backlight devices for `brightness_devices_retry`, which are listed as usual.
+pub(crate) fn brightness_devices_for_retry(
+) -> impl Iterator<Item = Result<BlockingDeviceImpl, SysError>> {
+    brightness_devices()
+}
+
+/// Returns the identity of all backlight devices.
+///
+/// This only reads sysfs, so it is as cheap as listing the devices.
+pub(crate) fn list_devices() -> Result<Vec<DeviceInfo>, SysError> {
+    brightness_devices()
+        .map(|device| device_info(&device?.device))
+        .collect()
+}
+
+/// Returns the identity of a backlight device.
+pub(crate) fn device_info(device: &str) -> Result<DeviceInfo, SysError> {
+    Ok(DeviceInfo {
+        name: device.to_owned(),
+        path: [BACKLIGHT_DIR, device]
+            .iter()
+            .collect::<PathBuf>()
+            .to_string_lossy()
+            .into_owned(),
+        kind: device_kind(device)?,
+    })
+}
+
+/// Backlight device found in sysfs, with the reasons it is excluded from the brightness devices.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct BacklightDiagnostic {
+    /// Device name, lossily converted if it is not valid UTF-8
+    pub name: String,
+    /// sysfs directory of the device
+    pub path: PathBuf,
+    /// Reasons the device is excluded, empty if it is listed by `brightness_devices`
+    pub exclusions: Vec<Exclusion>,
+}
+
+/// Reason a backlight device is excluded from the brightness devices.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Exclusion {
+    /// A file required to read the brightness is missing (e.g. `max_brightness`)
+    MissingFile(&'static str),
+    /// The device name is not valid UTF-8
+    InvalidName,
+}
+
+impl fmt::Display for Exclusion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Exclusion::MissingFile(name) => write!(f, "missing {name}"),
+            Exclusion::InvalidName => write!(f, "device name is not valid UTF-8"),
+        }
+    }
+}
+
+/// Returns all entries of the backlight class directory, including the devices that
+/// `brightness_devices` skips, with the reasons they are skipped.
+///
+/// This is meant for troubleshooting, e.g. to tell users why no device is found.
+pub fn diagnose_devices() -> Result<Vec<BacklightDiagnostic>, Error> {
+    let entries = fs::read_dir(BACKLIGHT_DIR).map_err(SysError::ReadingBacklightDirFailed)?;
+    let mut diagnostics = entries
+        .map(|entry| {
+            let entry = entry.map_err(SysError::ReadingBacklightDirFailed)?;
+            let path = entry.path();
+            let mut exclusions = Vec::new();
+            let name = match entry.file_name().into_string() {
+                Ok(name) => name,
+                Err(name) => {
+                    exclusions.push(Exclusion::InvalidName);
+                    name.to_string_lossy().into_owned()
+                }
+            };
+            exclusions.extend(
+                missing_values(&path)
+                    .into_iter()
+                    .map(Exclusion::MissingFile),
+            );
+            Ok(BacklightDiagnostic {
+                name,
+                path,
+                exclusions,
+            })
+        })
+        .collect::<Result<Vec<_>, SysError>>()?;
+    diagnostics.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(diagnostics)
+}
+
+/// Returns the names of the value files missing from a backlight device directory.
+pub(crate) fn missing_values(path: &Path) -> Vec<&'static str> {
+    [Value::Actual, Value::Max]
+        .iter()
+        .map(Value::as_str)
+        .filter(|name| !path.join(name).exists())
+        .collect()
+}
+
 #[derive(Clone, Copy, Debug)]
 pub(crate) enum Value {
     Actual,
@@ -96,7 +416,7 @@ pub(crate) enum Value {
 }
 
 impl Value {
-    pub(crate) fn as_str(&self) -> &str {
+    pub(crate) fn as_str(&self) -> &'static str {
         match self {
             Value::Actual => "actual_brightness",
             Value::Max => "max_brightness",
@@ -120,6 +440,26 @@ pub(crate) enum SysError {
         path: PathBuf,
         reason: String,
     },
+    #[error("Failed to read EDID from {}", .path.display())]
+    ReadingEdidFailed {
+        device: String,
+        path: PathBuf,
+        source: io::Error,
+    },
+    #[error("Failed to watch brightness of backlight device {device}")]
+    WatchingBrightnessFailed { device: String, source: io::Error },
+    #[error("Failed to watch brightness of backlight devices")]
+    WatchingBacklightDevicesFailed(#[source] io::Error),
+    #[error("Backlight device {device} is read-only")]
+    DeviceReadOnly { device: String },
+    #[error("Backlight device {device} applied {applied} instead of {requested}")]
+    SettingBrightnessClamped {
+        device: String,
+        requested: u32,
+        applied: u32,
+    },
+    #[error("Timed out after {timeout:?} waiting for logind to set brightness of {device}")]
+    LogindTimedOut { device: String, timeout: Duration },
     #[error("Failed to write brightness to {}", .path.display())]
     WritingBrightnessFailed {
         device: String,
@@ -128,18 +468,59 @@ pub(crate) enum SysError {
     },
 }
 
+impl SysError {
+    /// Returns whether listing devices again may succeed.
+    ///
+    /// Listing backlight devices only reads a directory, which does not fail because of devices
+    /// being added or removed concurrently.
+    pub(crate) fn is_transient(&self) -> bool {
+        false
+    }
+
+    /// Returns the category of the error, derived from the I/O error causing it if any.
+    pub(crate) fn kind(&self) -> ErrorKind {
+        match self {
+            SysError::DeviceReadOnly { .. } => return ErrorKind::Unsupported,
+            SysError::LogindTimedOut { .. } => return ErrorKind::Transient,
+            _ => {}
+        }
+        match self.source().and_then(|s| s.downcast_ref::<io::Error>()) {
+            Some(e) => match e.kind() {
+                io::ErrorKind::Interrupted
+                | io::ErrorKind::WouldBlock
+                | io::ErrorKind::TimedOut => ErrorKind::Transient,
+                io::ErrorKind::Unsupported => ErrorKind::Unsupported,
+                _ => ErrorKind::Other,
+            },
+            None => ErrorKind::Other,
+        }
+    }
+}
+
 impl From<SysError> for Error {
     fn from(e: SysError) -> Self {
         match &e {
-            SysError::ReadingBacklightDirFailed(_) => Error::ListingDevicesFailed(e.into()),
+            SysError::ReadingBacklightDirFailed(_)
+            | SysError::WatchingBacklightDevicesFailed(_) => Error::ListingDevicesFailed(e.into()),
             SysError::ReadingBacklightDeviceFailed { device, .. }
-            | SysError::ParsingBacklightInfoFailed { device, .. } => {
-                Error::GettingDeviceInfoFailed {
-                    device: device.clone(),
-                    source: e.into(),
-                }
-            }
-            SysError::WritingBrightnessFailed { device, .. } => Error::SettingBrightnessFailed {
+            | SysError::ParsingBacklightInfoFailed { device, .. }
+            | SysError::ReadingEdidFailed { device, .. }
+            | SysError::WatchingBrightnessFailed { device, .. } => Error::GettingDeviceInfoFailed {
+                device: device.clone(),
+                source: e.into(),
+            },
+            SysError::SettingBrightnessClamped {
+                device,
+                requested,
+                applied,
+            } => Error::SettingClamped {
+                device: device.clone(),
+                requested: *requested,
+                applied: *applied,
+            },
+            SysError::WritingBrightnessFailed { device, .. }
+            | SysError::LogindTimedOut { device, .. }
+            | SysError::DeviceReadOnly { device } => Error::SettingBrightnessFailed {
                 device: device.clone(),
                 source: e.into(),
             },
@@ -147,6 +528,46 @@ impl From<SysError> for Error {
     }
 }
 
+/// Converts a raw brightness value to a percentage, with the custom mapping of the device if any.
+pub(crate) fn raw_to_percentage(value: u32, max: u32, mapping: Option<&BrightnessMapping>) -> u32 {
+    match mapping {
+        Some(mapping) => mapping.to_percentage(value, 0, max),
+        None => (value * 100).checked_div(max).unwrap_or(0),
+    }
+}
+
+/// Returns the percentage that `set` applies for `requested`, see [`BrightnessExt::preview`].
+pub(crate) fn preview(requested: u32, mapping: Option<&BrightnessMapping>) -> u32 {
+    let percentage = requested.min(100);
+    mapping.map_or(percentage, |mapping| mapping.round_trip(percentage, None))
+}
+
+/// Converts a percentage to a raw brightness value, with the custom mapping of the device if any.
+pub(crate) fn percentage_to_raw(
+    percentage: u32,
+    max: u32,
+    mapping: Option<&BrightnessMapping>,
+) -> u32 {
+    match mapping {
+        Some(mapping) => mapping.to_raw(percentage, 0, max),
+        None => (u64::from(percentage) * u64::from(max) / 100) as u32,
+    }
+}
+
+/// Returns the percentage to send to the GNOME settings daemon, which maps percentages linearly,
+/// so that it applies `value`.
+pub(crate) fn gsd_percentage(
+    percentage: u32,
+    value: u32,
+    max: u32,
+    mapping: Option<&BrightnessMapping>,
+) -> u32 {
+    match mapping {
+        Some(_) => raw_to_percentage(value, max, None),
+        None => percentage,
+    }
+}
+
 /// Reads a backlight device brightness value from the filesystem.
 ///
 /// Note: Even though this makes a call to `std::fs`, we are communicating with a kernel pseudo file
@@ -170,17 +591,899 @@ pub(crate) fn read_value(device: &str, name: Value) -> Result<u32, SysError> {
         })
 }
 
+/// Whether logind provides the `SetBrightness` method, once known.
+static LOGIND_SET_BRIGHTNESS: OnceLock<bool> = OnceLock::new();
+
+/// Returns whether logind provides the `SetBrightness` method, or `None` if this is not known yet.
+///
+/// logind is introspected when brightness is first set, and the result is kept for the lifetime of
+/// the process. Without this method (systemd older than 243), brightness is written directly to the
+/// brightness file of the device.
+pub fn logind_has_set_brightness() -> Option<bool> {
+    LOGIND_SET_BRIGHTNESS.get().copied()
+}
+
+/// Returns whether the introspection data of the logind session lists `SetBrightness`.
+fn lists_set_brightness(introspection: &str) -> bool {
+    introspection.contains(&format!("<method name=\"{}\"", SET_BRIGHTNESS_METHOD))
+}
+
+/// Introspects logind to find whether it provides the `SetBrightness` method, and remembers the
+/// result.
+///
+/// Returns `None` if introspection fails.
+pub(crate) fn probe_logind(bus: &zbus::blocking::Connection) -> Option<bool> {
+    let introspection = bus
+        .call_method(
+            Some(USER_DBUS_NAME),
+            SESSION_OBJECT_PATH,
+            Some(INTROSPECTABLE_INTERFACE),
+            INTROSPECT_METHOD,
+            &(),
+        )
+        .and_then(|reply| reply.body::<String>())
+        .ok()?;
+    Some(*LOGIND_SET_BRIGHTNESS.get_or_init(|| lists_set_brightness(&introspection)))
+}
+
+/// Returns how `set` changes the brightness of a backlight device with the given options.
+pub(crate) fn set_mechanism(
+    device: &str,
+    use_pkexec: bool,
+    use_gnome_settings_daemon: bool,
+    read_only: bool,
+    connection: Option<zbus::blocking::Connection>,
+) -> Result<SetMechanism, Error> {
+    if read_only {
+        return Ok(SetMechanism::ReadOnly);
+    }
+    if use_gnome_settings_daemon && is_internal_panel(device)? {
+        return Ok(SetMechanism::GnomeSettingsDaemon);
+    }
+    let logind = match logind_has_set_brightness() {
+        Some(available) => available,
+        None => {
+            let bus = match connection {
+                Some(connection) => connection,
+                None => zbus::blocking::Connection::system().map_err(|e| {
+                    Error::GettingDeviceInfoFailed {
+                        device: device.to_owned(),
+                        source: e.into(),
+                    }
+                })?,
+            };
+            probe_logind(&bus).unwrap_or(true)
+        }
+    };
+    if logind {
+        return Ok(SetMechanism::Logind);
+    }
+    let path = [BACKLIGHT_DIR, device, "brightness"]
+        .iter()
+        .collect::<PathBuf>();
+    // Opening the file for writing checks permissions without changing the brightness.
+    match fs::OpenOptions::new().write(true).open(&path) {
+        Ok(_) => Ok(SetMechanism::DirectWrite),
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => Ok(if use_pkexec {
+            SetMechanism::Pkexec
+        } else {
+            SetMechanism::PermissionDenied
+        }),
+        Err(source) => Err(SysError::ReadingBacklightDeviceFailed {
+            device: device.into(),
+            path,
+            source,
+        }
+        .into()),
+    }
+}
+
+/// A `SetBrightness` call to be made by the logind worker thread.
+struct LogindCall {
+    bus: zbus::blocking::Connection,
+    device: String,
+    value: u32,
+    reply: mpsc::Sender<zbus::Result<()>>,
+}
+
+/// Sender of the thread making `SetBrightness` calls, started when brightness is first set.
+static LOGIND_WORKER: OnceLock<Mutex<mpsc::Sender<LogindCall>>> = OnceLock::new();
+
+/// Sends a `SetBrightness` call to the logind worker thread and returns the receiver of its reply.
+fn send_to_logind_worker(
+    bus: zbus::blocking::Connection,
+    device: String,
+    value: u32,
+) -> mpsc::Receiver<zbus::Result<()>> {
+    let (reply, receiver) = mpsc::channel();
+    let worker = LOGIND_WORKER.get_or_init(|| {
+        let (sender, calls) = mpsc::channel::<LogindCall>();
+        thread::spawn(move || {
+            for call in calls {
+                let response = call.bus.call_method(
+                    Some(USER_DBUS_NAME),
+                    SESSION_OBJECT_PATH,
+                    Some(SESSION_INTERFACE),
+                    SET_BRIGHTNESS_METHOD,
+                    &("backlight", call.device.as_str(), call.value),
+                );
+                // The caller may have stopped waiting after a timeout.
+                let _ = call.reply.send(response.map(drop));
+            }
+        });
+        Mutex::new(sender)
+    });
+    // The worker never exits, so sending only fails if it panicked, in which case the reply
+    // sender is dropped and the caller sees a disconnected receiver.
+    let call = LogindCall {
+        bus,
+        device,
+        value,
+        reply,
+    };
+    let _ = worker.lock().unwrap().send(call);
+    receiver
+}
+
+/// Sets the brightness of a backlight device through logind.
+///
+/// Returns `false` if logind does not provide the `SetBrightness` method. A connection to the system
+/// bus is opened if none is given.
+fn set_with_logind(
+    device: &str,
+    value: u32,
+    connection: Option<&zbus::blocking::Connection>,
+    timeout: Duration,
+) -> Result<bool, Error> {
+    if logind_has_set_brightness() == Some(false) {
+        return Ok(false);
+    }
+    let to_error = |e: zbus::Error| Error::SettingBrightnessFailed {
+        device: device.to_owned(),
+        source: e.into(),
+    };
+    let bus = match connection {
+        Some(connection) => connection.clone(),
+        None => zbus::blocking::Connection::system().map_err(to_error)?,
+    };
+    // If introspection fails, `SetBrightness` is attempted and will be probed again next time.
+    if logind_has_set_brightness().is_none() && probe_logind(&bus) == Some(false) {
+        return Ok(false);
+    }
+    // The blocking connection has no timeout, so the call is made on a worker thread.
+    let response = send_to_logind_worker(bus, device.to_owned(), value)
+        .recv_timeout(timeout)
+        .map_err(|_| SysError::LogindTimedOut {
+            device: device.to_owned(),
+            timeout,
+        })?;
+    match response {
+        Ok(()) => Ok(true),
+        Err(zbus::Error::MethodError(..)) => Ok(false),
+        Err(e) => Err(to_error(e)),
+    }
+}
+
+/// Sets the brightness of the built-in panel through the GNOME settings daemon.
+fn set_with_gnome_settings_daemon(percentage: u32) -> zbus::Result<()> {
+    let bus = zbus::blocking::Connection::session()?;
+    bus.call_method(
+        Some(GSD_POWER_DBUS_NAME),
+        GSD_POWER_OBJECT_PATH,
+        Some(PROPERTIES_INTERFACE),
+        SET_PROPERTY_METHOD,
+        &(
+            GSD_SCREEN_INTERFACE,
+            BRIGHTNESS_PROPERTY,
+            zbus::zvariant::Value::from(percentage as i32),
+        ),
+    )?;
+    Ok(())
+}
+
 /// Sets the brightness for a backlight device via the filesystem.
 ///
-/// This is a blocking operation that can take approximately 10-100ms depending on the device.
-pub(crate) fn set_value(device: &str, value: u32) -> Result<(), SysError> {
+/// If permission is denied and `use_pkexec` is set, the brightness is written through `pkexec`.
+///
+/// This is a blocking operation that can take approximately 10-100ms depending on the device, or
+/// much longer if the user is asked to authenticate.
+pub(crate) fn set_value(device: &str, value: u32, use_pkexec: bool) -> Result<(), SysError> {
     let path = [BACKLIGHT_DIR, device, "brightness"]
         .iter()
         .collect::<PathBuf>();
-    fs::write(&path, value.to_string()).map_err(|source| SysError::WritingBrightnessFailed {
+    let result = match fs::write(&path, value.to_string()) {
+        Err(e) if use_pkexec && e.kind() == io::ErrorKind::PermissionDenied => {
+            write_with_pkexec(&path, value)
+        }
+        result => result,
+    };
+    result.map_err(|source| SysError::WritingBrightnessFailed {
+        device: device.into(),
+        path,
+        source,
+    })
+}
+
+/// Reads `actual_brightness` back after writing the brightness file, and fails if the driver
+/// applied a different value.
+pub(crate) fn verify_value(device: &str, value: u32) -> Result<(), SysError> {
+    let applied = read_value(device, Value::Actual)?;
+    if applied == value {
+        Ok(())
+    } else {
+        Err(SysError::SettingBrightnessClamped {
+            device: device.into(),
+            requested: value,
+            applied,
+        })
+    }
+}
+
+/// Writes a value to a file by running `tee` through `pkexec`.
+fn write_with_pkexec(path: &Path, value: u32) -> io::Result<()> {
+    let mut child = Command::new(PKEXEC)
+        .arg("tee")
+        .arg(path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()?;
+    // Dropping stdin closes it so that `tee` exits. The child is waited for even if writing
+    // fails, so that it is not left as a zombie.
+    let written = match child.stdin.take() {
+        Some(mut stdin) => stdin.write_all(value.to_string().as_bytes()),
+        None => Ok(()),
+    };
+    let status = child.wait();
+    written?;
+    let status = status?;
+    if status.success() {
+        Ok(())
+    } else {
+        // pkexec exits with 126 if authorization is denied and 127 if the dialog is dismissed.
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} {}", PKEXEC, status),
+        ))
+    }
+}
+
+/// Reads a backlight device attribute that holds text.
+pub(crate) fn read_attribute(device: &str, name: &str) -> Result<String, SysError> {
+    let path = [BACKLIGHT_DIR, device, name].iter().collect::<PathBuf>();
+    fs::read_to_string(&path)
+        .map(|s| s.trim().to_owned())
+        .map_err(|source| SysError::ReadingBacklightDeviceFailed {
+            device: device.into(),
+            path,
+            source,
+        })
+}
+
+/// Returns the DRM connector directory associated with a backlight device.
+///
+/// The connector is found by walking up the device hierarchy of the backlight device. This finds
+/// the connector of panels driven by a DRM driver (e.g. `intel_backlight`) and of ddcci-backlight
+/// devices attached to the I2C bus of a connector, but not of firmware devices like `acpi_video0`.
+pub(crate) fn connector_dir(device: &str) -> Option<PathBuf> {
+    let link = [BACKLIGHT_DIR, device, "device"]
+        .iter()
+        .collect::<PathBuf>();
+    let mut dir = fs::canonicalize(link).ok()?;
+    while dir.starts_with(SYS_DEVICES_DIR) && dir != Path::new(SYS_DEVICES_DIR) {
+        if dir.join("edid").is_file() {
+            return Some(dir);
+        }
+        dir.pop();
+    }
+    None
+}
+
+/// Returns whether a backlight device controls the built-in panel of a laptop.
+///
+/// Firmware and platform devices always control the built-in panel. Raw devices control it if
+/// they are associated with an eDP, LVDS or DSI connector.
+pub(crate) fn is_internal_panel(device: &str) -> Result<bool, SysError> {
+    if let "firmware" | "platform" = read_attribute(device, "type")?.as_str() {
+        return Ok(true);
+    }
+    let connector = match connector_dir(device) {
+        Some(dir) => dir,
+        None => return Ok(false),
+    };
+    let name = connector.file_name().unwrap_or_default().to_string_lossy();
+    Ok(["-eDP-", "-LVDS-", "-DSI-"]
+        .iter()
+        .any(|kind| name.contains(kind)))
+}
+
+/// Primary display shared by the devices of an enumeration, found when first needed.
+pub(crate) type SharedPrimary = Arc<OnceLock<Option<String>>>;
+
+/// Returns whether a backlight device is the primary display, see [`find_primary`].
+pub(crate) fn is_primary(device: &str, primary: &SharedPrimary) -> Result<bool, SysError> {
+    let primary = match primary.get() {
+        Some(primary) => primary,
+        None => {
+            let found = find_primary()?;
+            primary.get_or_init(|| found)
+        }
+    };
+    Ok(primary.as_deref() == Some(device))
+}
+
+/// Returns the backlight device controlling the built-in panel, which is the primary display.
+///
+/// A panel often has several devices (e.g. `acpi_video0` and `intel_backlight`). The raw device of
+/// its eDP, LVDS or DSI connector is preferred, firmware and platform devices being used only if
+/// there is none. `None` is returned if there is no built-in panel or if the choice is ambiguous.
+fn find_primary() -> Result<Option<String>, SysError> {
+    let mut panels = Vec::new();
+    for entry in fs::read_dir(BACKLIGHT_DIR).map_err(SysError::ReadingBacklightDirFailed)? {
+        let entry = entry.map_err(SysError::ReadingBacklightDirFailed)?;
+        let name = match entry.file_name().into_string() {
+            Ok(name) if missing_values(&entry.path()).is_empty() => name,
+            _ => continue,
+        };
+        if is_internal_panel(&name)? {
+            let is_raw = read_attribute(&name, "type")? == "raw";
+            panels.push((name, is_raw));
+        }
+    }
+    Ok(select_primary(panels))
+}
+
+/// Returns the only raw device among built-in panel devices, or the only device if none is raw.
+fn select_primary(panels: Vec<(String, bool)>) -> Option<String> {
+    let (raw, other): (Vec<_>, Vec<_>) = panels.into_iter().partition(|&(_, is_raw)| is_raw);
+    let mut candidates = if raw.is_empty() { other } else { raw };
+    match candidates.len() {
+        1 => candidates.pop().map(|(name, _)| name),
+        _ => None,
+    }
+}
+
+/// Returns the kind of a backlight device.
+///
+/// Devices that are not built-in panels are controlled through DDC/CI if their parent device is on
+/// the `ddcci` bus, which is the case for ddcci-backlight devices. Parents on an I2C bus are also
+/// considered DDC/CI monitors.
+pub(crate) fn device_kind(device: &str) -> Result<DeviceKind, SysError> {
+    if is_internal_panel(device)? {
+        return Ok(DeviceKind::InternalPanel);
+    }
+    let subsystem = [BACKLIGHT_DIR, device, "device", "subsystem"]
+        .iter()
+        .collect::<PathBuf>();
+    Ok(match fs::read_link(subsystem) {
+        Ok(path) if path.ends_with("ddcci") || path.ends_with("i2c") => DeviceKind::ExternalDdc,
+        _ => DeviceKind::Other,
+    })
+}
+
+/// Reads the EDID of the display connector associated with a backlight device.
+///
+/// `None` is returned if no connector is found or if it has no EDID.
+pub(crate) fn read_edid(device: &str) -> Result<Option<Vec<u8>>, SysError> {
+    let path = match connector_dir(device) {
+        Some(dir) => dir.join("edid"),
+        None => return Ok(None),
+    };
+    let edid = fs::read(&path).map_err(|source| SysError::ReadingEdidFailed {
         device: device.into(),
-        path: path.clone(),
+        path,
         source,
     })?;
-    Ok(())
+    // Disconnected connectors have an empty EDID.
+    Ok(Some(edid).filter(|edid| !edid.is_empty()))
+}
+
+/// Returns whether the connector of a backlight device has content-adaptive backlight enabled.
+pub(crate) fn is_adaptive(device: &str) -> Result<bool, SysError> {
+    let path = match connector_dir(device) {
+        Some(dir) => dir.join("amdgpu").join("panel_power_savings"),
+        None => return Ok(false),
+    };
+    match fs::read_to_string(&path) {
+        Ok(level) => Ok(level.trim() != "0"),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(source) => Err(SysError::ReadingBacklightDeviceFailed {
+            device: device.into(),
+            path,
+            source,
+        }),
+    }
+}
+
+/// Returns whether the DRM connector of a backlight device drives a powered display.
+///
+/// `None` is returned if the device has no connector, or if the connector is not disabled and
+/// does not report `dpms`. A connector whose `enabled` attribute is `disabled` is reported as off
+/// regardless of `dpms`, which only reflects the power state requested for the connector.
+pub(crate) fn is_powered(device: &str) -> Result<Option<bool>, SysError> {
+    let connector = match connector_dir(device) {
+        Some(dir) => dir,
+        None => return Ok(None),
+    };
+    if read_connector_attribute(device, &connector, "enabled")?.as_deref() == Some("disabled") {
+        return Ok(Some(false));
+    }
+    Ok(read_connector_attribute(device, &connector, "dpms")?.map(|dpms| dpms == "On"))
+}
+
+/// Reads an attribute of a DRM connector, or returns `None` if the connector does not have it.
+fn read_connector_attribute(
+    device: &str,
+    connector: &Path,
+    name: &str,
+) -> Result<Option<String>, SysError> {
+    let path = connector.join(name);
+    match fs::read_to_string(&path) {
+        Ok(value) => Ok(Some(value.trim().to_owned())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(source) => Err(SysError::ReadingBacklightDeviceFailed {
+            device: device.into(),
+            path,
+            source,
+        }),
+    }
+}
+
+/// Reads the scale of a backlight device, which is only reported by some drivers.
+pub(crate) fn read_scale(device: &str) -> Result<BacklightScale, SysError> {
+    match read_attribute(device, "scale") {
+        Ok(scale) => Ok(match scale.as_str() {
+            "linear" => BacklightScale::Linear,
+            "non-linear" => BacklightScale::NonLinear,
+            _ => BacklightScale::Unknown,
+        }),
+        Err(SysError::ReadingBacklightDeviceFailed { source, .. })
+            if source.kind() == io::ErrorKind::NotFound =>
+        {
+            Ok(BacklightScale::Unknown)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Reads the DDC/CI capabilities string of a backlight device.
+///
+/// The ddcci driver exposes the capabilities of the monitor on the parent device of ddcci-backlight
+/// devices. `None` is returned for other devices.
+pub(crate) fn read_capabilities(device: &str) -> Result<Option<String>, SysError> {
+    let path = [BACKLIGHT_DIR, device, "device", "capabilities"]
+        .iter()
+        .collect::<PathBuf>();
+    match fs::read_to_string(&path) {
+        Ok(capabilities) => Ok(Some(capabilities.trim().to_owned())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(source) => Err(SysError::ReadingBacklightDeviceFailed {
+            device: device.into(),
+            path,
+            source,
+        }),
+    }
+}
+
+/// Brightness change of a backlight device.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct BrightnessChange {
+    /// Device name
+    pub device: String,
+    /// New brightness as a percentage
+    pub percentage: u32,
+}
+
+/// Blocking function that returns the brightness changes of all backlight devices.
+///
+/// See [`crate::linux::brightness_changes`]. The iterator blocks until the next change.
+pub fn brightness_changes() -> Result<impl Iterator<Item = Result<BrightnessChange, Error>>, Error>
+{
+    brightness_changes_coalesced(Duration::ZERO)
+}
+
+/// Blocking function that returns the brightness changes of all backlight devices once they
+/// settle.
+///
+/// See [`crate::linux::brightness_changes_coalesced`]. The iterator blocks until the next change.
+pub fn brightness_changes_coalesced(
+    window: Duration,
+) -> Result<impl Iterator<Item = Result<BrightnessChange, Error>>, Error> {
+    brightness_changes_with_mappings(window, HashMap::new())
+}
+
+/// Blocking function that returns the brightness changes of all backlight devices once they
+/// settle, using the custom mappings of the devices.
+///
+/// See [`crate::linux::brightness_changes_with_mappings`]. The iterator blocks until the next
+/// change.
+pub fn brightness_changes_with_mappings(
+    window: Duration,
+    mappings: HashMap<String, BrightnessMapping>,
+) -> Result<impl Iterator<Item = Result<BrightnessChange, Error>>, Error> {
+    let (mut inotify, devices) = watch_all_brightness()?;
+    let mut coalescer = Coalescer::new(window);
+    let mut pending = VecDeque::<String>::new();
+    let mut failed = false;
+    Ok(std::iter::from_fn(move || loop {
+        if let Some(device) = pending.pop_front() {
+            let change = read_percentage(&device, mappings.get(&device))
+                .map(|percentage| BrightnessChange { device, percentage })
+                .map_err(Into::into);
+            return Some(change);
+        }
+        if failed {
+            return None;
+        }
+        coalescer.flush(&mut pending);
+        if !pending.is_empty() {
+            continue;
+        }
+        let timeout = coalescer
+            .next_deadline()
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()));
+        match inotify.wait(timeout).and_then(|_| inotify.read_events()) {
+            Ok(descriptors) => coalescer.record(&descriptors, &devices),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+            Err(e) => {
+                failed = true;
+                return Some(Err(SysError::WatchingBacklightDevicesFailed(e).into()));
+            }
+        }
+    }))
+}
+
+/// Devices whose brightness changed, to be reported once it stopped changing for a window of time.
+#[derive(Debug)]
+pub(crate) struct Coalescer {
+    window: Duration,
+    deadlines: Vec<(String, Instant)>,
+}
+
+impl Coalescer {
+    pub(crate) fn new(window: Duration) -> Self {
+        Coalescer {
+            window,
+            deadlines: Vec::new(),
+        }
+    }
+
+    /// Restarts the window of the devices concerned by inotify events.
+    ///
+    /// Writes also modify `actual_brightness`, so a single change is reported per device for a
+    /// batch of events.
+    pub(crate) fn record(&mut self, descriptors: &[i32], devices: &HashMap<i32, String>) {
+        let deadline = Instant::now() + self.window;
+        for device in descriptors.iter().filter_map(|d| devices.get(d)) {
+            match self.deadlines.iter_mut().find(|(name, _)| name == device) {
+                Some((_, d)) => *d = deadline,
+                None => self.deadlines.push((device.clone(), deadline)),
+            }
+        }
+    }
+
+    /// Queues the devices whose window elapsed for a change to be reported.
+    pub(crate) fn flush(&mut self, pending: &mut VecDeque<String>) {
+        let now = Instant::now();
+        let (due, waiting) = std::mem::take(&mut self.deadlines)
+            .into_iter()
+            .partition::<Vec<_>, _>(|&(_, deadline)| deadline <= now);
+        self.deadlines = waiting;
+        pending.extend(due.into_iter().map(|(device, _)| device));
+    }
+
+    /// Returns when the next window elapses, if a device is waiting.
+    pub(crate) fn next_deadline(&self) -> Option<Instant> {
+        self.deadlines.iter().map(|&(_, deadline)| deadline).min()
+    }
+}
+
+/// Watches the brightness of all backlight devices.
+///
+/// Returns the inotify instance and the device associated with each watch descriptor.
+pub(crate) fn watch_all_brightness() -> Result<(Inotify, HashMap<i32, String>), SysError> {
+    let inotify = Inotify::new().map_err(SysError::WatchingBacklightDevicesFailed)?;
+    let mut devices = HashMap::new();
+    for device in brightness_devices() {
+        let device = device?.device;
+        let descriptors = inotify.watch_brightness(&device).map_err(|source| {
+            SysError::WatchingBrightnessFailed {
+                device: device.clone(),
+                source,
+            }
+        })?;
+        devices.extend(descriptors.iter().map(|&d| (d, device.clone())));
+    }
+    Ok((inotify, devices))
+}
+
+/// Reads the brightness of a backlight device as a percentage, like `get` with the given mapping.
+pub(crate) fn read_percentage(
+    device: &str,
+    mapping: Option<&BrightnessMapping>,
+) -> Result<u32, SysError> {
+    let max = read_value(device, Value::Max)?;
+    let actual = read_value(device, Value::Actual)?;
+    Ok(raw_to_percentage(actual, max, mapping))
+}
+
+/// Value written by the last `set` of a device in "trust last write" mode.
+#[derive(Debug)]
+pub(crate) struct LastWrite {
+    watch: BrightnessWatch,
+    value: Option<u32>,
+}
+
+impl LastWrite {
+    pub(crate) fn new(device: &str) -> Result<Self, SysError> {
+        let watch =
+            BrightnessWatch::new(device).map_err(|source| SysError::WatchingBrightnessFailed {
+                device: device.into(),
+                source,
+            })?;
+        Ok(LastWrite { watch, value: None })
+    }
+}
+
+/// Returns the last written brightness value if it can still be trusted, i.e. if the brightness
+/// has not been changed by someone else since.
+pub(crate) fn trusted_value(last_write: &Mutex<Option<LastWrite>>) -> Option<u32> {
+    let mut last_write = last_write.lock().unwrap();
+    let last_write = last_write.as_mut()?;
+    // Errors reading the watch are treated as an external change so that the device is read.
+    if last_write.watch.changed().unwrap_or(true) {
+        last_write.value = None;
+    }
+    last_write.value
+}
+
+/// Records a brightness value written by this crate.
+pub(crate) fn record_write(last_write: &Mutex<Option<LastWrite>>, value: u32) {
+    if let Some(last_write) = last_write.lock().unwrap().as_mut() {
+        // Discard the events caused by this write.
+        let _ = last_write.watch.changed();
+        last_write.value = Some(value);
+    }
+}
+
+/// A non-blocking inotify instance watching files for modifications.
+#[derive(Debug)]
+pub(crate) struct Inotify(fs::File);
+
+impl Inotify {
+    pub(crate) fn new() -> io::Result<Self> {
+        let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // The file takes ownership of the descriptor so that it gets closed on drop.
+        Ok(Inotify(unsafe { fs::File::from_raw_fd(fd) }))
+    }
+
+    /// Watches the brightness files of a backlight device and returns the watch descriptors.
+    ///
+    /// Writes to `brightness` and hardware changes signaled on `actual_brightness` both generate
+    /// events. Devices with a `brightness_hw_changed` attribute also signal hardware changes (e.g.
+    /// brightness hotkeys handled by the firmware) on it, so it is watched when present.
+    pub(crate) fn watch_brightness(&self, device: &str) -> io::Result<Vec<i32>> {
+        let hw_changed = [BACKLIGHT_DIR, device, HW_CHANGED]
+            .iter()
+            .collect::<PathBuf>();
+        let names = ["brightness", Value::Actual.as_str()];
+        names
+            .iter()
+            .copied()
+            .chain(hw_changed.exists().then_some(HW_CHANGED))
+            .map(|name| {
+                let path = [BACKLIGHT_DIR, device, name].iter().collect::<PathBuf>();
+                let path = CString::new(path.as_os_str().as_bytes())
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+                let descriptor = unsafe {
+                    libc::inotify_add_watch(self.as_raw_fd(), path.as_ptr(), libc::IN_MODIFY)
+                };
+                if descriptor < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(descriptor)
+            })
+            .collect()
+    }
+
+    /// Blocks until events are pending or the timeout elapses.
+    fn wait(&self, timeout: Option<Duration>) -> io::Result<()> {
+        let mut fd = libc::pollfd {
+            fd: self.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // Rounded up so that the timeout is not zero until it has fully elapsed.
+        let timeout = timeout.map_or(-1, |t| {
+            t.as_nanos().div_ceil(1_000_000).min(i32::MAX as u128) as i32
+        });
+        loop {
+            if unsafe { libc::poll(&mut fd, 1, timeout) } >= 0 {
+                return Ok(());
+            }
+            let e = io::Error::last_os_error();
+            if e.kind() != io::ErrorKind::Interrupted {
+                return Err(e);
+            }
+        }
+    }
+
+    /// Consumes pending events and returns the watch descriptors they concern.
+    ///
+    /// An error of kind `WouldBlock` is returned if there are no pending events.
+    pub(crate) fn read_events(&mut self) -> io::Result<Vec<i32>> {
+        // Events consist of a header followed by a file name, which is empty for watched files.
+        const HEADER_LEN: usize = size_of::<libc::inotify_event>();
+        let mut buffer = [0u8; 1024];
+        let mut descriptors = Vec::new();
+        loop {
+            let len = match self.0.read(&mut buffer) {
+                Ok(len) => len,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock && !descriptors.is_empty() => {
+                    return Ok(descriptors)
+                }
+                Err(e) => return Err(e),
+            };
+            let mut events = &buffer[..len];
+            while events.len() >= HEADER_LEN {
+                let event =
+                    unsafe { ptr::read_unaligned(events.as_ptr() as *const libc::inotify_event) };
+                descriptors.push(event.wd);
+                events = events
+                    .get(HEADER_LEN + event.len as usize..)
+                    .unwrap_or_default();
+            }
+        }
+    }
+}
+
+impl AsRawFd for Inotify {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+/// An inotify watch on the brightness files of a backlight device.
+#[derive(Debug)]
+struct BrightnessWatch(Inotify);
+
+impl BrightnessWatch {
+    fn new(device: &str) -> io::Result<Self> {
+        let inotify = Inotify::new()?;
+        inotify.watch_brightness(device)?;
+        Ok(BrightnessWatch(inotify))
+    }
+
+    /// Consumes pending events and returns whether there were any.
+    fn changed(&mut self) -> io::Result<bool> {
+        match self.0.read_events() {
+            Ok(_) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl BrightnessExt for BrightnessDevice {
+    fn set_trust_last_write(&mut self, trust: bool) -> Result<(), Error> {
+        let last_write = if trust {
+            Some(LastWrite::new(&self.0.device)?)
+        } else {
+            None
+        };
+        *self.0.last_write.get_mut().unwrap() = last_write;
+        Ok(())
+    }
+
+    fn set_use_pkexec(&mut self, enabled: bool) {
+        self.0.use_pkexec = enabled;
+    }
+
+    fn set_use_gnome_settings_daemon(&mut self, enabled: bool) {
+        self.0.use_gnome_settings_daemon = enabled;
+    }
+
+    fn set_read_only(&mut self, read_only: bool) {
+        self.0.read_only = read_only;
+    }
+
+    fn set_verify_writes(&mut self, enabled: bool) {
+        self.0.verify_writes = enabled;
+    }
+
+    fn set_logind_timeout(&mut self, timeout: Duration) {
+        self.0.logind_timeout = timeout;
+    }
+
+    fn set_system_connection(&mut self, connection: zbus::Connection) {
+        self.0.system_connection = Some(connection.into());
+    }
+
+    fn set_mapping(&mut self, mapping: Option<BrightnessMapping>) {
+        self.0.mapping = mapping;
+    }
+
+    fn preview(&self, requested: u32) -> u32 {
+        preview(requested, self.0.mapping.as_ref())
+    }
+
+    fn is_adaptive(&self) -> Result<bool, Error> {
+        Ok(is_adaptive(&self.0.device)?)
+    }
+
+    fn is_powered(&self) -> Result<Option<bool>, Error> {
+        Ok(is_powered(&self.0.device)?)
+    }
+
+    fn scale(&self) -> Result<BacklightScale, Error> {
+        Ok(read_scale(&self.0.device)?)
+    }
+
+    fn set_mechanism(&self) -> Result<SetMechanism, Error> {
+        set_mechanism(
+            &self.0.device,
+            self.0.use_pkexec,
+            self.0.use_gnome_settings_daemon,
+            self.0.read_only,
+            self.0.system_connection.clone(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selects_raw_device_of_built_in_panel_as_primary() {
+        let panels = |devices: &[(&str, bool)]| {
+            let devices = devices
+                .iter()
+                .map(|&(name, is_raw)| (name.to_owned(), is_raw));
+            select_primary(devices.collect())
+        };
+        assert_eq!(
+            panels(&[("acpi_video0", false), ("intel_backlight", true)]).as_deref(),
+            Some("intel_backlight")
+        );
+        assert_eq!(
+            panels(&[("acpi_video0", false)]).as_deref(),
+            Some("acpi_video0")
+        );
+        assert_eq!(panels(&[]), None);
+        // Ambiguous choices have no primary display.
+        assert_eq!(
+            panels(&[("acpi_video0", false), ("acpi_video1", false)]),
+            None
+        );
+        assert_eq!(
+            panels(&[
+                ("amdgpu_bl0", true),
+                ("amdgpu_bl1", true),
+                ("acpi_video0", false)
+            ]),
+            None
+        );
+    }
+
+    #[test]
+    fn preview_clamps_to_maximum() {
+        assert_eq!(preview(0, None), 0);
+        assert_eq!(preview(60, None), 60);
+        assert_eq!(preview(150, None), 100);
+    }
+
+    #[test]
+    fn preview_round_trips_through_mapping() {
+        // Percentages snap to the closest of the 11 raw values.
+        let mapping = BrightnessMapping::new([(0, 0), (100, 10)]).unwrap();
+        assert_eq!(preview(33, Some(&mapping)), 30);
+        assert_eq!(preview(36, Some(&mapping)), 40);
+        assert_eq!(preview(150, Some(&mapping)), 100);
+        let mapping = BrightnessMapping::new([(0, 0), (40, 50), (60, 50), (100, 100)]).unwrap();
+        assert_eq!(preview(50, Some(&mapping)), 40);
+    }
 }
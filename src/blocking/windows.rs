@@ -2,47 +2,89 @@
 
 //! Platform-specific implementation for Windows.
 
-use crate::{blocking::BrightnessDevice, Error};
+use crate::{
+    blocking::BrightnessDevice,
+    capabilities::Capabilities,
+    edid::{DisplayMode, EdidInfo},
+    levels::select_level,
+    BrightnessMapping, DeviceInfo, DeviceKind, Error, ErrorKind,
+};
 use itertools::Either;
 use std::{
     collections::HashMap,
+    error::Error as StdError,
     ffi::{c_void, OsString},
     fmt,
     iter::once,
     mem::size_of,
     os::windows::ffi::OsStringExt,
     ptr,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicU8, Ordering},
+        RwLock, RwLockReadGuard,
+    },
+    time::{Duration, Instant},
 };
 use windows::{
     core::{Error as WinError, PCWSTR},
     Win32::{
         Devices::Display::{
-            DestroyPhysicalMonitor, DisplayConfigGetDeviceInfo, GetDisplayConfigBufferSizes,
-            GetMonitorBrightness, GetNumberOfPhysicalMonitorsFromHMONITOR,
-            GetPhysicalMonitorsFromHMONITOR, QueryDisplayConfig, SetMonitorBrightness,
-            DISPLAYCONFIG_DEVICE_INFO_GET_TARGET_NAME, DISPLAYCONFIG_MODE_INFO,
-            DISPLAYCONFIG_MODE_INFO_TYPE_TARGET, DISPLAYCONFIG_OUTPUT_TECHNOLOGY_INTERNAL,
-            DISPLAYCONFIG_PATH_INFO, DISPLAYCONFIG_TARGET_DEVICE_NAME,
-            DISPLAYCONFIG_VIDEO_OUTPUT_TECHNOLOGY, DISPLAYPOLICY_AC, DISPLAYPOLICY_DC,
-            DISPLAY_BRIGHTNESS, IOCTL_VIDEO_QUERY_DISPLAY_BRIGHTNESS,
-            IOCTL_VIDEO_QUERY_SUPPORTED_BRIGHTNESS, IOCTL_VIDEO_SET_DISPLAY_BRIGHTNESS,
-            PHYSICAL_MONITOR,
+            CapabilitiesRequestAndCapabilitiesReply, DestroyPhysicalMonitor,
+            DisplayConfigGetDeviceInfo, GetCapabilitiesStringLength, GetDisplayConfigBufferSizes,
+            GetMonitorBrightness, GetMonitorCapabilities, GetMonitorColorTemperature,
+            GetNumberOfPhysicalMonitorsFromHMONITOR, GetPhysicalMonitorsFromHMONITOR,
+            GetVCPFeatureAndVCPFeatureReply, QueryDisplayConfig, SetMonitorBrightness,
+            SetMonitorColorTemperature, SetVCPFeature,
+            DISPLAYCONFIG_DEVICE_INFO_GET_ADVANCED_COLOR_INFO,
+            DISPLAYCONFIG_DEVICE_INFO_GET_TARGET_NAME, DISPLAYCONFIG_GET_ADVANCED_COLOR_INFO,
+            DISPLAYCONFIG_MODE_INFO, DISPLAYCONFIG_MODE_INFO_TYPE_SOURCE,
+            DISPLAYCONFIG_MODE_INFO_TYPE_TARGET, DISPLAYCONFIG_OUTPUT_TECHNOLOGY_COMPOSITE_VIDEO,
+            DISPLAYCONFIG_OUTPUT_TECHNOLOGY_DISPLAYPORT_EMBEDDED,
+            DISPLAYCONFIG_OUTPUT_TECHNOLOGY_DISPLAYPORT_EXTERNAL,
+            DISPLAYCONFIG_OUTPUT_TECHNOLOGY_DISPLAYPORT_USB_TUNNEL,
+            DISPLAYCONFIG_OUTPUT_TECHNOLOGY_DVI, DISPLAYCONFIG_OUTPUT_TECHNOLOGY_HD15,
+            DISPLAYCONFIG_OUTPUT_TECHNOLOGY_HDMI, DISPLAYCONFIG_OUTPUT_TECHNOLOGY_INTERNAL,
+            DISPLAYCONFIG_OUTPUT_TECHNOLOGY_OTHER, DISPLAYCONFIG_PATH_INFO,
+            DISPLAYCONFIG_TARGET_DEVICE_NAME, DISPLAYCONFIG_VIDEO_OUTPUT_TECHNOLOGY,
+            DISPLAYPOLICY_AC, DISPLAYPOLICY_DC, DISPLAY_BRIGHTNESS,
+            IOCTL_VIDEO_QUERY_DISPLAY_BRIGHTNESS, IOCTL_VIDEO_QUERY_SUPPORTED_BRIGHTNESS,
+            IOCTL_VIDEO_SET_DISPLAY_BRIGHTNESS, MC_COLOR_TEMPERATURE, MC_COLOR_TEMPERATURE_10000K,
+            MC_COLOR_TEMPERATURE_11500K, MC_COLOR_TEMPERATURE_4000K, MC_COLOR_TEMPERATURE_5000K,
+            MC_COLOR_TEMPERATURE_6500K, MC_COLOR_TEMPERATURE_7500K, MC_COLOR_TEMPERATURE_8200K,
+            MC_COLOR_TEMPERATURE_9300K, MC_MOMENTARY, MC_SUPPORTED_COLOR_TEMPERATURE_10000K,
+            MC_SUPPORTED_COLOR_TEMPERATURE_11500K, MC_SUPPORTED_COLOR_TEMPERATURE_4000K,
+            MC_SUPPORTED_COLOR_TEMPERATURE_5000K, MC_SUPPORTED_COLOR_TEMPERATURE_6500K,
+            MC_SUPPORTED_COLOR_TEMPERATURE_7500K, MC_SUPPORTED_COLOR_TEMPERATURE_8200K,
+            MC_SUPPORTED_COLOR_TEMPERATURE_9300K, MC_VCP_CODE_TYPE, PHYSICAL_MONITOR,
         },
         Foundation::{
-            CloseHandle, BOOL, ERROR_ACCESS_DENIED, ERROR_SUCCESS, HANDLE, LPARAM, RECT,
-            WIN32_ERROR,
+            CloseHandle, BOOL, ERROR_ACCESS_DENIED, ERROR_FILE_NOT_FOUND,
+            ERROR_GRAPHICS_DDCCI_INVALID_DATA, ERROR_GRAPHICS_DDCCI_INVALID_MESSAGE_CHECKSUM,
+            ERROR_GRAPHICS_DDCCI_INVALID_MESSAGE_COMMAND,
+            ERROR_GRAPHICS_DDCCI_INVALID_MESSAGE_LENGTH,
+            ERROR_GRAPHICS_DDCCI_MONITOR_RETURNED_INVALID_TIMING_STATUS_BYTE,
+            ERROR_GRAPHICS_DDCCI_VCP_NOT_SUPPORTED, ERROR_GRAPHICS_I2C_DEVICE_DOES_NOT_EXIST,
+            ERROR_GRAPHICS_I2C_ERROR_RECEIVING_DATA, ERROR_GRAPHICS_I2C_ERROR_TRANSMITTING_DATA,
+            ERROR_GRAPHICS_I2C_NOT_SUPPORTED, ERROR_GRAPHICS_INVALID_PHYSICAL_MONITOR_HANDLE,
+            ERROR_INSUFFICIENT_BUFFER, ERROR_MORE_DATA, ERROR_NOT_SUPPORTED, ERROR_SUCCESS,
+            E_HANDLE, HANDLE, LPARAM, LUID, POINT, RECT, WIN32_ERROR,
         },
         Graphics::Gdi::{
-            EnumDisplayDevicesW, EnumDisplayMonitors, GetMonitorInfoW, DISPLAY_DEVICEW,
-            DISPLAY_DEVICE_ACTIVE, HDC, HMONITOR, MONITORINFO, MONITORINFOEXW,
-            QDC_ONLY_ACTIVE_PATHS,
+            EnumDisplayDevicesW, EnumDisplayMonitors, GetMonitorInfoW, MonitorFromPoint,
+            DISPLAY_DEVICEW, DISPLAY_DEVICE_ACTIVE, HDC, HMONITOR, MONITORINFO, MONITORINFOEXW,
+            MONITOR_DEFAULTTONULL, QDC_ONLY_ACTIVE_PATHS,
         },
         Storage::FileSystem::{
             CreateFileW, FILE_GENERIC_READ, FILE_GENERIC_WRITE, FILE_SHARE_READ, FILE_SHARE_WRITE,
             OPEN_EXISTING,
         },
-        System::IO::DeviceIoControl,
-        UI::WindowsAndMessaging::EDD_GET_DEVICE_INTERFACE_NAME,
+        System::{
+            Registry::{RegGetValueW, HKEY_LOCAL_MACHINE, RRF_RT_REG_BINARY},
+            IO::DeviceIoControl,
+        },
+        UI::WindowsAndMessaging::{
+            GetCursorPos, EDD_GET_DEVICE_INTERFACE_NAME, MONITORINFOF_PRIMARY,
+        },
     },
 };
 
@@ -56,15 +98,462 @@ pub trait BrightnessExt {
 
     /// Returns the device path
     fn device_path(&self) -> Result<String, Error>;
+
+    /// Returns the device instance ID of the monitor (e.g. `DISPLAY\DEL40F4\5&2a4b3b3c&0&UID4353`).
+    ///
+    /// The instance ID is parsed from the device path. It identifies the monitor on the connector
+    /// it is plugged into, and does not change when displays are reordered, so unlike
+    /// `device_name` it can be used to remember settings for a monitor whose EDID has no serial
+    /// number.
+    fn instance_id(&self) -> Result<String, Error>;
+
+    /// Returns the friendly name of the monitor (e.g. "DELL U2720Q"), which is usually empty for
+    /// internal displays.
+    ///
+    /// This is meant to be shown to users. Unlike `device_name`, it is not unique and may change
+    /// with driver updates, so it should not be used to identify a device.
+    fn friendly_name(&self) -> Result<String, Error>;
+
+    /// Returns the GDI name of the display the monitor is attached to (e.g. `\\.\DISPLAY1`).
+    ///
+    /// This is the name used by GDI functions such as `ChangeDisplaySettingsExW` and found in
+    /// `MONITORINFOEXW`. `device_name` returns the name of the monitor device itself, which has
+    /// this name as a prefix (e.g. `\\.\DISPLAY1\Monitor0`).
+    fn gdi_device_name(&self) -> Result<String, Error>;
+
+    /// Returns the number of the display the monitor is attached to (e.g. 2 for `\\.\DISPLAY2`).
+    ///
+    /// This is the number shown for the display by the Windows display settings ("Display 2") in
+    /// usual configurations. How the settings number displays is not documented, so they may
+    /// differ, e.g. after adapters are added or removed.
+    fn display_number(&self) -> Result<u32, Error>;
+
+    /// Returns the technology of the connection to the monitor
+    fn output_technology(&self) -> Result<OutputTechnology, Error>;
+
+    /// Returns the locally unique identifier (LUID) of the display adapter (GPU) driving the
+    /// monitor, as a 64-bit value.
+    ///
+    /// The low 32 bits are `LUID.LowPart` and the high 32 bits are `LUID.HighPart`, which allows
+    /// correlating devices with other GPU APIs such as DXGI.
+    fn adapter_luid(&self) -> Result<u64, Error>;
+
+    /// Returns the raw `HMONITOR` handle value of the display monitor the device belongs to.
+    ///
+    /// Monitors in duplicate (mirror) mode share the same `HMONITOR`.
+    fn hmonitor(&self) -> Result<isize, Error>;
+
+    /// Returns whether the monitor responds to DDC/CI requests.
+    ///
+    /// This reads the brightness VCP feature as a probe, retrying once if it fails, as DDC/CI
+    /// communication is unreliable. `false` is returned for internal displays and for monitors that
+    /// do not respond to either read, which usually means that DDC/CI is disabled in the monitor's
+    /// on-screen menu. An error is returned if the monitor has no DDC/CI channel at all, and an
+    /// error of kind [`ErrorKind::Transient`] if it answers with corrupted data.
+    ///
+    /// No timeout is applied: each read blocks for as long as Windows waits for the monitor.
+    fn ddcci_enabled(&self) -> Result<bool, Error>;
+
+    /// Returns whether the device looks like a ghost monitor.
+    ///
+    /// Windows sometimes keeps reporting monitors that were disconnected, which fail every
+    /// brightness operation. An external monitor is considered a ghost if its output technology is
+    /// unknown, or if no EDID is stored for it and it does not respond to DDC/CI. Monitors with an
+    /// EDID are not probed, so this is cheap for most devices. `false` is returned for internal
+    /// displays.
+    fn is_ghost(&self) -> Result<bool, Error>;
+
+    /// Sets whether `set` avoids turning off the backlight of internal displays.
+    ///
+    /// When enabled, the lowest non-zero brightness level supported by an internal display is used
+    /// as a floor, so that setting 0% makes the display as dim as possible without turning it off.
+    /// [`BrightnessExt::set_raw`] can still set a zero level. This has no effect on external
+    /// monitors and is disabled by default.
+    fn set_nonzero_floor(&mut self, enabled: bool);
+
+    /// Sets the lowest percentage that `set` applies, raising lower requests to it.
+    ///
+    /// Some monitors are unreadable at their lowest brightness. This lets applications enforce a
+    /// comfortable minimum, e.g. based on [`BrightnessExt::brightness_raw_min`]. The floor is 0 by
+    /// default.
+    fn set_min_percentage(&mut self, percentage: u32);
+
+    /// Returns the raw value of the lowest brightness, in the native units of the device.
+    ///
+    /// For external monitors, this is the minimum reported by DDC/CI, which 0% maps to. For
+    /// internal displays, this is the lowest supported brightness level.
+    fn brightness_raw_min(&self) -> Result<u32, Error>;
+
+    /// Sets how `set` picks a brightness level on internal displays that only support a few
+    /// levels.
+    ///
+    /// This has no effect on external monitors. [`Rounding::Nearest`] is used by default.
+    fn set_rounding(&mut self, rounding: Rounding);
+
+    /// Sets whether `get` and `set` reopen the monitor and retry once when its handles went stale.
+    ///
+    /// Handles opened before the system sleeps or a monitor is power-cycled may no longer be valid
+    /// afterwards. When enabled, the monitor is found again by its device path and the operation
+    /// is retried with new handles. [`Error::DeviceDisconnected`] is returned if the monitor is
+    /// gone. This is disabled by default, as finding the monitor again opens the handles of all
+    /// monitors.
+    fn set_reopen_on_stale(&mut self, enabled: bool);
+
+    /// Sets a custom mapping between percentages and raw values used by `get` and `set`, or
+    /// restores the default linear mapping with `None`.
+    ///
+    /// See [`BrightnessMapping`]. For internal displays, the mapped level is then rounded to a
+    /// supported level according to [`BrightnessExt::set_rounding`].
+    fn set_mapping(&mut self, mapping: Option<BrightnessMapping>);
+
+    /// Sets whether `set` skips writing the brightness of an external monitor when it already has
+    /// the requested value, within `tolerance` raw DDC/CI units.
+    ///
+    /// `set` reads the brightness of external monitors before writing it, so this does not cost an
+    /// additional read. Skipping no-op writes avoids the flicker some monitors show on every write,
+    /// as well as wear of their non-volatile memory. `Some(0)` only skips writes of the exact
+    /// current value, and `None` always writes, which is the default. This has no effect on
+    /// internal displays.
+    fn set_skip_unchanged(&mut self, tolerance: Option<u32>);
+
+    /// Sets whether `set` reads the brightness of an external monitor back after writing it.
+    ///
+    /// Some monitors clamp the value written to a range narrower than the one they report, and
+    /// still report success. When enabled, [`Error::SettingClamped`] is returned if the value read
+    /// back differs from the value written by more than one raw unit. The brightness was changed
+    /// nonetheless. This is disabled by default, as it costs an additional DDC/CI read. This has no
+    /// effect on internal displays.
+    fn set_verify_writes(&mut self, enabled: bool);
+
+    /// Returns the percentage that `set(requested)` applies, without accessing the device.
+    ///
+    /// The floor set with [`BrightnessExt::set_min_percentage`] and the custom mapping are applied,
+    /// which lets a UI show where a slider snaps before setting brightness. Rounding to the levels
+    /// supported by internal displays is not included, as they are queried from the device. For
+    /// external monitors with a custom mapping, the DDC/CI range is assumed to be that of the
+    /// mapping.
+    fn preview(&self, requested: u32) -> u32;
+
+    /// Sets the brightness to a raw hardware value, bypassing the percentage mapping.
+    ///
+    /// For internal displays, this is a brightness level between 0 and 100 as understood by the
+    /// display driver. For external monitors, this is the DDC/CI brightness value.
+    fn set_raw(&self, value: u32) -> Result<(), Error>;
+
+    /// Returns the color temperature presets supported by the monitor.
+    ///
+    /// This uses the high-level Monitor Configuration API, which parses the monitor capabilities.
+    /// An error is returned for internal displays.
+    fn supported_color_temperatures(&self) -> Result<Vec<ColorTemperature>, Error>;
+
+    /// Returns the current color temperature preset of the monitor, or `None` if it is not one of
+    /// the known presets.
+    ///
+    /// An error is returned for internal displays.
+    fn color_temperature(&self) -> Result<Option<ColorTemperature>, Error>;
+
+    /// Sets the color temperature preset of the monitor.
+    ///
+    /// An error is returned for internal displays.
+    fn set_color_temperature(&self, temperature: ColorTemperature) -> Result<(), Error>;
+
+    /// Returns the MCCS version implemented by the monitor as `(major, minor)`.
+    ///
+    /// This reads the VCP version feature (`0xDF`). An error is returned for internal displays.
+    fn vcp_version(&self) -> Result<(u8, u8), Error>;
+
+    /// Returns the display presets supported by the monitor.
+    ///
+    /// The presets are read from the values listed for the display application feature (`0xDC`) in
+    /// the capabilities string. An empty list is returned if the feature is not listed, which
+    /// includes internal displays.
+    fn supported_display_presets(&self) -> Result<Vec<DisplayPreset>, Error>;
+
+    /// Returns the current display preset of the monitor.
+    ///
+    /// This reads the display application feature (`0xDC`). An error is returned for internal
+    /// displays.
+    fn display_preset(&self) -> Result<DisplayPreset, Error>;
+
+    /// Sets the display preset of the monitor.
+    ///
+    /// Monitors usually apply the brightness, contrast and color settings stored for the preset,
+    /// so the brightness may change as a side effect. An error is returned for internal displays.
+    fn set_display_preset(&self, preset: DisplayPreset) -> Result<(), Error>;
+
+    /// Returns whether a control of the monitor was changed from its on-screen menu since the last
+    /// call, and resets the indication.
+    ///
+    /// This reads the new control value feature (`0x02`), which is cheaper than reading every
+    /// feature of interest, so polling it is a lightweight way to detect that the user changed
+    /// e.g. the brightness with the buttons of the monitor. `None` is returned for monitors that
+    /// do not support the feature, which then need to be read in full, and for internal displays.
+    fn take_new_control_value(&self) -> Result<Option<bool>, Error>;
+
+    /// Returns the brightness levels supported by an internal display, exactly as reported by the
+    /// driver.
+    ///
+    /// Levels are expected to be percentages in increasing order, but some drivers report levels
+    /// that are unsorted or above 100. `set` picks one of them according to
+    /// [`BrightnessExt::set_rounding`]. An empty list is returned for external monitors.
+    fn supported_brightness_levels(&self) -> Result<Vec<u8>, Error>;
+
+    /// Returns the brightness state of an internal display, exactly as reported by the driver.
+    ///
+    /// `None` is returned for external monitors.
+    fn display_brightness(&self) -> Result<Option<DisplayBrightness>, Error>;
+
+    /// Returns whether the display is in HDR mode.
+    ///
+    /// This is the advanced color state reported by the display configuration API. In HDR mode, the
+    /// brightness of internal displays set by `set` only scales SDR content, and monitors may
+    /// ignore or limit DDC/CI brightness, so applications may want to warn users.
+    fn is_hdr_active(&self) -> Result<bool, Error>;
+
+    /// Returns the number of bits per color channel the display is driven with (e.g. 8 or 10).
+    ///
+    /// This is read from the advanced color information of the display configuration API, like
+    /// [`BrightnessExt::is_hdr_active`]. `None` is returned if the driver does not report it.
+    fn bit_depth(&self) -> Result<Option<u32>, Error>;
+
+    /// Returns the mode the display currently uses.
+    ///
+    /// The width and height are those of the desktop area shown on the display, and the refresh
+    /// rate is 0 if not reported. This is read from the active display configuration, so it
+    /// reflects mode changes made after the device was listed. Unlike the native mode in the EDID,
+    /// this is what is active now, e.g. a lower resolution while a game or a projector mode is
+    /// running.
+    fn current_mode(&self) -> Result<DisplayMode, Error>;
+
+    /// Returns the area the monitor covers on the virtual desktop, in pixels.
+    ///
+    /// The primary monitor has its top left corner at (0, 0), and other monitors are placed
+    /// relative to it as arranged in the display settings, so coordinates may be negative. See
+    /// [`brightness_devices_grid`] to arrange devices by position.
+    fn screen_rect(&self) -> Result<ScreenRect, Error>;
+
+    /// Returns how long the last brightness or VCP feature request to the device took, if any was
+    /// made.
+    ///
+    /// This is the round-trip time of the last DDC/CI request for external monitors, and of the
+    /// last IOCTL for internal displays. Monitors commonly take tens of milliseconds to answer
+    /// DDC/CI requests, so this can be used to flag slow monitors or to adapt the number of steps of
+    /// a transition.
+    fn last_io_duration(&self) -> Option<Duration>;
+}
+
+/// Area of a monitor on the virtual desktop, in pixels.
+///
+/// `right` and `bottom` are exclusive.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct ScreenRect {
+    /// X coordinate of the left edge
+    pub left: i32,
+    /// Y coordinate of the top edge
+    pub top: i32,
+    /// X coordinate of the right edge
+    pub right: i32,
+    /// Y coordinate of the bottom edge
+    pub bottom: i32,
+}
+
+/// Brightness state of an internal display, as reported by the driver in a `DISPLAY_BRIGHTNESS`
+/// structure.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct DisplayBrightness {
+    /// Power policy the brightness applies to: `DISPLAYPOLICY_AC` (1), `DISPLAYPOLICY_DC` (2), or
+    /// both (3)
+    pub policy: u8,
+    /// Brightness level on AC power, between 0 and 100
+    pub ac: u8,
+    /// Brightness level on battery (DC) power, between 0 and 100
+    pub dc: u8,
+}
+
+pub use crate::levels::Rounding;
+
+/// Technology of the connection between a display adapter and a monitor.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum OutputTechnology {
+    /// Internal connection of a built-in display
+    Internal,
+    /// HDMI
+    Hdmi,
+    /// DisplayPort, including embedded DisplayPort and DisplayPort over USB
+    DisplayPort,
+    /// DVI
+    Dvi,
+    /// VGA (HD15)
+    Vga,
+    /// Composite video
+    Composite,
+    /// Other technology, with its raw `DISPLAYCONFIG_VIDEO_OUTPUT_TECHNOLOGY` value
+    Other(i32),
+}
+
+impl From<DISPLAYCONFIG_VIDEO_OUTPUT_TECHNOLOGY> for OutputTechnology {
+    fn from(technology: DISPLAYCONFIG_VIDEO_OUTPUT_TECHNOLOGY) -> Self {
+        match technology {
+            DISPLAYCONFIG_OUTPUT_TECHNOLOGY_INTERNAL => OutputTechnology::Internal,
+            DISPLAYCONFIG_OUTPUT_TECHNOLOGY_HDMI => OutputTechnology::Hdmi,
+            DISPLAYCONFIG_OUTPUT_TECHNOLOGY_DISPLAYPORT_EXTERNAL
+            | DISPLAYCONFIG_OUTPUT_TECHNOLOGY_DISPLAYPORT_EMBEDDED
+            | DISPLAYCONFIG_OUTPUT_TECHNOLOGY_DISPLAYPORT_USB_TUNNEL => {
+                OutputTechnology::DisplayPort
+            }
+            DISPLAYCONFIG_OUTPUT_TECHNOLOGY_DVI => OutputTechnology::Dvi,
+            DISPLAYCONFIG_OUTPUT_TECHNOLOGY_HD15 => OutputTechnology::Vga,
+            DISPLAYCONFIG_OUTPUT_TECHNOLOGY_COMPOSITE_VIDEO => OutputTechnology::Composite,
+            DISPLAYCONFIG_VIDEO_OUTPUT_TECHNOLOGY(raw) => OutputTechnology::Other(raw),
+        }
+    }
+}
+
+impl fmt::Display for OutputTechnology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputTechnology::Internal => f.write_str("Internal"),
+            OutputTechnology::Hdmi => f.write_str("HDMI"),
+            OutputTechnology::DisplayPort => f.write_str("DisplayPort"),
+            OutputTechnology::Dvi => f.write_str("DVI"),
+            OutputTechnology::Vga => f.write_str("VGA"),
+            OutputTechnology::Composite => f.write_str("Composite"),
+            OutputTechnology::Other(raw) => write!(f, "Other ({})", raw),
+        }
+    }
+}
+
+/// Display preset of a monitor (e.g. a movie or game picture mode), as defined by the display
+/// application feature of MCCS (VCP `0xDC`).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum DisplayPreset {
+    /// Standard or default preset
+    Standard,
+    /// Productivity (e.g. office applications)
+    Productivity,
+    /// Mixed (e.g. internet with a mix of text and images)
+    Mixed,
+    /// Movie
+    Movie,
+    /// User defined
+    UserDefined,
+    /// Games
+    Games,
+    /// Sports (fast motion)
+    Sports,
+    /// Professional, with all signal processing disabled
+    Professional,
+    /// Other preset, usually vendor-specific, with its raw VCP value
+    Other(u16),
+}
+
+impl DisplayPreset {
+    fn from_vcp(value: u16) -> Self {
+        match value {
+            0x00 => DisplayPreset::Standard,
+            0x01 => DisplayPreset::Productivity,
+            0x02 => DisplayPreset::Mixed,
+            0x03 => DisplayPreset::Movie,
+            0x04 => DisplayPreset::UserDefined,
+            0x05 => DisplayPreset::Games,
+            0x06 => DisplayPreset::Sports,
+            0x07 => DisplayPreset::Professional,
+            value => DisplayPreset::Other(value),
+        }
+    }
+
+    fn to_vcp(self) -> u16 {
+        match self {
+            DisplayPreset::Standard => 0x00,
+            DisplayPreset::Productivity => 0x01,
+            DisplayPreset::Mixed => 0x02,
+            DisplayPreset::Movie => 0x03,
+            DisplayPreset::UserDefined => 0x04,
+            DisplayPreset::Games => 0x05,
+            DisplayPreset::Sports => 0x06,
+            DisplayPreset::Professional => 0x07,
+            DisplayPreset::Other(value) => value,
+        }
+    }
+}
+
+/// Color temperature preset of a monitor.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum ColorTemperature {
+    /// 4000 K
+    K4000,
+    /// 5000 K
+    K5000,
+    /// 6500 K
+    K6500,
+    /// 7500 K
+    K7500,
+    /// 8200 K
+    K8200,
+    /// 9300 K
+    K9300,
+    /// 10000 K
+    K10000,
+    /// 11500 K
+    K11500,
 }
 
+/// Color temperature presets with their Monitor Configuration API value and capability flag.
+const COLOR_TEMPERATURES: [(ColorTemperature, MC_COLOR_TEMPERATURE, u32); 8] = [
+    (
+        ColorTemperature::K4000,
+        MC_COLOR_TEMPERATURE_4000K,
+        MC_SUPPORTED_COLOR_TEMPERATURE_4000K,
+    ),
+    (
+        ColorTemperature::K5000,
+        MC_COLOR_TEMPERATURE_5000K,
+        MC_SUPPORTED_COLOR_TEMPERATURE_5000K,
+    ),
+    (
+        ColorTemperature::K6500,
+        MC_COLOR_TEMPERATURE_6500K,
+        MC_SUPPORTED_COLOR_TEMPERATURE_6500K,
+    ),
+    (
+        ColorTemperature::K7500,
+        MC_COLOR_TEMPERATURE_7500K,
+        MC_SUPPORTED_COLOR_TEMPERATURE_7500K,
+    ),
+    (
+        ColorTemperature::K8200,
+        MC_COLOR_TEMPERATURE_8200K,
+        MC_SUPPORTED_COLOR_TEMPERATURE_8200K,
+    ),
+    (
+        ColorTemperature::K9300,
+        MC_COLOR_TEMPERATURE_9300K,
+        MC_SUPPORTED_COLOR_TEMPERATURE_9300K,
+    ),
+    (
+        ColorTemperature::K10000,
+        MC_COLOR_TEMPERATURE_10000K,
+        MC_SUPPORTED_COLOR_TEMPERATURE_10000K,
+    ),
+    (
+        ColorTemperature::K11500,
+        MC_COLOR_TEMPERATURE_11500K,
+        MC_SUPPORTED_COLOR_TEMPERATURE_11500K,
+    ),
+];
+
 #[derive(Debug)]
 pub struct BlockingDeviceImpl {
-    #[allow(dead_code)]
-    pub hmonitor: isize,
-    physical_monitor: WrappedPhysicalMonitor,
-    file_handle: WrappedFileHandle,
+    /// Replaced when the device is reopened, see [`BrightnessExt::set_reopen_on_stale`].
+    handles: RwLock<Handles>,
     pub device_name: String,
+    /// Note: MONITORINFOEXW.szDevice, which DISPLAY_DEVICEW.DeviceName starts with.
+    pub(crate) gdi_device_name: String,
     /// Note: PHYSICAL_MONITOR.szPhysicalMonitorDescription == DISPLAY_DEVICEW.DeviceString
     /// Description is **not** unique.
     pub(crate) device_description: String,
@@ -72,16 +561,122 @@ pub struct BlockingDeviceImpl {
     /// Note: DISPLAYCONFIG_TARGET_DEVICE_NAME.monitorDevicePath == DISPLAY_DEVICEW.DeviceID (with EDD_GET_DEVICE_INTERFACE_NAME)\
     /// These are in the "DOS Device Path" format.
     pub(crate) device_path: String,
-    output_technology: DISPLAYCONFIG_VIDEO_OUTPUT_TECHNOLOGY,
+    /// Note: DISPLAYCONFIG_TARGET_DEVICE_NAME.monitorFriendlyDeviceName, which is usually empty for
+    /// internal displays.
+    pub(crate) friendly_name: String,
+    pub(crate) output_technology: DISPLAYCONFIG_VIDEO_OUTPUT_TECHNOLOGY,
+    pub(crate) adapter_luid: u64,
+    /// Note: DISPLAYCONFIG_TARGET_DEVICE_NAME.header.id, the ID of the target on its adapter.
+    target_id: u32,
+    /// See [`brightness_devices_including_virtual`].
+    is_virtual: bool,
+    is_primary: bool,
+    pub(crate) nonzero_floor: AtomicBool,
+    pub(crate) rounding: AtomicU8,
+    pub(crate) min_percentage: AtomicU32,
+    pub(crate) reopen_on_stale: AtomicBool,
+    pub(crate) mapping: RwLock<Option<BrightnessMapping>>,
+    pub(crate) skip_unchanged: RwLock<Option<u32>>,
+    pub(crate) verify_writes: AtomicBool,
+    /// Capabilities string, read on first use as reading it over DDC/CI may take seconds. It is
+    /// read again after the device is reopened, as the monitor may have changed.
+    capabilities: RwLock<Option<String>>,
+    /// See [`BrightnessExt::last_io_duration`].
+    pub(crate) last_io_duration: RwLock<Option<Duration>>,
+}
+
+#[derive(Debug)]
+struct Handles {
+    hmonitor: isize,
+    physical_monitor: WrappedPhysicalMonitor,
+    file_handle: WrappedFileHandle,
 }
 
 impl BlockingDeviceImpl {
     fn is_internal(&self) -> bool {
         self.output_technology == DISPLAYCONFIG_OUTPUT_TECHNOLOGY_INTERNAL
     }
+
+    /// The guard must be held while the handles are used, so that they are not closed by a
+    /// concurrent reopen.
+    fn handles(&self) -> RwLockReadGuard<'_, Handles> {
+        self.handles.read().unwrap()
+    }
+
+    pub(crate) fn hmonitor(&self) -> isize {
+        self.handles().hmonitor
+    }
+
+    /// Runs an I/O request to the device, recording how long it took.
+    fn timed<T>(&self, request: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = request();
+        *self.last_io_duration.write().unwrap() = Some(start.elapsed());
+        result
+    }
+
+    /// Runs `op`, and if it failed because of a stale handle and reopening is enabled, reopens the
+    /// device and runs `op` once more.
+    fn with_reopen<T>(&self, op: impl Fn(&Self) -> Result<T, SysError>) -> Result<T, SysError> {
+        match op(self) {
+            Err(e) if e.is_stale_handle() && self.reopen_on_stale.load(Ordering::Relaxed) => {
+                self.reopen()?;
+                op(self)
+            }
+            result => result,
+        }
+    }
+
+    /// Finds the monitor again by its device path and takes over its handles.
+    fn reopen(&self) -> Result<(), SysError> {
+        // Devices enumerated as internal only have no physical monitor handle, keep it that way.
+        let selection = if self.is_virtual {
+            Selection::IncludingVirtual
+        } else if self.handles().physical_monitor.0.is_invalid() {
+            Selection::InternalOnly
+        } else {
+            Selection::Controllable
+        };
+        let fresh = devices(selection, false)
+            .filter_map(Result::ok)
+            .find(|device| device.device_path == self.device_path)
+            .ok_or_else(|| SysError::DeviceDisconnected {
+                monitor: self.monitor_name(),
+            })?;
+        *self.handles.write().unwrap() = fresh.handles.into_inner().unwrap();
+        *self.capabilities.write().unwrap() = None;
+        Ok(())
+    }
+
+    fn monitor_name(&self) -> MonitorName {
+        MonitorName {
+            device_name: self.device_name.clone(),
+            friendly_name: self.friendly_name.clone(),
+        }
+    }
+}
+
+/// Names of a monitor, used in errors.\
+/// Errors are displayed with the friendly name (e.g. "DELL U2720Q") when the monitor has one, as the
+/// device name (e.g. `\\.\DISPLAY1`) means little to users.
+#[derive(Clone, Debug)]
+pub(crate) struct MonitorName {
+    device_name: String,
+    friendly_name: String,
+}
+
+impl fmt::Display for MonitorName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.friendly_name.is_empty() {
+            f.write_str(&self.device_name)
+        } else {
+            f.write_str(&self.friendly_name)
+        }
+    }
 }
 
-/// A safe wrapper for a physical monitor handle that implements `Drop` to call `DestroyPhysicalMonitor`
+/// A safe wrapper for a physical monitor handle that implements `Drop` to call `DestroyPhysicalMonitor`\
+/// The handle is null for devices enumerated without physical monitors.
 struct WrappedPhysicalMonitor(HANDLE);
 
 impl fmt::Debug for WrappedPhysicalMonitor {
@@ -92,6 +687,9 @@ impl fmt::Debug for WrappedPhysicalMonitor {
 
 impl Drop for WrappedPhysicalMonitor {
     fn drop(&mut self) {
+        if self.0.is_invalid() {
+            return;
+        }
         unsafe {
             DestroyPhysicalMonitor(self.0);
         }
@@ -126,74 +724,384 @@ impl crate::blocking::Brightness for BlockingDeviceImpl {
     }
 
     fn get(&self) -> Result<u32, Error> {
-        Ok(if self.is_internal() {
-            ioctl_query_display_brightness(self)?
-        } else {
-            ddcci_get_monitor_brightness(self)?.get_current_percentage()
-        })
+        let mapping = self.mapping.read().unwrap();
+        Ok(self.with_reopen(|device| {
+            Ok(if device.is_internal() {
+                let level = ioctl_query_display_brightness(device)?;
+                match mapping.as_ref() {
+                    Some(mapping) => mapping.to_percentage(level, 0, 100),
+                    None => level,
+                }
+            } else {
+                let values = ddcci_get_monitor_brightness(device)?;
+                match mapping.as_ref() {
+                    Some(mapping) => mapping.to_percentage(values.current, values.min, values.max),
+                    None => values.get_current_percentage(),
+                }
+            })
+        })?)
     }
 
     fn set(&self, percentage: u32) -> Result<(), Error> {
+        let percentage = percentage.max(self.min_percentage.load(Ordering::Relaxed));
+        let mapping = self.mapping.read().unwrap();
+        Ok(self.with_reopen(|device| {
+            if device.is_internal() {
+                let supported = ioctl_query_supported_brightness(device)?;
+                let level = match mapping.as_ref() {
+                    Some(mapping) => mapping.to_raw(percentage, 0, 100),
+                    None => percentage,
+                };
+                let new_value = supported.select(
+                    level,
+                    Rounding::from_u8(device.rounding.load(Ordering::Relaxed)),
+                    device.nonzero_floor.load(Ordering::Relaxed),
+                );
+                ioctl_set_display_brightness(device, new_value)?;
+            } else {
+                let current = ddcci_get_monitor_brightness(device)?;
+                let new_value = match mapping.as_ref() {
+                    Some(mapping) => mapping.to_raw(percentage, current.min, current.max),
+                    None => current.percentage_to_current(percentage),
+                };
+                let unchanged = device
+                    .skip_unchanged
+                    .read()
+                    .unwrap()
+                    .is_some_and(|tolerance| new_value.abs_diff(current.current) <= tolerance);
+                if !unchanged {
+                    ddcci_set_monitor_brightness(device, new_value)?;
+                    if device.verify_writes.load(Ordering::Relaxed) {
+                        let applied = ddcci_get_monitor_brightness(device)?.current;
+                        if applied.abs_diff(new_value) > 1 {
+                            return Err(SysError::SettingBrightnessClamped {
+                                monitor: device.monitor_name(),
+                                requested: new_value,
+                                applied,
+                            });
+                        }
+                    }
+                }
+            }
+            Ok(())
+        })?)
+    }
+
+    fn edid(&self) -> Result<Option<EdidInfo>, Error> {
+        Ok(read_edid(self)?.and_then(|edid| EdidInfo::parse(&edid)))
+    }
+
+    fn is_primary(&self) -> Result<bool, Error> {
+        Ok(self.is_primary)
+    }
+
+    fn kind(&self) -> Result<DeviceKind, Error> {
+        if self.is_virtual {
+            return Ok(DeviceKind::Virtual);
+        }
+        Ok(device_kind(self.output_technology))
+    }
+
+    fn raw_capabilities(&self) -> Result<Option<String>, Error> {
         if self.is_internal() {
-            let supported = ioctl_query_supported_brightness(self)?;
-            let new_value = supported.get_nearest(percentage);
-            ioctl_set_display_brightness(self, new_value)?;
-        } else {
-            let current = ddcci_get_monitor_brightness(self)?;
-            let new_value = current.percentage_to_current(percentage);
-            ddcci_set_monitor_brightness(self, new_value)?;
+            return Ok(None);
         }
-        Ok(())
+        Ok(Some(ddcci_get_capabilities(self)?))
+    }
+
+    fn brightness_raw_max(&self) -> Result<u32, Error> {
+        if self.is_internal() {
+            return Ok(100);
+        }
+        Ok(ddcci_get_monitor_brightness(self)?.max)
+    }
+}
+
+fn device_kind(output_technology: DISPLAYCONFIG_VIDEO_OUTPUT_TECHNOLOGY) -> DeviceKind {
+    if output_technology == DISPLAYCONFIG_OUTPUT_TECHNOLOGY_INTERNAL {
+        DeviceKind::InternalPanel
+    } else {
+        DeviceKind::ExternalDdc
+    }
+}
+
+/// Blocking function that returns the brightness devices of the given monitors.
+///
+/// This is useful for applications that already enumerate monitors (e.g. with
+/// `EnumDisplayMonitors`) and want brightness devices for the same snapshot of the display
+/// topology. `hmonitors` are raw `HMONITOR` handle values.
+pub fn brightness_devices_from_hmonitors<I>(
+    hmonitors: I,
+) -> impl Iterator<Item = Result<BrightnessDevice, Error>>
+where
+    I: IntoIterator<Item = isize>,
+{
+    devices_from_hmonitors(
+        hmonitors.into_iter().map(HMONITOR).collect(),
+        Selection::Controllable,
+        false,
+    )
+    .map(|r| r.map(BrightnessDevice).map_err(Into::into))
+}
+
+/// Blocking function that returns the brightness devices of internal displays only.
+///
+/// External monitors are skipped without opening physical monitor handles, so no DDC/CI
+/// communication takes place. This is faster than `brightness_devices` and avoids the delays some
+/// docks cause when their monitors are queried.
+pub fn internal_brightness_devices() -> impl Iterator<Item = Result<BrightnessDevice, Error>> {
+    devices(Selection::InternalOnly, false).map(|r| r.map(BrightnessDevice).map_err(Into::into))
+}
+
+/// Blocking function that returns the brightness devices, including virtual displays.
+///
+/// Virtual displays, such as those of remote desktop sessions or virtual machines, are skipped by
+/// `brightness_devices`. They are returned here with the [`DeviceKind::Virtual`] kind, which is
+/// useful for tools that test display software. Their brightness usually cannot be controlled, and
+/// information that Windows does not report for them (e.g. the friendly name) is left empty.
+pub fn brightness_devices_including_virtual(
+) -> impl Iterator<Item = Result<BrightnessDevice, Error>> {
+    devices(Selection::IncludingVirtual, false).map(|r| r.map(BrightnessDevice).map_err(Into::into))
+}
+
+/// Blocking function that returns the brightness devices of the monitors driven by a display
+/// adapter.
+///
+/// `adapter_luid` is the LUID of the adapter, as returned by [`BrightnessExt::adapter_luid`]. This
+/// is useful on systems with several GPUs.
+pub fn brightness_devices_on_adapter(
+    adapter_luid: u64,
+) -> impl Iterator<Item = Result<BrightnessDevice, Error>> {
+    devices_on_adapter(adapter_luid).map(|r| r.map(BrightnessDevice).map_err(Into::into))
+}
+
+pub(crate) fn devices_on_adapter(
+    adapter_luid: u64,
+) -> impl Iterator<Item = Result<BlockingDeviceImpl, SysError>> {
+    devices(Selection::Controllable, false)
+        .filter(move |d| d.as_ref().map_or(true, |d| d.adapter_luid == adapter_luid))
+}
+
+/// Blocking function that returns the brightness device of the monitor under the mouse cursor.
+///
+/// `None` is returned if the cursor is not on a monitor with a brightness device. If several
+/// monitors show the same image (duplicate mode), the first one is returned.
+pub fn device_under_cursor() -> Result<Option<BrightnessDevice>, Error> {
+    Ok(device_impl_under_cursor()?.map(BrightnessDevice))
+}
+
+pub(crate) fn device_impl_under_cursor() -> Result<Option<BlockingDeviceImpl>, SysError> {
+    let hmonitor = unsafe {
+        let mut point = POINT::default();
+        GetCursorPos(&mut point)
+            .ok()
+            .map_err(SysError::GettingCursorPositionFailed)?;
+        MonitorFromPoint(point, MONITOR_DEFAULTTONULL)
+    };
+    if hmonitor.0 == 0 {
+        return Ok(None);
+    }
+    devices_from_hmonitors(vec![hmonitor], Selection::Controllable, false)
+        .next()
+        .transpose()
+}
+
+/// Blocking function that returns the brightness devices arranged by the position of their monitor.
+///
+/// Devices are grouped in rows from top to bottom, each sorted from left to right, so that a wall of
+/// monitors can be addressed as a grid. Monitors whose vertical extents overlap are in the same row,
+/// even if they are not aligned. Any error listing a device or getting its position is returned, as
+/// a grid missing a monitor would be misleading.
+pub fn brightness_devices_grid() -> Result<Vec<Vec<BrightnessDevice>>, Error> {
+    let devices = brightness_devices()
+        .map(|device| {
+            let device = device?;
+            Ok((screen_rect(&device)?, BrightnessDevice(device)))
+        })
+        .collect::<Result<Vec<_>, SysError>>()?;
+    Ok(arrange_in_grid(devices))
+}
+
+/// Groups items in rows by their position, see [`brightness_devices_grid`].
+pub(crate) fn arrange_in_grid<T>(mut items: Vec<(ScreenRect, T)>) -> Vec<Vec<T>> {
+    items.sort_by_key(|(rect, _)| (rect.top, rect.left));
+    let mut rows = Vec::<(i32, Vec<(ScreenRect, T)>)>::new();
+    for (rect, item) in items {
+        match rows.last_mut() {
+            // The item overlaps vertically with every monitor already in the row.
+            Some((bottom, row)) if rect.top < *bottom => {
+                *bottom = (*bottom).min(rect.bottom);
+                row.push((rect, item));
+            }
+            _ => rows.push((rect.bottom, vec![(rect, item)])),
+        }
+    }
+    rows.into_iter()
+        .map(|(_, mut row)| {
+            row.sort_by_key(|(rect, _)| rect.left);
+            row.into_iter().map(|(_, item)| item).collect()
+        })
+        .collect()
+}
+
+/// Returns the percentage that `set` applies for `requested`, see [`BrightnessExt::preview`].
+pub(crate) fn preview(device: &BlockingDeviceImpl, requested: u32) -> u32 {
+    preview_percentage(
+        requested,
+        device.min_percentage.load(Ordering::Relaxed),
+        device.mapping.read().unwrap().as_ref(),
+        device.is_internal(),
+    )
+}
+
+/// Returns the percentage that `set` applies for `requested` with the given options of a device.
+fn preview_percentage(
+    requested: u32,
+    min_percentage: u32,
+    mapping: Option<&BrightnessMapping>,
+    is_internal: bool,
+) -> u32 {
+    let percentage = requested.max(min_percentage).min(100);
+    match mapping {
+        // Levels of internal displays are mapped between 0 and 100.
+        Some(mapping) if is_internal => mapping.round_trip(percentage, Some((0, 100))),
+        Some(mapping) => mapping.round_trip(percentage, None),
+        None => percentage,
     }
 }
 
 pub(crate) fn brightness_devices() -> impl Iterator<Item = Result<BlockingDeviceImpl, SysError>> {
+    devices(Selection::Controllable, false)
+}
+
+/// Returns the brightness devices for `brightness_devices_retry`, which also retries finding the
+/// display devices of monitors that have none, see [`get_display_devices_from_monitor_info`].
+pub(crate) fn brightness_devices_for_retry(
+) -> impl Iterator<Item = Result<BlockingDeviceImpl, SysError>> {
+    devices(Selection::Controllable, true)
+}
+
+/// Devices returned when enumerating monitors.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Selection {
+    /// Monitors that can be controlled
+    Controllable,
+    /// Internal displays only. Physical monitor handles are not requested, so the physical monitor
+    /// handle of the devices is null, as DDC/CI is not used with them.
+    InternalOnly,
+    /// Monitors that can be controlled and virtual displays. The file handle of virtual displays
+    /// is null, as they cannot be opened.
+    IncludingVirtual,
+}
+
+/// Returns the brightness devices of all monitors, see [`devices_from_hmonitors`].
+pub(crate) fn devices(
+    selection: Selection,
+    retry_display_devices: bool,
+) -> impl Iterator<Item = Result<BlockingDeviceImpl, SysError>> {
+    match unsafe { enum_display_monitors() } {
+        Ok((hmonitors, partial)) => Either::Left(
+            devices_from_hmonitors(hmonitors, selection, retry_display_devices)
+                .chain(partial.map(Err)),
+        ),
+        Err(e) => Either::Right(once(Err(e))),
+    }
+}
+
+/// Returns the brightness devices of the given monitors.
+///
+/// With `retry_display_devices`, finding the display devices of a monitor is retried after a delay
+/// if there are none, see [`get_display_devices_from_monitor_info`].
+pub(crate) fn devices_from_hmonitors(
+    hmonitors: Vec<HMONITOR>,
+    selection: Selection,
+    retry_display_devices: bool,
+) -> impl Iterator<Item = Result<BlockingDeviceImpl, SysError>> {
     unsafe {
         let device_info_map = match get_device_info_map() {
             Ok(info) => info,
             Err(e) => return Either::Right(once(Err(e))),
         };
-        let hmonitors = match enum_display_monitors() {
-            Ok(monitors) => monitors,
-            Err(e) => return Either::Right(once(Err(e))),
-        };
         Either::Left(hmonitors.into_iter().flat_map(move |hmonitor| {
-            let physical_monitors = match get_physical_monitors_from_hmonitor(hmonitor) {
-                Ok(p) => p,
+            let monitor_info = match get_monitor_info(hmonitor) {
+                Ok(i) => i,
                 Err(e) => return vec![Err(e)],
             };
-            let display_devices = match get_display_devices_from_hmonitor(hmonitor) {
-                Ok(p) => p,
+            let display_devices =
+                get_display_devices_from_monitor_info(&monitor_info, retry_display_devices);
+            let pairs = if selection == Selection::InternalOnly {
+                Ok(display_devices
+                    .into_iter()
+                    .filter(|device| {
+                        device_info_map.get(&device.DeviceID).is_some_and(|info| {
+                            info.outputTechnology == DISPLAYCONFIG_OUTPUT_TECHNOLOGY_INTERNAL
+                        })
+                    })
+                    .map(|device| (WrappedPhysicalMonitor(HANDLE::default()), device))
+                    .collect())
+            } else {
+                get_physical_monitors_from_hmonitor(hmonitor)
+                    .and_then(|physical| associate_monitors(physical, display_devices))
+            };
+            let pairs = match pairs {
+                Ok(pairs) => pairs,
                 Err(e) => return vec![Err(e)],
             };
-            if display_devices.len() != physical_monitors.len() {
-                // There doesn't seem to be any way to directly associate a physical monitor
-                // handle with the equivalent display device, other than by array indexing
-                // https://stackoverflow.com/questions/63095216/how-to-associate-physical-monitor-with-monitor-deviceid
-                return vec![Err(SysError::EnumerationMismatch)];
-            }
-            physical_monitors
+            pairs
                 .into_iter()
-                .zip(display_devices)
                 .filter_map(|(physical_monitor, display_device)| {
-                    get_file_handle_for_display_device(&display_device)
-                        .transpose()
-                        .map(|file_handle| (physical_monitor, display_device, file_handle))
+                    let file_handle = match get_file_handle_for_display_device(&display_device) {
+                        Ok(None) if selection == Selection::IncludingVirtual => Ok(None),
+                        result => result.transpose()?.map(Some),
+                    };
+                    Some((physical_monitor, display_device, file_handle))
                 })
                 .map(|(physical_monitor, display_device, file_handle)| {
                     let file_handle = file_handle?;
-                    let info = device_info_map
-                        .get(&display_device.DeviceID)
-                        .ok_or(SysError::DeviceInfoMissing)?;
+                    let is_virtual = file_handle.is_none();
+                    let virtual_info;
+                    let info = match device_info_map.get(&display_device.DeviceID) {
+                        Some(info) => info,
+                        // The display configuration of virtual displays may not be accessible.
+                        None if is_virtual => {
+                            virtual_info = DISPLAYCONFIG_TARGET_DEVICE_NAME {
+                                outputTechnology: DISPLAYCONFIG_OUTPUT_TECHNOLOGY_OTHER,
+                                ..Default::default()
+                            };
+                            &virtual_info
+                        }
+                        None => return Err(SysError::DeviceInfoMissing),
+                    };
+                    let file_handle = file_handle.unwrap_or(WrappedFileHandle(HANDLE::default()));
                     Ok(BlockingDeviceImpl {
-                        hmonitor: hmonitor.0,
-                        physical_monitor,
-                        file_handle,
+                        handles: RwLock::new(Handles {
+                            hmonitor: hmonitor.0,
+                            physical_monitor,
+                            file_handle,
+                        }),
                         device_name: wchar_to_string(&display_device.DeviceName),
+                        gdi_device_name: wchar_to_string(&monitor_info.szDevice),
                         device_description: wchar_to_string(&display_device.DeviceString),
                         device_key: wchar_to_string(&display_device.DeviceKey),
                         device_path: wchar_to_string(&display_device.DeviceID),
+                        friendly_name: wchar_to_string(&info.monitorFriendlyDeviceName),
                         output_technology: info.outputTechnology,
+                        adapter_luid: luid_to_u64(info.header.adapterId),
+                        target_id: info.header.id,
+                        is_virtual,
+                        is_primary: flag_set(
+                            monitor_info.monitorInfo.dwFlags,
+                            MONITORINFOF_PRIMARY,
+                        ),
+                        nonzero_floor: AtomicBool::new(false),
+                        rounding: AtomicU8::new(Rounding::Nearest as u8),
+                        min_percentage: AtomicU32::new(0),
+                        reopen_on_stale: AtomicBool::new(false),
+                        mapping: RwLock::new(None),
+                        skip_unchanged: RwLock::new(None),
+                        verify_writes: AtomicBool::new(false),
+                        capabilities: RwLock::new(None),
+                        last_io_duration: RwLock::new(None),
                     })
                 })
                 .collect()
@@ -201,11 +1109,40 @@ pub(crate) fn brightness_devices() -> impl Iterator<Item = Result<BlockingDevice
     }
 }
 
-/// Returns a `HashMap` of Device Path to `DISPLAYCONFIG_TARGET_DEVICE_NAME`.\
-/// This can be used to find the `DISPLAYCONFIG_VIDEO_OUTPUT_TECHNOLOGY` for a monitor.\
-/// The output technology is used to determine if a device is internal or external.
-unsafe fn get_device_info_map(
-) -> Result<HashMap<[u16; 128], DISPLAYCONFIG_TARGET_DEVICE_NAME>, SysError> {
+/// Returns the identity of all display devices without opening physical monitor or file handles.
+///
+/// Display devices without a display configuration (e.g. Remote Desktop sessions) are skipped, as
+/// they cannot be controlled.
+pub(crate) fn list_devices() -> Result<Vec<DeviceInfo>, SysError> {
+    unsafe {
+        let device_info_map = get_device_info_map()?;
+        let mut devices = Vec::new();
+        let (hmonitors, partial) = enum_display_monitors()?;
+        if let Some(e) = partial {
+            return Err(e);
+        }
+        for hmonitor in hmonitors {
+            let monitor_info = get_monitor_info(hmonitor)?;
+            devices.extend(
+                get_display_devices_from_monitor_info(&monitor_info, false)
+                    .into_iter()
+                    .filter_map(|display_device| {
+                        let info = device_info_map.get(&display_device.DeviceID)?;
+                        Some(DeviceInfo {
+                            name: wchar_to_string(&display_device.DeviceName),
+                            path: wchar_to_string(&display_device.DeviceID),
+                            kind: device_kind(info.outputTechnology),
+                        })
+                    }),
+            );
+        }
+        Ok(devices)
+    }
+}
+
+/// Returns the paths and modes of the active display configuration.
+unsafe fn query_display_config(
+) -> Result<(Vec<DISPLAYCONFIG_PATH_INFO>, Vec<DISPLAYCONFIG_MODE_INFO>), SysError> {
     let mut path_count = 0;
     let mut mode_count = 0;
     check_status(
@@ -225,6 +1162,17 @@ unsafe fn get_device_info_map(
         ),
         SysError::QueryDisplayConfigFailed,
     )?;
+    display_paths.truncate(path_count as usize);
+    display_modes.truncate(mode_count as usize);
+    Ok((display_paths, display_modes))
+}
+
+/// Returns a `HashMap` of Device Path to `DISPLAYCONFIG_TARGET_DEVICE_NAME`.\
+/// This can be used to find the `DISPLAYCONFIG_VIDEO_OUTPUT_TECHNOLOGY` for a monitor.\
+/// The output technology is used to determine if a device is internal or external.
+unsafe fn get_device_info_map(
+) -> Result<HashMap<[u16; 128], DISPLAYCONFIG_TARGET_DEVICE_NAME>, SysError> {
+    let (_, display_modes) = query_display_config()?;
     display_modes
         .into_iter()
         .filter(|mode| mode.infoType == DISPLAYCONFIG_MODE_INFO_TYPE_TARGET)
@@ -249,8 +1197,11 @@ unsafe fn get_device_info_map(
 
 /// Calls `EnumDisplayMonitors` and returns a list of `HMONITOR` handles.\
 /// Note that a `HMONITOR` is a logical construct that may correspond to multiple physical monitors.\
-/// e.g. when in "Duplicate" mode two physical monitors will belong to the same `HMONITOR`
-unsafe fn enum_display_monitors() -> Result<Vec<HMONITOR>, SysError> {
+/// e.g. when in "Duplicate" mode two physical monitors will belong to the same `HMONITOR`\
+/// If the enumeration fails after some monitors were reported, they are returned anyway with the
+/// error, so that callers can tell an incomplete list from a complete one. An empty list therefore
+/// means that there is no monitor, not that the enumeration failed.
+unsafe fn enum_display_monitors() -> Result<(Vec<HMONITOR>, Option<SysError>), SysError> {
     unsafe extern "system" fn enum_monitors(
         handle: HMONITOR,
         _: HDC,
@@ -262,24 +1213,38 @@ unsafe fn enum_display_monitors() -> Result<Vec<HMONITOR>, SysError> {
         true.into()
     }
     let mut hmonitors = Vec::<HMONITOR>::new();
-    EnumDisplayMonitors(
+    let result = EnumDisplayMonitors(
         HDC::default(),
         ptr::null_mut(),
         Some(enum_monitors),
         LPARAM(&mut hmonitors as *mut _ as isize),
     )
-    .ok()
-    .map_err(SysError::EnumDisplayMonitorsFailed)?;
-    Ok(hmonitors)
+    .ok();
+    match result {
+        Ok(()) => Ok((hmonitors, None)),
+        // Some drivers fail the enumeration after reporting valid monitors, which can still be
+        // controlled.
+        Err(e) if !hmonitors.is_empty() => {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(
+                error = %e,
+                monitors = hmonitors.len(),
+                "Monitor enumeration failed, using the monitors enumerated so far",
+            );
+            Ok((hmonitors, Some(SysError::EnumDisplayMonitorsIncomplete(e))))
+        }
+        Err(e) => Err(SysError::EnumDisplayMonitorsFailed(e)),
+    }
 }
 
 /// Gets the list of `PHYSICAL_MONITOR` handles that belong to a `HMONITOR`.\
 /// These handles are required for use with the DDC/CI functions, however a valid handle will still
 /// be returned for non DDC/CI monitors and also Remote Desktop Session displays.\
 /// Also note that physically connected but disabled (inactive) monitors are not returned from this API.
+/// Each handle is returned with the description of the monitor.
 unsafe fn get_physical_monitors_from_hmonitor(
     hmonitor: HMONITOR,
-) -> Result<Vec<WrappedPhysicalMonitor>, SysError> {
+) -> Result<Vec<(WrappedPhysicalMonitor, String)>, SysError> {
     let mut physical_number: u32 = 0;
     BOOL(GetNumberOfPhysicalMonitorsFromHMONITOR(
         hmonitor,
@@ -297,26 +1262,108 @@ unsafe fn get_physical_monitors_from_hmonitor(
     .ok()
     .map_err(SysError::GetPhysicalMonitorsFailed)?;
     // Transform immediately into WrappedPhysicalMonitor so the handles don't leak
-    raw_physical_monitors
-        .into_iter()
-        .for_each(|pm| physical_monitors.push(WrappedPhysicalMonitor(pm.hPhysicalMonitor)));
+    raw_physical_monitors.into_iter().for_each(|pm| {
+        physical_monitors.push((
+            WrappedPhysicalMonitor(pm.hPhysicalMonitor),
+            // The struct is packed, so the field is copied to be read.
+            wchar_to_string(&{ pm.szPhysicalMonitorDescription }),
+        ))
+    });
     Ok(physical_monitors)
 }
 
-/// Gets the list of display devices that belong to a `HMONITOR`.\
-/// Due to the `EDD_GET_DEVICE_INTERFACE_NAME` flag, the `DISPLAY_DEVICEW` will contain the DOS
-/// device path for each monitor in the `DeviceID` field.\
-/// Note: Connected but inactive displays have been filtered out.
-unsafe fn get_display_devices_from_hmonitor(
-    hmonitor: HMONITOR,
-) -> Result<Vec<DISPLAY_DEVICEW>, SysError> {
+/// Associates the physical monitors of a `HMONITOR` with its display devices.\
+/// There doesn't seem to be any way to directly associate a physical monitor handle with the
+/// equivalent display device, other than by array indexing
+/// https://stackoverflow.com/questions/63095216/how-to-associate-physical-monitor-with-monitor-deviceid \
+/// The order of both lists has been seen to differ with monitors daisy-chained through DisplayPort
+/// MST hubs, so pairs are checked using the monitor description that both APIs report. If the
+/// descriptions don't match by index, monitors are paired by description when this is unambiguous.
+/// Otherwise an error is returned rather than risking controlling the wrong monitor. Note that
+/// identical monitors have the same description, so a mismatch between them can't be detected.
+fn associate_monitors(
+    physical_monitors: Vec<(WrappedPhysicalMonitor, String)>,
+    display_devices: Vec<DISPLAY_DEVICEW>,
+) -> Result<Vec<(WrappedPhysicalMonitor, DISPLAY_DEVICEW)>, SysError> {
+    if display_devices.len() != physical_monitors.len() {
+        return Err(SysError::EnumerationMismatch);
+    }
+    let in_order = physical_monitors
+        .iter()
+        .zip(&display_devices)
+        .all(|((_, description), device)| *description == wchar_to_string(&device.DeviceString));
+    if in_order {
+        return Ok(physical_monitors
+            .into_iter()
+            .map(|(physical_monitor, _)| physical_monitor)
+            .zip(display_devices)
+            .collect());
+    }
+    let mut physical_monitors = physical_monitors.into_iter().map(Some).collect::<Vec<_>>();
+    display_devices
+        .into_iter()
+        .map(|device| {
+            let description = wchar_to_string(&device.DeviceString);
+            let mut candidates = physical_monitors
+                .iter_mut()
+                .filter(|p| matches!(p, Some((_, d)) if *d == description));
+            match (candidates.next(), candidates.next()) {
+                (Some(candidate), None) => Ok((candidate.take().unwrap().0, device)),
+                _ => Err(SysError::AmbiguousMonitorAssociation),
+            }
+        })
+        .collect()
+}
+
+/// Gets the monitor info of a `HMONITOR`, which includes its GDI device name and whether it is the
+/// primary monitor.
+unsafe fn get_monitor_info(hmonitor: HMONITOR) -> Result<MONITORINFOEXW, SysError> {
     let mut info = MONITORINFOEXW::default();
     info.monitorInfo.cbSize = size_of::<MONITORINFOEXW>() as u32;
     let info_ptr = &mut info as *mut _ as *mut MONITORINFO;
     GetMonitorInfoW(hmonitor, info_ptr)
         .ok()
         .map_err(SysError::GetMonitorInfoFailed)?;
-    Ok((0..)
+    Ok(info)
+}
+
+/// Gets the list of display devices that belong to a `HMONITOR`, given its monitor info.\
+/// Due to the `EDD_GET_DEVICE_INTERFACE_NAME` flag, the `DISPLAY_DEVICEW` will contain the DOS
+/// device path for each monitor in the `DeviceID` field.\
+/// Note: Connected but inactive displays have been filtered out.\
+/// `EnumDisplayDevicesW` fails both past the last device and on errors, without setting a last
+/// error to tell them apart. It has been seen to fail for the first device while the display
+/// configuration changes (e.g. during a resolution change), which made the monitor vanish. With
+/// `retry`, the enumeration is retried once after a delay if none is found. This is only done by
+/// `brightness_devices_retry`, as monitors without display devices (e.g. some virtual displays)
+/// would slow down every enumeration.
+unsafe fn get_display_devices_from_monitor_info(
+    info: &MONITORINFOEXW,
+    retry: bool,
+) -> Vec<DISPLAY_DEVICEW> {
+    let mut devices = enum_display_devices(info);
+    if devices.is_empty() && retry {
+        std::thread::sleep(DISPLAY_DEVICES_RETRY_DELAY);
+        devices = enum_display_devices(info);
+    }
+    #[cfg(feature = "tracing")]
+    if devices.is_empty() {
+        tracing::warn!(
+            monitor = %wchar_to_string(&info.szDevice),
+            "No display device found for monitor",
+        );
+    }
+    devices
+        .into_iter()
+        .filter(|device| flag_set(device.StateFlags, DISPLAY_DEVICE_ACTIVE))
+        .collect()
+}
+
+const DISPLAY_DEVICES_RETRY_DELAY: Duration = Duration::from_millis(50);
+
+/// Enumerates the display devices of a monitor, active or not, until `EnumDisplayDevicesW` fails.
+unsafe fn enum_display_devices(info: &MONITORINFOEXW) -> Vec<DISPLAY_DEVICEW> {
+    (0..)
         .map_while(|device_number| {
             let mut device = DISPLAY_DEVICEW {
                 cb: size_of::<DISPLAY_DEVICEW>() as u32,
@@ -329,10 +1376,9 @@ unsafe fn get_display_devices_from_hmonitor(
                 EDD_GET_DEVICE_INTERFACE_NAME,
             )
             .as_bool()
-            .then(|| device)
+            .then_some(device)
         })
-        .filter(|device| flag_set(device.StateFlags, DISPLAY_DEVICE_ACTIVE))
-        .collect())
+        .collect()
 }
 
 /// Opens and returns a file handle for a display device using its DOS device path.\
@@ -369,6 +1415,8 @@ unsafe fn get_file_handle_for_display_device(
 pub(crate) enum SysError {
     #[error("Failed to enumerate device monitors")]
     EnumDisplayMonitorsFailed(#[source] WinError),
+    #[error("Failed to enumerate all device monitors")]
+    EnumDisplayMonitorsIncomplete(#[source] WinError),
     #[error("Failed to get display config buffer sizes")]
     GetDisplayConfigBufferSizesFailed(#[source] WinError),
     #[error("Failed to query display config")]
@@ -377,6 +1425,8 @@ pub(crate) enum SysError {
     DisplayConfigGetDeviceInfoFailed(#[source] WinError),
     #[error("Failed to get monitor info")]
     GetMonitorInfoFailed(#[source] WinError),
+    #[error("Failed to get the cursor position")]
+    GettingCursorPositionFailed(#[source] WinError),
     #[error("Failed to get physical monitors from the HMONITOR")]
     GetPhysicalMonitorsFailed(#[source] WinError),
     #[error(
@@ -389,80 +1439,288 @@ pub(crate) enum SysError {
      were connected while loading devices"
     )]
     DeviceInfoMissing,
+    #[error(
+    "Unable to reliably associate physical monitors with display devices, this can happen with \
+     monitors daisy-chained through a DisplayPort MST hub"
+    )]
+    AmbiguousMonitorAssociation,
     #[error("Failed to open monitor interface handle (CreateFileW)")]
     OpeningMonitorDeviceInterfaceHandleFailed {
         device_name: String,
         source: WinError,
     },
-    #[error("Failed to query supported brightness (IOCTL)")]
+    #[error("Failed to query supported brightness of {monitor} (IOCTL)")]
     IoctlQuerySupportedBrightnessFailed {
-        device_name: String,
+        monitor: MonitorName,
         source: WinError,
     },
-    #[error("Failed to query display brightness (IOCTL)")]
+    #[error(
+        "More than {size} bytes of supported brightness levels were reported for {monitor} (IOCTL)"
+    )]
+    IoctlQuerySupportedBrightnessTooLarge { monitor: MonitorName, size: usize },
+    #[error("No supported brightness level was reported for {monitor} (IOCTL)")]
+    IoctlQuerySupportedBrightnessEmpty { monitor: MonitorName },
+    #[error("Failed to query display brightness of {monitor} (IOCTL)")]
     IoctlQueryDisplayBrightnessFailed {
-        device_name: String,
+        monitor: MonitorName,
         source: WinError,
     },
-    #[error("Unexpected response when querying display brightness (IOCTL)")]
-    IoctlQueryDisplayBrightnessUnexpectedResponse { device_name: String },
-    #[error("Failed to get monitor brightness (DDCCI)")]
+    #[error("Unexpected response when querying display brightness of {monitor} (IOCTL)")]
+    IoctlQueryDisplayBrightnessUnexpectedResponse { monitor: MonitorName },
+    #[error("Failed to get brightness of {monitor} (DDCCI)")]
     GettingMonitorBrightnessFailed {
-        device_name: String,
+        monitor: MonitorName,
         source: WinError,
     },
-    #[error("Failed to set monitor brightness (IOCTL)")]
+    #[error(
+        "Invalid brightness of {monitor} (DDCCI): current value {current} is not between {min} \
+         and {max}"
+    )]
+    GettingMonitorBrightnessInvalid {
+        monitor: MonitorName,
+        min: u32,
+        current: u32,
+        max: u32,
+    },
+    #[error("Failed to set brightness of {monitor} (IOCTL)")]
     IoctlSetBrightnessFailed {
-        device_name: String,
+        monitor: MonitorName,
         source: WinError,
     },
-    #[error("Failed to set monitor brightness (DDCCI)")]
+    #[error("Failed to set brightness of {monitor} (DDCCI)")]
     SettingBrightnessFailed {
-        device_name: String,
+        monitor: MonitorName,
         source: WinError,
     },
-}
-
-impl From<SysError> for Error {
-    fn from(e: SysError) -> Self {
-        match &e {
-            SysError::EnumerationMismatch
-            | SysError::DeviceInfoMissing
-            | SysError::GetDisplayConfigBufferSizesFailed(..)
-            | SysError::QueryDisplayConfigFailed(..)
-            | SysError::DisplayConfigGetDeviceInfoFailed(..)
-            | SysError::GetPhysicalMonitorsFailed(..)
-            | SysError::EnumDisplayMonitorsFailed(..)
-            | SysError::GetMonitorInfoFailed(..)
-            | SysError::OpeningMonitorDeviceInterfaceHandleFailed { .. } => {
-                Error::ListingDevicesFailed(Box::new(e))
-            }
-            SysError::IoctlQuerySupportedBrightnessFailed { device_name, .. }
-            | SysError::IoctlQueryDisplayBrightnessFailed { device_name, .. }
-            | SysError::IoctlQueryDisplayBrightnessUnexpectedResponse { device_name }
-            | SysError::GettingMonitorBrightnessFailed { device_name, .. } => {
-                Error::GettingDeviceInfoFailed {
-                    device: device_name.clone(),
-                    source: Box::new(e),
+    #[error("{monitor} does not support DDC/CI")]
+    DdcciNotSupported {
+        monitor: MonitorName,
+        source: WinError,
+    },
+    #[error("Failed to probe DDC/CI support of {monitor}")]
+    ProbingDdcciFailed {
+        monitor: MonitorName,
+        source: WinError,
+    },
+    #[error("Failed to read EDID of {monitor} from the registry")]
+    ReadingEdidFailed {
+        monitor: MonitorName,
+        source: WinError,
+    },
+    #[error("Failed to get capabilities string of {monitor} (DDCCI)")]
+    GettingCapabilitiesStringFailed {
+        monitor: MonitorName,
+        source: WinError,
+    },
+    #[error("Failed to get VCP version of {monitor} (DDCCI)")]
+    GettingVcpVersionFailed {
+        monitor: MonitorName,
+        source: WinError,
+    },
+    #[error("Failed to get display preset of {monitor} (DDCCI)")]
+    GettingDisplayPresetFailed {
+        monitor: MonitorName,
+        source: WinError,
+    },
+    #[error("Failed to set display preset of {monitor} (DDCCI)")]
+    SettingDisplayPresetFailed {
+        monitor: MonitorName,
+        source: WinError,
+    },
+    #[error("Failed to get new control value of {monitor} (DDCCI)")]
+    GettingNewControlValueFailed {
+        monitor: MonitorName,
+        source: WinError,
+    },
+    #[error("Failed to reset new control value of {monitor} (DDCCI)")]
+    ResettingNewControlValueFailed {
+        monitor: MonitorName,
+        source: WinError,
+    },
+    #[error("Failed to get capabilities of {monitor} (DDCCI)")]
+    GettingMonitorCapabilitiesFailed {
+        monitor: MonitorName,
+        source: WinError,
+    },
+    #[error("Failed to get color temperature of {monitor} (DDCCI)")]
+    GettingColorTemperatureFailed {
+        monitor: MonitorName,
+        source: WinError,
+    },
+    #[error("Failed to set color temperature of {monitor} (DDCCI)")]
+    SettingColorTemperatureFailed {
+        monitor: MonitorName,
+        source: WinError,
+    },
+    #[error("{monitor} was not found again after its handle went stale")]
+    DeviceDisconnected { monitor: MonitorName },
+    #[error("Unexpected device path format for {monitor}")]
+    UnexpectedDevicePath { monitor: MonitorName },
+    #[error("No active mode found for {monitor} in the display configuration")]
+    ActiveModeMissing { monitor: MonitorName },
+    #[error("Failed to get advanced color info of {monitor}")]
+    GettingAdvancedColorInfoFailed {
+        monitor: MonitorName,
+        source: WinError,
+    },
+    #[error("Unexpected GDI device name format for {monitor}")]
+    UnexpectedGdiDeviceName { monitor: MonitorName },
+    #[error("{monitor} applied brightness {applied} instead of {requested} (DDCCI)")]
+    SettingBrightnessClamped {
+        monitor: MonitorName,
+        requested: u32,
+        applied: u32,
+    },
+}
+
+impl SysError {
+    /// Returns whether the error is caused by monitors being connected or disconnected while
+    /// devices were listed, or by the system failing to enumerate all monitors, in which case
+    /// listing them again may succeed.
+    pub(crate) fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            SysError::EnumerationMismatch
+                | SysError::DeviceInfoMissing
+                | SysError::EnumDisplayMonitorsIncomplete(..)
+        )
+    }
+
+    /// Returns the category of the error, derived from the `HRESULT` of its source if any.
+    pub(crate) fn kind(&self) -> ErrorKind {
+        if self.is_transient() {
+            return ErrorKind::Transient;
+        }
+        match self {
+            SysError::DdcciNotSupported { .. }
+            | SysError::IoctlQuerySupportedBrightnessEmpty { .. } => return ErrorKind::Unsupported,
+            _ => {}
+        }
+        let code = match self.source().and_then(|s| s.downcast_ref::<WinError>()) {
+            Some(source) => source.code(),
+            None => return ErrorKind::Other,
+        };
+        match code {
+            E_HANDLE | ERROR_GRAPHICS_INVALID_PHYSICAL_MONITOR_HANDLE => ErrorKind::StaleHandle,
+            ERROR_GRAPHICS_DDCCI_VCP_NOT_SUPPORTED
+            | ERROR_GRAPHICS_I2C_NOT_SUPPORTED
+            | ERROR_GRAPHICS_I2C_DEVICE_DOES_NOT_EXIST => ErrorKind::Unsupported,
+            code if code == ERROR_NOT_SUPPORTED.to_hresult() => ErrorKind::Unsupported,
+            ERROR_GRAPHICS_I2C_ERROR_TRANSMITTING_DATA
+            | ERROR_GRAPHICS_I2C_ERROR_RECEIVING_DATA
+            | ERROR_GRAPHICS_DDCCI_INVALID_DATA
+            | ERROR_GRAPHICS_DDCCI_MONITOR_RETURNED_INVALID_TIMING_STATUS_BYTE
+            | ERROR_GRAPHICS_DDCCI_INVALID_MESSAGE_COMMAND
+            | ERROR_GRAPHICS_DDCCI_INVALID_MESSAGE_LENGTH
+            | ERROR_GRAPHICS_DDCCI_INVALID_MESSAGE_CHECKSUM => ErrorKind::Transient,
+            _ => ErrorKind::Other,
+        }
+    }
+
+    /// Returns whether a brightness operation failed because a monitor handle is no longer valid,
+    /// which happens after the system sleeps or the monitor is power-cycled.
+    fn is_stale_handle(&self) -> bool {
+        let brightness_operation = matches!(
+            self,
+            SysError::IoctlQuerySupportedBrightnessFailed { .. }
+                | SysError::IoctlQueryDisplayBrightnessFailed { .. }
+                | SysError::IoctlSetBrightnessFailed { .. }
+                | SysError::GettingMonitorBrightnessFailed { .. }
+                | SysError::SettingBrightnessFailed { .. }
+        );
+        brightness_operation && self.kind() == ErrorKind::StaleHandle
+    }
+}
+
+impl From<SysError> for Error {
+    fn from(e: SysError) -> Self {
+        match &e {
+            SysError::EnumerationMismatch
+            | SysError::DeviceInfoMissing
+            | SysError::AmbiguousMonitorAssociation
+            | SysError::GetDisplayConfigBufferSizesFailed(..)
+            | SysError::QueryDisplayConfigFailed(..)
+            | SysError::DisplayConfigGetDeviceInfoFailed(..)
+            | SysError::GetPhysicalMonitorsFailed(..)
+            | SysError::EnumDisplayMonitorsFailed(..)
+            | SysError::EnumDisplayMonitorsIncomplete(..)
+            | SysError::GetMonitorInfoFailed(..)
+            | SysError::GettingCursorPositionFailed(..)
+            | SysError::OpeningMonitorDeviceInterfaceHandleFailed { .. } => {
+                Error::ListingDevicesFailed(Box::new(e))
+            }
+            SysError::IoctlQuerySupportedBrightnessFailed { monitor, .. }
+            | SysError::IoctlQuerySupportedBrightnessTooLarge { monitor, .. }
+            | SysError::IoctlQuerySupportedBrightnessEmpty { monitor }
+            | SysError::IoctlQueryDisplayBrightnessFailed { monitor, .. }
+            | SysError::IoctlQueryDisplayBrightnessUnexpectedResponse { monitor }
+            | SysError::GettingMonitorBrightnessFailed { monitor, .. }
+            | SysError::GettingMonitorBrightnessInvalid { monitor, .. }
+            | SysError::ReadingEdidFailed { monitor, .. }
+            | SysError::UnexpectedDevicePath { monitor }
+            | SysError::UnexpectedGdiDeviceName { monitor }
+            | SysError::GettingAdvancedColorInfoFailed { monitor, .. }
+            | SysError::ActiveModeMissing { monitor }
+            | SysError::DdcciNotSupported { monitor, .. }
+            | SysError::ProbingDdcciFailed { monitor, .. }
+            | SysError::GettingMonitorCapabilitiesFailed { monitor, .. }
+            | SysError::GettingCapabilitiesStringFailed { monitor, .. }
+            | SysError::GettingVcpVersionFailed { monitor, .. }
+            | SysError::GettingDisplayPresetFailed { monitor, .. }
+            | SysError::GettingNewControlValueFailed { monitor, .. }
+            | SysError::ResettingNewControlValueFailed { monitor, .. }
+            | SysError::GettingColorTemperatureFailed { monitor, .. } => {
+                Error::GettingDeviceInfoFailed {
+                    device: monitor.device_name.clone(),
+                    source: Box::new(e),
                 }
             }
-            SysError::SettingBrightnessFailed { device_name, .. }
-            | SysError::IoctlSetBrightnessFailed { device_name, .. } => {
+            SysError::SettingBrightnessFailed { monitor, .. }
+            | SysError::IoctlSetBrightnessFailed { monitor, .. }
+            | SysError::SettingColorTemperatureFailed { monitor, .. }
+            | SysError::SettingDisplayPresetFailed { monitor, .. } => {
                 Error::SettingBrightnessFailed {
-                    device: device_name.clone(),
+                    device: monitor.device_name.clone(),
                     source: Box::new(e),
                 }
             }
+            SysError::DeviceDisconnected { monitor } => Error::DeviceDisconnected {
+                device: monitor.device_name.clone(),
+            },
+            SysError::SettingBrightnessClamped {
+                monitor,
+                requested,
+                applied,
+            } => Error::SettingClamped {
+                device: monitor.device_name.clone(),
+                requested: *requested,
+                applied: *applied,
+            },
         }
     }
 }
 
+fn luid_to_u64(luid: LUID) -> u64 {
+    (u64::from(luid.HighPart as u32) << 32) | u64::from(luid.LowPart)
+}
+
+fn u64_to_luid(luid: u64) -> LUID {
+    LUID {
+        LowPart: luid as u32,
+        HighPart: (luid >> 32) as i32,
+    }
+}
+
 fn wchar_to_string(s: &[u16]) -> String {
     let end = s.iter().position(|&x| x == 0).unwrap_or(s.len());
     let truncated = &s[0..end];
     OsString::from_wide(truncated).to_string_lossy().into()
 }
 
+fn to_wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(once(0)).collect()
+}
+
 fn to_win32_error(status: i32) -> WIN32_ERROR {
     WIN32_ERROR(status as u32)
 }
@@ -474,6 +1732,10 @@ where
     to_win32_error(status).ok().map_err(f)
 }
 
+/// Brightness values reported by a monitor.\
+/// `min` is the value of the lowest brightness and `max` the value of the highest brightness. Some
+/// monitors report an inverted range where `min > max`, i.e. higher values are darker; the mapping
+/// to and from percentages is inverted accordingly.
 #[derive(Debug, Default)]
 struct DdcciBrightnessValues {
     min: u32,
@@ -482,125 +1744,679 @@ struct DdcciBrightnessValues {
 }
 
 impl DdcciBrightnessValues {
+    /// Returns the values if they are consistent, i.e. `current` lies between `min` and `max`.\
+    /// DDC/CI has little error detection, so corrupted readings are possible.
+    fn checked(min: u32, current: u32, max: u32) -> Option<Self> {
+        let in_range = min.min(max) <= current && current <= min.max(max);
+        in_range.then_some(DdcciBrightnessValues { min, current, max })
+    }
+
     fn get_current_percentage(&self) -> u32 {
-        let normalised_max = (self.max - self.min) as f64;
-        let normalised_current = (self.current - self.min) as f64;
-        (normalised_current / normalised_max * 100.0).round() as u32
+        let range = f64::from(self.max) - f64::from(self.min);
+        if range == 0.0 {
+            return 0;
+        }
+        let fraction = (f64::from(self.current) - f64::from(self.min)) / range;
+        (fraction * 100.0).round().clamp(0.0, 100.0) as u32
     }
 
     fn percentage_to_current(&self, percentage: u32) -> u32 {
-        let normalised_max = (self.max - self.min) as f64;
-        let fraction = percentage as f64 / 100.0;
-        let normalised_current = fraction * normalised_max;
-        normalised_current.round() as u32 + self.min
+        let range = f64::from(self.max) - f64::from(self.min);
+        let fraction = f64::from(percentage.min(100)) / 100.0;
+        // This lies between `min` and `max`, so it fits in a `u32`.
+        (f64::from(self.min) + fraction * range).round() as u32
     }
 }
 
 fn ddcci_get_monitor_brightness(
     device: &BlockingDeviceImpl,
 ) -> Result<DdcciBrightnessValues, SysError> {
-    unsafe {
-        let mut v = DdcciBrightnessValues::default();
+    let mut v = DdcciBrightnessValues::default();
+    let result = device.timed(|| unsafe {
         BOOL(GetMonitorBrightness(
-            device.physical_monitor.0,
+            device.handles().physical_monitor.0,
             &mut v.min,
             &mut v.current,
             &mut v.max,
         ))
         .ok()
-        .map(|_| v)
-        .map_err(|e| SysError::GettingMonitorBrightnessFailed {
-            device_name: device.device_name.clone(),
-            source: e,
+    });
+    trace_get_vcp(
+        device,
+        VCP_BRIGHTNESS,
+        result.as_ref().map(|_| (false, v.current, v.max)),
+    );
+    let (min, current, max) = result.map(|_| (v.min, v.current, v.max)).or_else(|e| {
+        // The high-level API fails if the capabilities reported by the monitor are malformed
+        // or omit brightness, even though the monitor may still answer VCP requests.
+        get_vcp_feature(device, VCP_BRIGHTNESS)
+            .map(|(_, current, max)| (0, current, max))
+            .map_err(|_| SysError::GettingMonitorBrightnessFailed {
+                monitor: device.monitor_name(),
+                source: e,
+            })
+    })?;
+    DdcciBrightnessValues::checked(min, current, max).ok_or_else(|| {
+        SysError::GettingMonitorBrightnessInvalid {
+            monitor: device.monitor_name(),
+            min,
+            current,
+            max,
+        }
+    })
+}
+
+/// Reads a VCP feature directly, returning whether it is momentary, its current value and its
+/// maximum value.
+fn get_vcp_feature(device: &BlockingDeviceImpl, code: u8) -> Result<(bool, u32, u32), WinError> {
+    let result = device.timed(|| unsafe {
+        let (mut code_type, mut current, mut max) = (MC_VCP_CODE_TYPE::default(), 0, 0);
+        BOOL(GetVCPFeatureAndVCPFeatureReply(
+            device.handles().physical_monitor.0,
+            code,
+            &mut code_type,
+            &mut current,
+            &mut max,
+        ))
+        .ok()
+        .map(|_| (code_type == MC_MOMENTARY, current, max))
+    });
+    trace_get_vcp(device, code, result.as_ref().map(|&v| v));
+    result
+}
+
+/// Writes a VCP feature directly.
+fn set_vcp_feature(device: &BlockingDeviceImpl, code: u8, value: u32) -> Result<(), WinError> {
+    let result = device.timed(|| unsafe {
+        BOOL(SetVCPFeature(
+            device.handles().physical_monitor.0,
+            code,
+            value,
+        ))
+        .ok()
+    });
+    trace_set_vcp(device, code, value, result.as_ref().map(|_| ()));
+    result
+}
+
+/// VCP code of the new control value feature, which signals changes made from the on-screen menu.
+const VCP_NEW_CONTROL_VALUE: u8 = 0x02;
+/// Value of the new control value feature when no control was changed.
+const NO_NEW_CONTROL_VALUE: u32 = 0x01;
+/// Value of the new control value feature when controls were changed.
+const NEW_CONTROL_VALUE: u32 = 0x02;
+/// VCP code of the brightness feature.
+const VCP_BRIGHTNESS: u8 = 0x10;
+/// VCP code of the display application feature, which selects the display preset.
+const VCP_DISPLAY_PRESET: u8 = 0xDC;
+/// VCP code of the VCP version feature.
+const VCP_VERSION: u8 = 0xDF;
+
+/// Returns the capabilities string of a monitor, reading it on first use.
+fn ddcci_get_capabilities(device: &BlockingDeviceImpl) -> Result<String, SysError> {
+    if let Some(capabilities) = device.capabilities.read().unwrap().as_ref() {
+        return Ok(capabilities.clone());
+    }
+    let capabilities = ddcci_read_capabilities(device)?;
+    *device.capabilities.write().unwrap() = Some(capabilities.clone());
+    Ok(capabilities)
+}
+
+/// Reads the capabilities string of a monitor.\
+/// The Monitor Configuration API takes care of reassembling the string from the fragments sent by
+/// the monitor.
+fn ddcci_read_capabilities(device: &BlockingDeviceImpl) -> Result<String, SysError> {
+    let error = |source| SysError::GettingCapabilitiesStringFailed {
+        monitor: device.monitor_name(),
+        source,
+    };
+    unsafe {
+        let mut len = 0;
+        BOOL(GetCapabilitiesStringLength(
+            device.handles().physical_monitor.0,
+            &mut len,
+        ))
+        .ok()
+        .map_err(error)?;
+        let mut buffer = vec![0u8; len as usize];
+        BOOL(CapabilitiesRequestAndCapabilitiesReply(
+            device.handles().physical_monitor.0,
+            &mut buffer,
+        ))
+        .ok()
+        .map_err(error)?;
+        // The length includes the terminating null character.
+        let end = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+        Ok(String::from_utf8_lossy(&buffer[..end]).into_owned())
+    }
+}
+
+pub(crate) fn ddcci_get_vcp_version(device: &BlockingDeviceImpl) -> Result<(u8, u8), SysError> {
+    let (_, current, _) = get_vcp_feature(device, VCP_VERSION).map_err(|source| {
+        SysError::GettingVcpVersionFailed {
+            monitor: device.monitor_name(),
+            source,
+        }
+    })?;
+    Ok(((current >> 8) as u8, current as u8))
+}
+
+pub(crate) fn ddcci_supported_display_presets(
+    device: &BlockingDeviceImpl,
+) -> Result<Vec<DisplayPreset>, SysError> {
+    if device.is_internal() {
+        return Ok(Vec::new());
+    }
+    let capabilities = Capabilities::parse(&ddcci_get_capabilities(device)?);
+    Ok(capabilities
+        .vcp_features
+        .iter()
+        .find(|feature| feature.code == VCP_DISPLAY_PRESET)
+        .map(|feature| {
+            feature
+                .values
+                .iter()
+                .map(|&value| DisplayPreset::from_vcp(value.into()))
+                .collect()
         })
+        .unwrap_or_default())
+}
+
+pub(crate) fn ddcci_get_display_preset(
+    device: &BlockingDeviceImpl,
+) -> Result<DisplayPreset, SysError> {
+    let (_, current, _) = get_vcp_feature(device, VCP_DISPLAY_PRESET).map_err(|source| {
+        SysError::GettingDisplayPresetFailed {
+            monitor: device.monitor_name(),
+            source,
+        }
+    })?;
+    Ok(DisplayPreset::from_vcp(current as u16))
+}
+
+pub(crate) fn ddcci_set_display_preset(
+    device: &BlockingDeviceImpl,
+    preset: DisplayPreset,
+) -> Result<(), SysError> {
+    set_vcp_feature(device, VCP_DISPLAY_PRESET, preset.to_vcp().into()).map_err(|source| {
+        SysError::SettingDisplayPresetFailed {
+            monitor: device.monitor_name(),
+            source,
+        }
+    })
+}
+
+pub(crate) fn ddcci_take_new_control_value(
+    device: &BlockingDeviceImpl,
+) -> Result<Option<bool>, SysError> {
+    if device.is_internal() {
+        return Ok(None);
+    }
+    let current = match get_vcp_feature(device, VCP_NEW_CONTROL_VALUE) {
+        Ok((_, current, _)) => current,
+        Err(e) if e.code() == ERROR_GRAPHICS_DDCCI_VCP_NOT_SUPPORTED => return Ok(None),
+        Err(source) => {
+            return Err(SysError::GettingNewControlValueFailed {
+                monitor: device.monitor_name(),
+                source,
+            })
+        }
+    };
+    // Other values, such as 0xFF for monitors without user controls, mean that nothing changed.
+    if current != NEW_CONTROL_VALUE {
+        return Ok(Some(false));
+    }
+    set_vcp_feature(device, VCP_NEW_CONTROL_VALUE, NO_NEW_CONTROL_VALUE).map_err(|source| {
+        SysError::ResettingNewControlValueFailed {
+            monitor: device.monitor_name(),
+            source,
+        }
+    })?;
+    Ok(Some(true))
+}
+
+/// Returns whether a device looks like a monitor that is no longer connected.\
+/// Ghost monitors usually have the "Generic Non-PnP Monitor" driver, which means that no EDID was
+/// read, and no I2C channel. Real monitors with DDC/CI disabled still have an EDID.
+pub(crate) fn is_ghost(device: &BlockingDeviceImpl) -> Result<bool, SysError> {
+    if device.is_internal() {
+        return Ok(false);
+    }
+    if device.output_technology == DISPLAYCONFIG_OUTPUT_TECHNOLOGY_OTHER {
+        return Ok(true);
+    }
+    if read_edid(device)?.is_some() {
+        return Ok(false);
+    }
+    match ddcci_probe(device) {
+        Ok(responds) => Ok(!responds),
+        Err(SysError::DdcciNotSupported { .. }) => Ok(true),
+        // The monitor answered, if with corrupted data.
+        Err(e @ SysError::ProbingDdcciFailed { .. }) if e.kind() == ErrorKind::Transient => {
+            Ok(false)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Reads the brightness VCP feature to check that the monitor responds to DDC/CI.\
+/// Communication errors are retried once, as they are often glitches. If the monitor still does not
+/// answer, DDC/CI is usually disabled in its on-screen menu. If it answers with corrupted data,
+/// DDC/CI is enabled but unreliable, and the transient error is returned. Errors about the I2C
+/// channel itself mean that DDC/CI is not supported at all.
+pub(crate) fn ddcci_probe(device: &BlockingDeviceImpl) -> Result<bool, SysError> {
+    if device.is_internal() {
+        return Ok(false);
+    }
+    let read = || get_vcp_feature(device, VCP_BRIGHTNESS).map(|_| ());
+    let result = read().or_else(|e| match e.code() {
+        ERROR_GRAPHICS_I2C_ERROR_TRANSMITTING_DATA
+        | ERROR_GRAPHICS_I2C_ERROR_RECEIVING_DATA
+        | ERROR_GRAPHICS_DDCCI_INVALID_DATA
+        | ERROR_GRAPHICS_DDCCI_MONITOR_RETURNED_INVALID_TIMING_STATUS_BYTE
+        | ERROR_GRAPHICS_DDCCI_INVALID_MESSAGE_COMMAND
+        | ERROR_GRAPHICS_DDCCI_INVALID_MESSAGE_LENGTH
+        | ERROR_GRAPHICS_DDCCI_INVALID_MESSAGE_CHECKSUM => read(),
+        _ => Err(e),
+    });
+    match result {
+        Ok(()) => Ok(true),
+        Err(e) => match e.code() {
+            // The monitor answered, it just doesn't know about brightness.
+            ERROR_GRAPHICS_DDCCI_VCP_NOT_SUPPORTED => Ok(true),
+            ERROR_GRAPHICS_I2C_ERROR_TRANSMITTING_DATA
+            | ERROR_GRAPHICS_I2C_ERROR_RECEIVING_DATA => Ok(false),
+            ERROR_GRAPHICS_I2C_NOT_SUPPORTED | ERROR_GRAPHICS_I2C_DEVICE_DOES_NOT_EXIST => {
+                Err(SysError::DdcciNotSupported {
+                    monitor: device.monitor_name(),
+                    source: e,
+                })
+            }
+            _ => Err(SysError::ProbingDdcciFailed {
+                monitor: device.monitor_name(),
+                source: e,
+            }),
+        },
     }
 }
 
 fn ddcci_set_monitor_brightness(device: &BlockingDeviceImpl, value: u32) -> Result<(), SysError> {
-    unsafe {
-        BOOL(SetMonitorBrightness(device.physical_monitor.0, value))
-            .ok()
-            .map_err(|e| SysError::SettingBrightnessFailed {
-                device_name: device.device_name.clone(),
+    let result = device.timed(|| unsafe {
+        BOOL(SetMonitorBrightness(
+            device.handles().physical_monitor.0,
+            value,
+        ))
+        .ok()
+    });
+    trace_set_vcp(device, VCP_BRIGHTNESS, value, result.as_ref().map(|_| ()));
+    result.or_else(|e| {
+        // See `ddcci_get_monitor_brightness`.
+        set_vcp_feature(device, VCP_BRIGHTNESS, value).map_err(|_| {
+            SysError::SettingBrightnessFailed {
+                monitor: device.monitor_name(),
                 source: e,
-            })
+            }
+        })
+    })
+}
+
+pub(crate) fn ddcci_supported_color_temperatures(
+    device: &BlockingDeviceImpl,
+) -> Result<Vec<ColorTemperature>, SysError> {
+    let (mut capabilities, mut supported) = (0, 0);
+    unsafe {
+        BOOL(GetMonitorCapabilities(
+            device.handles().physical_monitor.0,
+            &mut capabilities,
+            &mut supported,
+        ))
+        .ok()
+        .map_err(|e| SysError::GettingMonitorCapabilitiesFailed {
+            monitor: device.monitor_name(),
+            source: e,
+        })?;
+    }
+    Ok(COLOR_TEMPERATURES
+        .iter()
+        .filter(|&&(_, _, flag)| flag_set(supported, flag))
+        .map(|&(temperature, ..)| temperature)
+        .collect())
+}
+
+pub(crate) fn ddcci_get_color_temperature(
+    device: &BlockingDeviceImpl,
+) -> Result<Option<ColorTemperature>, SysError> {
+    let mut current = MC_COLOR_TEMPERATURE::default();
+    unsafe {
+        BOOL(GetMonitorColorTemperature(
+            device.handles().physical_monitor.0,
+            &mut current,
+        ))
+        .ok()
+        .map_err(|e| SysError::GettingColorTemperatureFailed {
+            monitor: device.monitor_name(),
+            source: e,
+        })?;
+    }
+    Ok(COLOR_TEMPERATURES
+        .iter()
+        .find(|&&(_, value, _)| value == current)
+        .map(|&(temperature, ..)| temperature))
+}
+
+pub(crate) fn ddcci_set_color_temperature(
+    device: &BlockingDeviceImpl,
+    temperature: ColorTemperature,
+) -> Result<(), SysError> {
+    let value = COLOR_TEMPERATURES
+        .iter()
+        .find(|&&(t, ..)| t == temperature)
+        .map(|&(_, value, _)| value)
+        .unwrap_or_default();
+    unsafe {
+        BOOL(SetMonitorColorTemperature(
+            device.handles().physical_monitor.0,
+            value,
+        ))
+        .ok()
+        .map_err(|e| SysError::SettingColorTemperatureFailed {
+            monitor: device.monitor_name(),
+            source: e,
+        })
+    }
+}
+
+/// Logs the DDC/CI packets of a "Get VCP Feature" transaction.\
+/// The Monitor Configuration API does not expose the bytes exchanged with the monitor, so the
+/// packets are reconstructed from the parameters and results of the API call.
+#[cfg(feature = "tracing")]
+fn trace_get_vcp<E: fmt::Display>(
+    device: &BlockingDeviceImpl,
+    code: u8,
+    result: Result<(bool, u32, u32), E>,
+) {
+    let request = crate::ddc::get_vcp_request(code);
+    match result {
+        Ok((momentary, current, max)) => tracing::trace!(
+            device = %device.device_name,
+            request = ?request,
+            reply = ?crate::ddc::get_vcp_reply(code, momentary, current as u16, max as u16),
+            "DDC/CI Get VCP Feature {:#04x}",
+            code,
+        ),
+        Err(e) => tracing::trace!(
+            device = %device.device_name,
+            request = ?request,
+            error = %e,
+            "DDC/CI Get VCP Feature {:#04x} failed",
+            code,
+        ),
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+fn trace_get_vcp<T, E>(_: &BlockingDeviceImpl, _: u8, _: Result<T, E>) {}
+
+/// Logs the DDC/CI packet of a "Set VCP Feature" request.\
+/// See `trace_get_vcp`.
+#[cfg(feature = "tracing")]
+fn trace_set_vcp<E: fmt::Display>(
+    device: &BlockingDeviceImpl,
+    code: u8,
+    value: u32,
+    result: Result<(), E>,
+) {
+    let request = crate::ddc::set_vcp_request(code, value as u16);
+    match result {
+        Ok(()) => tracing::trace!(
+            device = %device.device_name,
+            request = ?request,
+            "DDC/CI Set VCP Feature {:#04x}",
+            code,
+        ),
+        Err(e) => tracing::trace!(
+            device = %device.device_name,
+            request = ?request,
+            error = %e,
+            "DDC/CI Set VCP Feature {:#04x} failed",
+            code,
+        ),
     }
 }
 
+#[cfg(not(feature = "tracing"))]
+fn trace_set_vcp<E>(_: &BlockingDeviceImpl, _: u8, _: u32, _: Result<(), E>) {}
+
 /// Each level is a value from 0 to 100
 #[derive(Debug)]
 struct IoctlSupportedBrightnessLevels(Vec<u8>);
 
 impl IoctlSupportedBrightnessLevels {
-    fn get_nearest(&self, percentage: u32) -> u8 {
-        self.0
-            .iter()
-            .copied()
-            .min_by_key(|&num| (num as i64 - percentage as i64).abs())
-            .unwrap_or(0)
+    /// Returns the level to use for a percentage, see [`select_level`].
+    fn select(&self, percentage: u32, rounding: Rounding, nonzero: bool) -> u8 {
+        select_level(&self.0, percentage, rounding, nonzero)
+    }
+}
+
+/// Returns the raw value of the lowest brightness of a device.
+pub(crate) fn brightness_raw_min(device: &BlockingDeviceImpl) -> Result<u32, SysError> {
+    if device.is_internal() {
+        let supported = ioctl_query_supported_brightness(device)?;
+        return Ok(supported.0.iter().copied().min().unwrap_or(0).into());
+    }
+    Ok(ddcci_get_monitor_brightness(device)?.min)
+}
+
+pub(crate) fn supported_brightness_levels(
+    device: &BlockingDeviceImpl,
+) -> Result<Vec<u8>, SysError> {
+    if !device.is_internal() {
+        return Ok(Vec::new());
     }
+    Ok(ioctl_query_supported_brightness(device)?.0)
 }
 
+/// Size of the buffer first used to query supported brightness levels, which holds all distinct
+/// levels.
+const SUPPORTED_BRIGHTNESS_BUFFER_SIZE: usize = 256;
+
+/// Largest buffer used to query supported brightness levels, in case a driver reports levels more
+/// than once.
+const SUPPORTED_BRIGHTNESS_BUFFER_MAX_SIZE: usize = 64 * 1024;
+
 fn ioctl_query_supported_brightness(
     device: &BlockingDeviceImpl,
 ) -> Result<IoctlSupportedBrightnessLevels, SysError> {
     unsafe {
         let mut bytes_returned = 0;
-        let mut out_buffer = Vec::<u8>::with_capacity(256);
-        DeviceIoControl(
-            device.file_handle.0,
-            IOCTL_VIDEO_QUERY_SUPPORTED_BRIGHTNESS,
-            ptr::null_mut(),
-            0,
-            out_buffer.as_mut_ptr() as *mut c_void,
-            out_buffer.capacity() as u32,
-            &mut bytes_returned,
-            ptr::null_mut(),
-        )
-        .ok()
-        .map(|_| {
-            out_buffer.set_len(bytes_returned as usize);
-            IoctlSupportedBrightnessLevels(out_buffer)
+        let mut out_buffer = Vec::<u8>::with_capacity(SUPPORTED_BRIGHTNESS_BUFFER_SIZE);
+        loop {
+            let result = device
+                .timed(|| {
+                    DeviceIoControl(
+                        device.handles().file_handle.0,
+                        IOCTL_VIDEO_QUERY_SUPPORTED_BRIGHTNESS,
+                        ptr::null_mut(),
+                        0,
+                        out_buffer.as_mut_ptr() as *mut c_void,
+                        out_buffer.capacity() as u32,
+                        &mut bytes_returned,
+                        ptr::null_mut(),
+                    )
+                })
+                .ok();
+            match result {
+                Ok(()) => break,
+                Err(e)
+                    if e.code() == ERROR_INSUFFICIENT_BUFFER.to_hresult()
+                        || e.code() == ERROR_MORE_DATA.to_hresult() =>
+                {
+                    // Drivers may report the required size, otherwise the buffer is doubled.
+                    let size = (bytes_returned as usize).max(out_buffer.capacity() * 2);
+                    if out_buffer.capacity() >= SUPPORTED_BRIGHTNESS_BUFFER_MAX_SIZE {
+                        return Err(SysError::IoctlQuerySupportedBrightnessTooLarge {
+                            monitor: device.monitor_name(),
+                            size: out_buffer.capacity(),
+                        });
+                    }
+                    out_buffer = Vec::with_capacity(size.min(SUPPORTED_BRIGHTNESS_BUFFER_MAX_SIZE));
+                }
+                Err(e) => {
+                    return Err(SysError::IoctlQuerySupportedBrightnessFailed {
+                        monitor: device.monitor_name(),
+                        source: e,
+                    })
+                }
+            }
+        }
+        // Some virtual display drivers succeed without returning any level, which would otherwise
+        // be treated as only supporting 0 and turn the display black.
+        if bytes_returned == 0 {
+            return Err(SysError::IoctlQuerySupportedBrightnessEmpty {
+                monitor: device.monitor_name(),
+            });
+        }
+        out_buffer.set_len(bytes_returned as usize);
+        Ok(IoctlSupportedBrightnessLevels(out_buffer))
+    }
+}
+
+pub(crate) fn current_mode(device: &BlockingDeviceImpl) -> Result<DisplayMode, SysError> {
+    let (display_paths, display_modes) = unsafe { query_display_config()? };
+    let adapter_luid = u64_to_luid(device.adapter_luid);
+    display_paths
+        .iter()
+        .find(|path| {
+            path.targetInfo.adapterId == adapter_luid && path.targetInfo.id == device.target_id
         })
-        .map_err(|e| SysError::IoctlQuerySupportedBrightnessFailed {
-            device_name: device.device_name.clone(),
-            source: e,
+        .and_then(|path| {
+            let mode =
+                display_modes.get(unsafe { path.sourceInfo.Anonymous.modeInfoIdx } as usize)?;
+            (mode.infoType == DISPLAYCONFIG_MODE_INFO_TYPE_SOURCE).then_some((path, mode))
+        })
+        .map(|(path, mode)| {
+            let source_mode = unsafe { mode.Anonymous.sourceMode };
+            let refresh_rate = path.targetInfo.refreshRate;
+            DisplayMode {
+                width: source_mode.width,
+                height: source_mode.height,
+                refresh_rate: match refresh_rate.Denominator {
+                    0 => 0.0,
+                    denominator => f64::from(refresh_rate.Numerator) / f64::from(denominator),
+                },
+            }
         })
+        .ok_or_else(|| SysError::ActiveModeMissing {
+            monitor: device.monitor_name(),
+        })
+}
+
+pub(crate) fn screen_rect(device: &BlockingDeviceImpl) -> Result<ScreenRect, SysError> {
+    let info = unsafe { get_monitor_info(HMONITOR(device.hmonitor()))? };
+    let rect = info.monitorInfo.rcMonitor;
+    Ok(ScreenRect {
+        left: rect.left,
+        top: rect.top,
+        right: rect.right,
+        bottom: rect.bottom,
+    })
+}
+
+pub(crate) fn is_hdr_active(device: &BlockingDeviceImpl) -> Result<bool, SysError> {
+    let info = advanced_color_info(device)?;
+    // Bit 1 of the flags is `advancedColorEnabled`.
+    Ok(unsafe { info.Anonymous.value } & 0x2 != 0)
+}
+
+pub(crate) fn bit_depth(device: &BlockingDeviceImpl) -> Result<Option<u32>, SysError> {
+    let info = advanced_color_info(device)?;
+    Ok(Some(info.bitsPerColorChannel).filter(|&bits| bits != 0))
+}
+
+fn advanced_color_info(
+    device: &BlockingDeviceImpl,
+) -> Result<DISPLAYCONFIG_GET_ADVANCED_COLOR_INFO, SysError> {
+    let mut info = DISPLAYCONFIG_GET_ADVANCED_COLOR_INFO::default();
+    info.header.size = size_of::<DISPLAYCONFIG_GET_ADVANCED_COLOR_INFO>() as u32;
+    info.header.adapterId = u64_to_luid(device.adapter_luid);
+    info.header.id = device.target_id;
+    info.header.r#type = DISPLAYCONFIG_DEVICE_INFO_GET_ADVANCED_COLOR_INFO;
+    let result = to_win32_error(unsafe { DisplayConfigGetDeviceInfo(&mut info.header) });
+    if result != ERROR_SUCCESS {
+        return Err(SysError::GettingAdvancedColorInfoFailed {
+            monitor: device.monitor_name(),
+            source: result.into(),
+        });
     }
+    Ok(info)
+}
+
+pub(crate) fn display_brightness(
+    device: &BlockingDeviceImpl,
+) -> Result<Option<DisplayBrightness>, SysError> {
+    if !device.is_internal() {
+        return Ok(None);
+    }
+    let raw = ioctl_query_raw_display_brightness(device)?;
+    Ok(Some(DisplayBrightness {
+        policy: raw.ucDisplayPolicy,
+        ac: raw.ucACBrightness,
+        dc: raw.ucDCBrightness,
+    }))
 }
 
 fn ioctl_query_display_brightness(device: &BlockingDeviceImpl) -> Result<u32, SysError> {
+    let display_brightness = ioctl_query_raw_display_brightness(device)?;
+    match display_brightness.ucDisplayPolicy as u32 {
+        DISPLAYPOLICY_AC => {
+            // This is a value between 0 and 100.
+            Ok(display_brightness.ucACBrightness as u32)
+        }
+        DISPLAYPOLICY_DC => {
+            // This is a value between 0 and 100.
+            Ok(display_brightness.ucDCBrightness as u32)
+        }
+        _ => Err(SysError::IoctlQueryDisplayBrightnessUnexpectedResponse {
+            monitor: device.monitor_name(),
+        }),
+    }
+}
+
+fn ioctl_query_raw_display_brightness(
+    device: &BlockingDeviceImpl,
+) -> Result<DISPLAY_BRIGHTNESS, SysError> {
     unsafe {
         let mut bytes_returned = 0;
         let mut display_brightness = DISPLAY_BRIGHTNESS::default();
-        DeviceIoControl(
-            device.file_handle.0,
-            IOCTL_VIDEO_QUERY_DISPLAY_BRIGHTNESS,
-            ptr::null_mut(),
-            0,
-            &mut display_brightness as *mut DISPLAY_BRIGHTNESS as *mut c_void,
-            size_of::<DISPLAY_BRIGHTNESS>() as u32,
-            &mut bytes_returned,
-            ptr::null_mut(),
-        )
-        .ok()
-        .map_err(|e| SysError::IoctlQueryDisplayBrightnessFailed {
-            device_name: device.device_name.clone(),
-            source: e,
-        })
-        .and_then(|_| match display_brightness.ucDisplayPolicy as u32 {
-            DISPLAYPOLICY_AC => {
-                // This is a value between 0 and 100.
-                Ok(display_brightness.ucACBrightness as u32)
-            }
-            DISPLAYPOLICY_DC => {
-                // This is a value between 0 and 100.
-                Ok(display_brightness.ucDCBrightness as u32)
-            }
-            _ => Err(SysError::IoctlQueryDisplayBrightnessUnexpectedResponse {
-                device_name: device.device_name.clone(),
-            }),
-        })
+        device
+            .timed(|| {
+                DeviceIoControl(
+                    device.handles().file_handle.0,
+                    IOCTL_VIDEO_QUERY_DISPLAY_BRIGHTNESS,
+                    ptr::null_mut(),
+                    0,
+                    &mut display_brightness as *mut DISPLAY_BRIGHTNESS as *mut c_void,
+                    size_of::<DISPLAY_BRIGHTNESS>() as u32,
+                    &mut bytes_returned,
+                    ptr::null_mut(),
+                )
+            })
+            .ok()
+            .map_err(|e| SysError::IoctlQueryDisplayBrightnessFailed {
+                monitor: device.monitor_name(),
+                source: e,
+            })?;
+        Ok(display_brightness)
+    }
+}
+
+/// Sets a raw brightness value, which is a level between 0 and 100 for internal displays and a
+/// DDC/CI value for external monitors.
+pub(crate) fn set_raw_value(device: &BlockingDeviceImpl, value: u32) -> Result<(), SysError> {
+    if device.is_internal() {
+        ioctl_set_display_brightness(device, value.min(100) as u8)
+    } else {
+        ddcci_set_monitor_brightness(device, value)
     }
 }
 
@@ -614,27 +2430,109 @@ fn ioctl_set_display_brightness(device: &BlockingDeviceImpl, value: u8) -> Resul
             ucDisplayPolicy: DISPLAYPOLICY_BOTH,
         };
         let mut bytes_returned = 0;
-        DeviceIoControl(
-            device.file_handle.0,
-            IOCTL_VIDEO_SET_DISPLAY_BRIGHTNESS,
-            &mut display_brightness as *mut DISPLAY_BRIGHTNESS as *mut c_void,
-            size_of::<DISPLAY_BRIGHTNESS>() as u32,
+        device
+            .timed(|| {
+                DeviceIoControl(
+                    device.handles().file_handle.0,
+                    IOCTL_VIDEO_SET_DISPLAY_BRIGHTNESS,
+                    &mut display_brightness as *mut DISPLAY_BRIGHTNESS as *mut c_void,
+                    size_of::<DISPLAY_BRIGHTNESS>() as u32,
+                    ptr::null_mut(),
+                    0,
+                    &mut bytes_returned,
+                    ptr::null_mut(),
+                )
+            })
+            .ok()
+            .map(|_| {
+                // There is a bug where if the IOCTL_VIDEO_QUERY_DISPLAY_BRIGHTNESS is
+                // called immediately after then it won't show the newly updated values
+                // Doing a very tiny sleep seems to mitigate this
+                std::thread::sleep(std::time::Duration::from_nanos(1));
+            })
+            .map_err(|e| SysError::IoctlSetBrightnessFailed {
+                monitor: device.monitor_name(),
+                source: e,
+            })
+    }
+}
+
+/// Returns the hardware ID and instance ID of a monitor.\
+/// The device path has the form `\\?\DISPLAY#<hardware ID>#<instance ID>#{<interface GUID>}`.
+fn device_instance(device: &BlockingDeviceImpl) -> Option<(&str, &str)> {
+    let mut parts = device.device_path.split('#').skip(1);
+    match (parts.next(), parts.next()) {
+        (Some(hardware_id), Some(instance_id)) => Some((hardware_id, instance_id)),
+        _ => None,
+    }
+}
+
+pub(crate) fn instance_id(device: &BlockingDeviceImpl) -> Result<String, SysError> {
+    let (hardware_id, instance_id) =
+        device_instance(device).ok_or_else(|| SysError::UnexpectedDevicePath {
+            monitor: device.monitor_name(),
+        })?;
+    Ok(format!(r"DISPLAY\{}\{}", hardware_id, instance_id))
+}
+
+/// Returns the number at the end of the GDI device name of a monitor (`\\.\DISPLAY<number>`).
+pub(crate) fn display_number(device: &BlockingDeviceImpl) -> Result<u32, SysError> {
+    device
+        .gdi_device_name
+        .strip_prefix(r"\\.\DISPLAY")
+        .and_then(|number| number.parse().ok())
+        .ok_or_else(|| SysError::UnexpectedGdiDeviceName {
+            monitor: device.monitor_name(),
+        })
+}
+
+/// Reads the EDID of a monitor from the registry.\
+/// The EDID is stored under the `Device Parameters` key of the device instance of the monitor.\
+/// A `None` value means that the device path is not in the expected format or that no EDID was
+/// stored for the monitor.
+fn read_edid(device: &BlockingDeviceImpl) -> Result<Option<Vec<u8>>, SysError> {
+    let (hardware_id, instance_id) = match device_instance(device) {
+        Some(instance) => instance,
+        None => return Ok(None),
+    };
+    let key = to_wide(&format!(
+        r"SYSTEM\CurrentControlSet\Enum\DISPLAY\{}\{}\Device Parameters",
+        hardware_id, instance_id
+    ));
+    let value = to_wide("EDID");
+    let to_sys_error = |e| SysError::ReadingEdidFailed {
+        monitor: device.monitor_name(),
+        source: e,
+    };
+    unsafe {
+        let mut len = 0;
+        let status = RegGetValueW(
+            HKEY_LOCAL_MACHINE,
+            PCWSTR(key.as_ptr()),
+            PCWSTR(value.as_ptr()),
+            RRF_RT_REG_BINARY,
+            ptr::null_mut(),
             ptr::null_mut(),
-            0,
-            &mut bytes_returned,
+            &mut len,
+        );
+        if status == ERROR_FILE_NOT_FOUND {
+            return Ok(None);
+        }
+        status.ok().map_err(to_sys_error)?;
+        let mut edid = vec![0u8; len as usize];
+        RegGetValueW(
+            HKEY_LOCAL_MACHINE,
+            PCWSTR(key.as_ptr()),
+            PCWSTR(value.as_ptr()),
+            RRF_RT_REG_BINARY,
             ptr::null_mut(),
+            edid.as_mut_ptr() as *mut c_void,
+            &mut len,
         )
         .ok()
-        .map(|_| {
-            // There is a bug where if the IOCTL_VIDEO_QUERY_DISPLAY_BRIGHTNESS is
-            // called immediately after then it won't show the newly updated values
-            // Doing a very tiny sleep seems to mitigate this
-            std::thread::sleep(std::time::Duration::from_nanos(1));
-        })
-        .map_err(|e| SysError::IoctlSetBrightnessFailed {
-            device_name: device.device_name.clone(),
-            source: e,
-        })
+        .map_err(to_sys_error)?;
+        edid.truncate(len as usize);
+        Ok(Some(edid))
     }
 }
 
@@ -650,4 +2548,251 @@ impl BrightnessExt for BrightnessDevice {
     fn device_path(&self) -> Result<String, Error> {
         Ok(self.0.device_path.clone())
     }
+
+    fn instance_id(&self) -> Result<String, Error> {
+        Ok(instance_id(&self.0)?)
+    }
+
+    fn friendly_name(&self) -> Result<String, Error> {
+        Ok(self.0.friendly_name.clone())
+    }
+
+    fn gdi_device_name(&self) -> Result<String, Error> {
+        Ok(self.0.gdi_device_name.clone())
+    }
+
+    fn display_number(&self) -> Result<u32, Error> {
+        Ok(display_number(&self.0)?)
+    }
+
+    fn output_technology(&self) -> Result<OutputTechnology, Error> {
+        Ok(self.0.output_technology.into())
+    }
+
+    fn adapter_luid(&self) -> Result<u64, Error> {
+        Ok(self.0.adapter_luid)
+    }
+
+    fn hmonitor(&self) -> Result<isize, Error> {
+        Ok(self.0.hmonitor())
+    }
+
+    fn ddcci_enabled(&self) -> Result<bool, Error> {
+        Ok(ddcci_probe(&self.0)?)
+    }
+
+    fn is_ghost(&self) -> Result<bool, Error> {
+        Ok(is_ghost(&self.0)?)
+    }
+
+    fn set_nonzero_floor(&mut self, enabled: bool) {
+        self.0.nonzero_floor.store(enabled, Ordering::Relaxed);
+    }
+
+    fn set_min_percentage(&mut self, percentage: u32) {
+        self.0
+            .min_percentage
+            .store(percentage.min(100), Ordering::Relaxed);
+    }
+
+    fn brightness_raw_min(&self) -> Result<u32, Error> {
+        Ok(brightness_raw_min(&self.0)?)
+    }
+
+    fn set_rounding(&mut self, rounding: Rounding) {
+        self.0.rounding.store(rounding as u8, Ordering::Relaxed);
+    }
+
+    fn set_reopen_on_stale(&mut self, enabled: bool) {
+        self.0.reopen_on_stale.store(enabled, Ordering::Relaxed);
+    }
+
+    fn set_mapping(&mut self, mapping: Option<BrightnessMapping>) {
+        *self.0.mapping.write().unwrap() = mapping;
+    }
+
+    fn set_skip_unchanged(&mut self, tolerance: Option<u32>) {
+        *self.0.skip_unchanged.write().unwrap() = tolerance;
+    }
+
+    fn set_verify_writes(&mut self, enabled: bool) {
+        self.0.verify_writes.store(enabled, Ordering::Relaxed);
+    }
+
+    fn preview(&self, requested: u32) -> u32 {
+        preview(&self.0, requested)
+    }
+
+    fn set_raw(&self, value: u32) -> Result<(), Error> {
+        Ok(set_raw_value(&self.0, value)?)
+    }
+
+    fn supported_color_temperatures(&self) -> Result<Vec<ColorTemperature>, Error> {
+        Ok(ddcci_supported_color_temperatures(&self.0)?)
+    }
+
+    fn color_temperature(&self) -> Result<Option<ColorTemperature>, Error> {
+        Ok(ddcci_get_color_temperature(&self.0)?)
+    }
+
+    fn set_color_temperature(&self, temperature: ColorTemperature) -> Result<(), Error> {
+        Ok(ddcci_set_color_temperature(&self.0, temperature)?)
+    }
+
+    fn vcp_version(&self) -> Result<(u8, u8), Error> {
+        Ok(ddcci_get_vcp_version(&self.0)?)
+    }
+
+    fn supported_display_presets(&self) -> Result<Vec<DisplayPreset>, Error> {
+        Ok(ddcci_supported_display_presets(&self.0)?)
+    }
+
+    fn display_preset(&self) -> Result<DisplayPreset, Error> {
+        Ok(ddcci_get_display_preset(&self.0)?)
+    }
+
+    fn set_display_preset(&self, preset: DisplayPreset) -> Result<(), Error> {
+        Ok(ddcci_set_display_preset(&self.0, preset)?)
+    }
+
+    fn take_new_control_value(&self) -> Result<Option<bool>, Error> {
+        Ok(ddcci_take_new_control_value(&self.0)?)
+    }
+
+    fn supported_brightness_levels(&self) -> Result<Vec<u8>, Error> {
+        Ok(supported_brightness_levels(&self.0)?)
+    }
+
+    fn display_brightness(&self) -> Result<Option<DisplayBrightness>, Error> {
+        Ok(display_brightness(&self.0)?)
+    }
+
+    fn is_hdr_active(&self) -> Result<bool, Error> {
+        Ok(is_hdr_active(&self.0)?)
+    }
+
+    fn bit_depth(&self) -> Result<Option<u32>, Error> {
+        Ok(bit_depth(&self.0)?)
+    }
+
+    fn current_mode(&self) -> Result<DisplayMode, Error> {
+        Ok(current_mode(&self.0)?)
+    }
+
+    fn screen_rect(&self) -> Result<ScreenRect, Error> {
+        Ok(screen_rect(&self.0)?)
+    }
+
+    fn last_io_duration(&self) -> Option<Duration> {
+        *self.0.last_io_duration.read().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preview_clamps_to_floor_and_maximum() {
+        assert_eq!(preview_percentage(150, 0, None, false), 100);
+        assert_eq!(preview_percentage(5, 20, None, true), 20);
+        assert_eq!(preview_percentage(50, 20, None, false), 50);
+    }
+
+    #[test]
+    fn preview_round_trips_through_mapping() {
+        let mapping = BrightnessMapping::new([(0, 0), (100, 10)]).unwrap();
+        // External monitors use the range of the mapping.
+        assert_eq!(preview_percentage(33, 0, Some(&mapping), false), 30);
+        assert_eq!(preview_percentage(36, 0, Some(&mapping), false), 40);
+        // Internal displays use levels between 0 and 100.
+        let mapping = BrightnessMapping::new([(50, 20)]).unwrap();
+        assert_eq!(preview_percentage(75, 0, Some(&mapping), true), 75);
+        assert_eq!(preview_percentage(10, 40, Some(&mapping), true), 40);
+    }
+
+    #[test]
+    fn ddcci_values_reject_current_outside_range() {
+        assert!(DdcciBrightnessValues::checked(0, 101, 100).is_none());
+        assert!(DdcciBrightnessValues::checked(10, 5, 100).is_none());
+        assert!(DdcciBrightnessValues::checked(100, 101, 0).is_none());
+        assert!(DdcciBrightnessValues::checked(0, 100, 100).is_some());
+        assert!(DdcciBrightnessValues::checked(50, 50, 50).is_some());
+    }
+
+    #[test]
+    fn ddcci_values_convert_percentages() {
+        let values = DdcciBrightnessValues::checked(20, 60, 120).unwrap();
+        assert_eq!(values.get_current_percentage(), 40);
+        assert_eq!(values.percentage_to_current(40), 60);
+        assert_eq!(values.percentage_to_current(0), 20);
+        assert_eq!(values.percentage_to_current(150), 120);
+    }
+
+    #[test]
+    fn ddcci_values_convert_percentages_of_empty_range() {
+        let values = DdcciBrightnessValues::checked(50, 50, 50).unwrap();
+        assert_eq!(values.get_current_percentage(), 0);
+        assert_eq!(values.percentage_to_current(0), 50);
+        assert_eq!(values.percentage_to_current(100), 50);
+    }
+
+    #[test]
+    fn ddcci_values_convert_percentages_of_inverted_range() {
+        let values = DdcciBrightnessValues::checked(100, 25, 0).unwrap();
+        assert_eq!(values.get_current_percentage(), 75);
+        assert_eq!(values.percentage_to_current(75), 25);
+        assert_eq!(values.percentage_to_current(0), 100);
+        assert_eq!(values.percentage_to_current(100), 0);
+    }
+
+    fn rect(left: i32, top: i32, width: i32, height: i32) -> ScreenRect {
+        ScreenRect {
+            left,
+            top,
+            right: left + width,
+            bottom: top + height,
+        }
+    }
+
+    #[test]
+    fn arranges_aligned_monitors_in_rows() {
+        let grid = arrange_in_grid(vec![
+            (rect(1920, 1080, 1920, 1080), 'd'),
+            (rect(0, 0, 1920, 1080), 'a'),
+            (rect(0, 1080, 1920, 1080), 'c'),
+            (rect(1920, 0, 1920, 1080), 'b'),
+        ]);
+        assert_eq!(grid, [vec!['a', 'b'], vec!['c', 'd']]);
+        assert!(arrange_in_grid::<char>(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn arranges_staggered_monitors_in_rows() {
+        let grid = arrange_in_grid(vec![
+            (rect(0, 0, 1920, 1080), 'a'),
+            (rect(1920, 200, 1920, 1080), 'b'),
+            (rect(0, 1080, 1920, 1080), 'c'),
+            (rect(1920, 1280, 1920, 1080), 'd'),
+        ]);
+        assert_eq!(grid, [vec!['a', 'b'], vec!['c', 'd']]);
+    }
+
+    #[test]
+    fn arranges_misaligned_monitors_by_overlap_with_whole_row() {
+        // A portrait monitor overlaps both landscape monitors, but they do not overlap each other.
+        let grid = arrange_in_grid(vec![
+            (rect(0, 1080, 1920, 1080), 'c'),
+            (rect(0, 0, 1920, 1080), 'b'),
+            (rect(-1080, 0, 1080, 1920), 'a'),
+        ]);
+        assert_eq!(grid, [vec!['a', 'b'], vec!['c']]);
+        // Monitors above the primary monitor have negative coordinates.
+        let grid = arrange_in_grid(vec![
+            (rect(0, 0, 2560, 1440), 'b'),
+            (rect(-1920, -300, 1920, 1080), 'a'),
+            (rect(-1920, 780, 1920, 1080), 'c'),
+        ]);
+        assert_eq!(grid, [vec!['a', 'b'], vec!['c']]);
+    }
 }
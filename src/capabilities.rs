@@ -0,0 +1,186 @@
+// Copyright (C) 2022 The brightness project authors. Distributed under the 0BSD license.
+
+//! Definitions for the DDC/CI capabilities string reported by monitors.
+
+/// Information parsed from the capabilities string of a monitor.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct Capabilities {
+    /// Unparsed capabilities string
+    pub raw: String,
+    /// Protocol class (e.g. `monitor`)
+    pub protocol: Option<String>,
+    /// Display technology (e.g. `lcd`)
+    pub display_type: Option<String>,
+    /// Model name
+    pub model: Option<String>,
+    /// MCCS version (e.g. `2.1`)
+    pub mccs_version: Option<String>,
+    /// Supported VCP features
+    pub vcp_features: Vec<VcpFeature>,
+}
+
+/// VCP feature listed in a capabilities string.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VcpFeature {
+    /// VCP code
+    pub code: u8,
+    /// Values supported by a non-continuous feature, empty if not listed
+    pub values: Vec<u8>,
+}
+
+impl Capabilities {
+    /// Parses a capabilities string.
+    ///
+    /// Parsing is lenient as many monitors report slightly malformed strings: unknown entries are
+    /// ignored and parsing stops at the first unbalanced parenthesis, keeping the entries read so
+    /// far.
+    pub fn parse(raw: &str) -> Self {
+        let mut capabilities = Capabilities {
+            raw: raw.to_owned(),
+            protocol: None,
+            display_type: None,
+            model: None,
+            mccs_version: None,
+            vcp_features: Vec::new(),
+        };
+        let trimmed = raw.trim().trim_end_matches('\0');
+        // The whole string is supposed to be enclosed in parentheses, but this is often omitted.
+        let body = match trimmed.strip_prefix('(') {
+            Some(rest) => match rest.strip_suffix(')') {
+                Some(inner) if matching_paren(rest) == Some(inner.len()) => inner,
+                _ => trimmed,
+            },
+            None => trimmed,
+        };
+        for (name, value) in entries(body) {
+            let text = Some(value.trim().to_owned());
+            match name {
+                "prot" => capabilities.protocol = text,
+                "type" => capabilities.display_type = text,
+                "model" => capabilities.model = text,
+                "mccs_ver" => capabilities.mccs_version = text,
+                "vcp" => capabilities.vcp_features = parse_vcp(value),
+                _ => {}
+            }
+        }
+        capabilities
+    }
+
+    /// Returns whether a VCP feature is listed as supported.
+    pub fn supports(&self, code: u8) -> bool {
+        self.vcp_features.iter().any(|feature| feature.code == code)
+    }
+}
+
+/// Returns the top-level `name(value)` entries of a capabilities string.
+fn entries(mut s: &str) -> Vec<(&str, &str)> {
+    let mut entries = Vec::new();
+    while let Some(open) = s.find('(') {
+        let name = s[..open].trim();
+        let rest = &s[open + 1..];
+        let close = match matching_paren(rest) {
+            Some(close) => close,
+            None => break,
+        };
+        entries.push((name, &rest[..close]));
+        s = &rest[close + 1..];
+    }
+    entries
+}
+
+/// Returns the index of the parenthesis closing the one just before `s`.
+fn matching_paren(s: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some(i),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Parses the value of the `vcp` entry, a list of hexadecimal codes that may each be followed by a
+/// parenthesized list of supported values.
+fn parse_vcp(mut s: &str) -> Vec<VcpFeature> {
+    let mut features = Vec::new();
+    loop {
+        s = s.trim_start();
+        let end = s
+            .find(|c: char| c.is_whitespace() || c == '(')
+            .unwrap_or(s.len());
+        if end == 0 {
+            break;
+        }
+        let code = u8::from_str_radix(&s[..end], 16).ok();
+        s = s[end..].trim_start();
+        let mut values = Vec::new();
+        if let Some(rest) = s.strip_prefix('(') {
+            let close = match matching_paren(rest) {
+                Some(close) => close,
+                None => break,
+            };
+            values = rest[..close]
+                .split_whitespace()
+                .filter_map(|v| u8::from_str_radix(v, 16).ok())
+                .collect();
+            s = &rest[close + 1..];
+        }
+        if let Some(code) = code {
+            features.push(VcpFeature { code, values });
+        }
+    }
+    features
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_well_formed_string() {
+        let capabilities = Capabilities::parse("(prot(monitor)type(lcd)model(X)vcp(10 14(05 06)))");
+        assert_eq!(capabilities.protocol.as_deref(), Some("monitor"));
+        assert_eq!(capabilities.display_type.as_deref(), Some("lcd"));
+        assert_eq!(capabilities.model.as_deref(), Some("X"));
+        assert_eq!(
+            capabilities.vcp_features,
+            vec![
+                VcpFeature {
+                    code: 0x10,
+                    values: vec![],
+                },
+                VcpFeature {
+                    code: 0x14,
+                    values: vec![0x05, 0x06],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_string_without_outer_parentheses() {
+        let capabilities = Capabilities::parse("prot(monitor)vcp(10)");
+        assert_eq!(capabilities.protocol.as_deref(), Some("monitor"));
+        assert!(capabilities.supports(0x10));
+    }
+
+    #[test]
+    fn ignores_malformed_strings() {
+        for raw in ["", "(", " ( ", ")", "()", "((", "(()", "\0", "(\0"] {
+            let capabilities = Capabilities::parse(raw);
+            assert_eq!(capabilities.protocol, None, "{:?}", raw);
+            assert!(capabilities.vcp_features.is_empty(), "{:?}", raw);
+        }
+    }
+
+    #[test]
+    fn keeps_entries_before_unbalanced_parenthesis() {
+        let capabilities = Capabilities::parse("prot(monitor)vcp(10 14(05");
+        assert_eq!(capabilities.protocol.as_deref(), Some("monitor"));
+        assert!(capabilities.vcp_features.is_empty());
+    }
+}
@@ -0,0 +1,99 @@
+// Copyright (C) 2022 The brightness project authors. Distributed under the 0BSD license.
+
+//! Brightness limited by a cap that can change at runtime.
+
+use crate::{edid::EdidInfo, Brightness, BrightnessDevice, DeviceKind, Error};
+use async_trait::async_trait;
+
+/// Device whose brightness is kept at or below a cap, which can be changed at any time.
+///
+/// This is meant for limits driven by the application, e.g. lowering the cap when the battery is
+/// critically low and raising it again when the laptop is plugged in. The cap applies to `set` on
+/// this wrapper, and lowering it below the current brightness turns the device down immediately.
+/// Brightness changes made by other applications are not limited.
+#[derive(Debug)]
+pub struct CappedBrightness<D = BrightnessDevice> {
+    device: D,
+    max: u32,
+}
+
+impl<D> CappedBrightness<D>
+where
+    D: Brightness + Send + Sync,
+{
+    /// Wraps a device, limiting its brightness to `max` as a percentage.
+    ///
+    /// The current brightness of the device is not changed until it is set or the cap is changed.
+    pub fn new(device: D, max: u32) -> Self {
+        CappedBrightness {
+            device,
+            max: max.min(100),
+        }
+    }
+
+    /// Returns the wrapped device.
+    pub fn device(&self) -> &D {
+        &self.device
+    }
+
+    /// Returns the wrapped device, removing the cap.
+    pub fn into_inner(self) -> D {
+        self.device
+    }
+
+    /// Returns the cap as a percentage.
+    pub fn dynamic_max(&self) -> u32 {
+        self.max
+    }
+
+    /// Changes the cap as a percentage, and turns the device down to it if it is brighter.
+    ///
+    /// Raising the cap does not change the brightness of the device. The cap is changed even if
+    /// reading or setting the brightness fails.
+    pub async fn set_dynamic_max(&mut self, max: u32) -> Result<(), Error> {
+        self.max = max.min(100);
+        if self.device.get().await? > self.max {
+            self.device.set(self.max).await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<D> Brightness for CappedBrightness<D>
+where
+    D: Brightness + Send + Sync,
+{
+    async fn device_name(&self) -> Result<String, Error> {
+        self.device.device_name().await
+    }
+
+    async fn get(&self) -> Result<u32, Error> {
+        self.device.get().await
+    }
+
+    /// Sets the brightness as a percentage, limited to the cap.
+    async fn set(&mut self, percentage: u32) -> Result<(), Error> {
+        self.device.set(percentage.min(self.max)).await
+    }
+
+    async fn edid(&self) -> Result<Option<EdidInfo>, Error> {
+        self.device.edid().await
+    }
+
+    async fn is_primary(&self) -> Result<bool, Error> {
+        self.device.is_primary().await
+    }
+
+    async fn kind(&self) -> Result<DeviceKind, Error> {
+        self.device.kind().await
+    }
+
+    async fn raw_capabilities(&self) -> Result<Option<String>, Error> {
+        self.device.raw_capabilities().await
+    }
+
+    async fn brightness_raw_max(&self) -> Result<u32, Error> {
+        self.device.brightness_raw_max().await
+    }
+}
@@ -0,0 +1,144 @@
+// Copyright (C) 2022 The brightness project authors. Distributed under the 0BSD license.
+
+//! Command model for controlling brightness from another process.
+
+use crate::{brightness_devices, set_all, Brightness, BrightnessDevice, Error};
+use futures::StreamExt;
+use std::error::Error as StdError;
+
+/// Command executed by [`execute`].
+///
+/// Devices are identified by name, as returned by `device_name`. With the `serde` feature, commands
+/// can be serialized, so that a client can send them to a process executing them over any
+/// transport (e.g. a socket or SSH).
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[non_exhaustive]
+pub enum BrightnessCommand {
+    /// Lists the names of all devices
+    List,
+    /// Gets the brightness of a device as a percentage
+    Get {
+        /// Device name
+        device: String,
+    },
+    /// Sets the brightness of a device as a percentage
+    Set {
+        /// Device name
+        device: String,
+        /// Brightness percentage
+        percentage: u32,
+    },
+    /// Sets the brightness of all devices as a percentage
+    SetAll {
+        /// Brightness percentage
+        percentage: u32,
+    },
+}
+
+/// Response to a [`BrightnessCommand`].
+///
+/// With the `serde` feature, responses can be serialized to be sent back to the client.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[non_exhaustive]
+pub enum BrightnessResponse {
+    /// Names of the devices, in response to [`BrightnessCommand::List`]
+    Devices(Vec<String>),
+    /// Brightness percentage, in response to [`BrightnessCommand::Get`]
+    Brightness(u32),
+    /// The brightness was set, in response to [`BrightnessCommand::Set`] and
+    /// [`BrightnessCommand::SetAll`]
+    Done,
+    /// The command failed
+    Failed {
+        /// Description of the errors, including their causes
+        message: String,
+    },
+}
+
+/// Executes a command on the devices of the running system.
+///
+/// Errors are reported as [`BrightnessResponse::Failed`], as [`Error`] cannot be serialized. With
+/// [`BrightnessCommand::SetAll`], the devices that can be set are set even if others fail.
+pub async fn execute(command: BrightnessCommand) -> BrightnessResponse {
+    let result = match command {
+        BrightnessCommand::List => list().await.map(BrightnessResponse::Devices),
+        BrightnessCommand::Get { device } => match find(&device).await {
+            Ok(device) => device.get().await.map(BrightnessResponse::Brightness),
+            Err(e) => Err(e),
+        },
+        BrightnessCommand::Set { device, percentage } => match find(&device).await {
+            Ok(mut device) => device
+                .set(percentage)
+                .await
+                .map(|_| BrightnessResponse::Done),
+            Err(e) => Err(e),
+        },
+        BrightnessCommand::SetAll { percentage } => {
+            let messages = set_all(percentage, None)
+                .await
+                .into_iter()
+                .filter_map(|r| r.err())
+                .map(|e| message(&e))
+                .collect::<Vec<_>>();
+            return if messages.is_empty() {
+                BrightnessResponse::Done
+            } else {
+                BrightnessResponse::Failed {
+                    message: messages.join("\n"),
+                }
+            };
+        }
+    };
+    result.unwrap_or_else(|e| BrightnessResponse::Failed {
+        message: message(&e),
+    })
+}
+
+async fn list() -> Result<Vec<String>, Error> {
+    let mut devices = Box::pin(brightness_devices());
+    let mut names = Vec::new();
+    while let Some(device) = devices.next().await {
+        names.push(device?.device_name().await?);
+    }
+    Ok(names)
+}
+
+/// Returns the device with the given name. Devices that fail to be listed are skipped, and the
+/// first error is returned if the device is not found, as it may have been the one.
+async fn find(name: &str) -> Result<BrightnessDevice, Error> {
+    let mut devices = Box::pin(brightness_devices());
+    let mut first_error = None;
+    while let Some(device) = devices.next().await {
+        let found = match device {
+            Ok(device) => device
+                .device_name()
+                .await
+                .map(|device_name| (device_name == name).then_some(device)),
+            Err(e) => Err(e),
+        };
+        match found {
+            Ok(Some(device)) => return Ok(device),
+            Ok(None) => {}
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    Err(first_error.unwrap_or_else(|| Error::DeviceNotFound {
+        device: name.to_owned(),
+    }))
+}
+
+/// Returns the description of an error followed by its causes.
+fn message(e: &Error) -> String {
+    let mut message = e.to_string();
+    let mut source = e.source();
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
@@ -0,0 +1,39 @@
+// Copyright (C) 2022 The brightness project authors. Distributed under the 0BSD license.
+
+//! Detection of other software controlling the brightness of a device.
+
+use crate::{Brightness, Error};
+use async_io::Timer;
+use std::time::Duration;
+
+/// Brightness changed by someone else shortly after it was set.
+///
+/// This is a clue that another process controls the monitor, e.g. vendor software such as Dell
+/// Display Manager, and is likely to revert further changes as well.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct PossibleConflict {
+    /// Brightness percentage read right after it was set
+    pub applied: u32,
+    /// Brightness percentage read after the delay
+    pub current: u32,
+}
+
+/// Sets the brightness of a device as a percentage, then checks that it is not changed by someone
+/// else within `delay`.
+///
+/// The brightness is read right after it is set, and again after `delay`. A device applying a
+/// different value than requested (e.g. because it only supports a few levels) is not a conflict,
+/// but a change between both reads is, and is returned as a [`PossibleConflict`]. Detection is best
+/// effort: a change made by the user in the meantime is reported too, and other software may revert
+/// the brightness after `delay`.
+pub async fn set_detecting_conflict(
+    device: &mut (dyn Brightness + Send + Sync),
+    percentage: u32,
+    delay: Duration,
+) -> Result<Option<PossibleConflict>, Error> {
+    device.set(percentage).await?;
+    let applied = device.get().await?;
+    Timer::after(delay).await;
+    let current = device.get().await?;
+    Ok((current != applied).then_some(PossibleConflict { applied, current }))
+}
@@ -0,0 +1,82 @@
+// Copyright (C) 2022 The brightness project authors. Distributed under the 0BSD license.
+
+//! Mapping of ambient light levels to brightness.
+
+/// Curve mapping an ambient light level in lux to a brightness percentage.
+///
+/// The curve is defined by control points between which the brightness is linearly interpolated.
+/// Below the first point and above the last point, the brightness of the closest point is used.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BrightnessCurve {
+    points: Vec<(f64, u32)>,
+    hysteresis: u32,
+}
+
+impl BrightnessCurve {
+    /// Creates a curve from `(lux, percentage)` control points.
+    ///
+    /// Points do not need to be sorted. Percentages above 100 are clamped and points with a NaN
+    /// light level are ignored.
+    pub fn new<I>(points: I) -> Self
+    where
+        I: IntoIterator<Item = (f64, u32)>,
+    {
+        let mut points = points
+            .into_iter()
+            .filter(|(lux, _)| !lux.is_nan())
+            .map(|(lux, percentage)| (lux, percentage.min(100)))
+            .collect::<Vec<_>>();
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        BrightnessCurve {
+            points,
+            hysteresis: 0,
+        }
+    }
+
+    /// Sets the smallest brightness change in percentage points that is applied when adjusting
+    /// brightness.
+    ///
+    /// This prevents brightness from flickering because of small variations of the ambient light.
+    pub fn with_hysteresis(mut self, hysteresis: u32) -> Self {
+        self.hysteresis = hysteresis;
+        self
+    }
+
+    /// Returns the smallest brightness change in percentage points that is applied.
+    pub fn hysteresis(&self) -> u32 {
+        self.hysteresis
+    }
+
+    /// Returns the brightness percentage for an ambient light level in lux.
+    ///
+    /// `None` is returned if the curve has no points or if `lux` is NaN.
+    pub fn percentage(&self, lux: f64) -> Option<u32> {
+        if lux.is_nan() {
+            return None;
+        }
+        let upper = self.points.partition_point(|&(x, _)| x < lux);
+        let (x1, y1) = *self.points.get(upper).or_else(|| self.points.last())?;
+        let (x0, y0) = match upper.checked_sub(1) {
+            Some(lower) => self.points[lower],
+            None => return Some(y1),
+        };
+        if lux >= x1 {
+            return Some(y1);
+        }
+        let fraction = (lux - x0) / (x1 - x0);
+        let percentage = f64::from(y0) + fraction * (f64::from(y1) - f64::from(y0));
+        Some(percentage.round() as u32)
+    }
+
+    /// Returns the brightness to apply for an ambient light level, given the current brightness.
+    ///
+    /// `None` is returned if the brightness should be left unchanged.
+    pub(crate) fn target(&self, current: u32, lux: f64) -> Option<u32> {
+        let target = self.percentage(lux)?;
+        if target == current || current.abs_diff(target) < self.hysteresis {
+            None
+        } else {
+            Some(target)
+        }
+    }
+}
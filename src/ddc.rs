@@ -0,0 +1,67 @@
+// Copyright (C) 2022 The brightness project authors. Distributed under the 0BSD license.
+
+//! DDC/CI packet framing, as defined by the VESA DDC/CI standard.
+//!
+//! Packets are represented as the bytes following the I2C address, i.e. starting with the source
+//! address and ending with the checksum.
+
+/// I2C write address of the display.
+const DISPLAY_ADDRESS: u8 = 0x6E;
+/// Source address used by the host.
+const HOST_ADDRESS: u8 = 0x51;
+/// Address standing in for the destination in the checksum of replies.
+const VIRTUAL_HOST_ADDRESS: u8 = 0x50;
+
+const GET_VCP_FEATURE: u8 = 0x01;
+const GET_VCP_FEATURE_REPLY: u8 = 0x02;
+const SET_VCP_FEATURE: u8 = 0x03;
+
+/// Frames a request sent by the host to the display.
+pub(crate) fn request(payload: &[u8]) -> Vec<u8> {
+    frame(DISPLAY_ADDRESS, HOST_ADDRESS, payload)
+}
+
+/// Frames a reply sent by the display to the host.
+pub(crate) fn reply(payload: &[u8]) -> Vec<u8> {
+    frame(VIRTUAL_HOST_ADDRESS, DISPLAY_ADDRESS, payload)
+}
+
+/// Returns the checksum of a packet, which is the XOR of all its bytes, including the destination
+/// address.
+pub(crate) fn checksum(destination: u8, bytes: &[u8]) -> u8 {
+    bytes.iter().fold(destination, |sum, b| sum ^ b)
+}
+
+fn frame(destination: u8, source: u8, payload: &[u8]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(payload.len() + 3);
+    packet.push(source);
+    packet.push(0x80 | payload.len() as u8);
+    packet.extend_from_slice(payload);
+    packet.push(checksum(destination, &packet));
+    packet
+}
+
+pub(crate) fn get_vcp_request(code: u8) -> Vec<u8> {
+    request(&[GET_VCP_FEATURE, code])
+}
+
+/// Frames a successful "Get VCP Feature" reply. `momentary` is the VCP type of the feature.
+pub(crate) fn get_vcp_reply(code: u8, momentary: bool, current: u16, max: u16) -> Vec<u8> {
+    let [max_high, max_low] = max.to_be_bytes();
+    let [current_high, current_low] = current.to_be_bytes();
+    reply(&[
+        GET_VCP_FEATURE_REPLY,
+        0x00,
+        code,
+        momentary.into(),
+        max_high,
+        max_low,
+        current_high,
+        current_low,
+    ])
+}
+
+pub(crate) fn set_vcp_request(code: u8, value: u16) -> Vec<u8> {
+    let [high, low] = value.to_be_bytes();
+    request(&[SET_VCP_FEATURE, code, high, low])
+}
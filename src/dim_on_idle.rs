@@ -0,0 +1,79 @@
+// Copyright (C) 2022 The brightness project authors. Distributed under the 0BSD license.
+
+//! Dimming of a device while the user is idle.
+
+use crate::{Brightness, Error};
+
+/// Dims a device and restores its previous brightness afterwards.
+///
+/// Idle detection is left to the application, which calls [`DimOnIdle::dim`] when the user becomes
+/// idle and [`DimOnIdle::restore`] on activity.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DimOnIdle {
+    level: u32,
+    saved: Option<Saved>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Saved {
+    previous: u32,
+    dimmed: u32,
+}
+
+impl DimOnIdle {
+    /// Creates an instance dimming to the given brightness percentage.
+    pub fn new(level: u32) -> Self {
+        DimOnIdle {
+            level: level.min(100),
+            saved: None,
+        }
+    }
+
+    /// Returns whether the device is currently dimmed by this instance.
+    pub fn is_dimmed(&self) -> bool {
+        self.saved.is_some()
+    }
+
+    /// Dims the device, remembering its brightness, and returns whether it was dimmed.
+    ///
+    /// Nothing is done if the device is already dimmed, or if its brightness is already at or
+    /// below the dim level.
+    pub async fn dim(
+        &mut self,
+        device: &mut (dyn Brightness + Send + Sync),
+    ) -> Result<bool, Error> {
+        if self.saved.is_some() {
+            return Ok(false);
+        }
+        let previous = device.get().await?;
+        if previous <= self.level {
+            return Ok(false);
+        }
+        device.set(self.level).await?;
+        // The brightness applied may differ from the requested one on devices with few levels.
+        let dimmed = device.get().await?;
+        self.saved = Some(Saved { previous, dimmed });
+        Ok(true)
+    }
+
+    /// Restores the brightness the device had before being dimmed, and returns whether it was
+    /// restored.
+    ///
+    /// The brightness is read again first: if it changed while the device was dimmed (e.g. the
+    /// user adjusted it), the change is kept and nothing is restored. Either way, the device is no
+    /// longer considered dimmed.
+    pub async fn restore(
+        &mut self,
+        device: &mut (dyn Brightness + Send + Sync),
+    ) -> Result<bool, Error> {
+        let saved = match self.saved.take() {
+            Some(saved) => saved,
+            None => return Ok(false),
+        };
+        if device.get().await? != saved.dimmed {
+            return Ok(false);
+        }
+        device.set(saved.previous).await?;
+        Ok(true)
+    }
+}
@@ -0,0 +1,315 @@
+// Copyright (C) 2022 The brightness project authors. Distributed under the 0BSD license.
+
+//! Definitions for the Extended Display Identification Data (EDID) reported by monitors.
+
+const HEADER: [u8; 8] = [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00];
+const BLOCK_LEN: usize = 128;
+const DESCRIPTOR_OFFSETS: [usize; 4] = [54, 72, 90, 108];
+const DESCRIPTOR_LEN: usize = 18;
+const SERIAL_NUMBER_TAG: u8 = 0xFF;
+const TEXT_TAG: u8 = 0xFE;
+const MONITOR_NAME_TAG: u8 = 0xFC;
+
+/// Information parsed from the base EDID block of a monitor.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct EdidInfo {
+    /// Three-letter PNP manufacturer ID (e.g. `DEL`)
+    pub manufacturer_id: String,
+    /// Manufacturer product code
+    pub product_code: u16,
+    /// Numeric serial number, `0` if unused by the manufacturer
+    pub serial_number: u32,
+    /// Preferred timing of the monitor, if described by the EDID
+    pub native_mode: Option<DisplayMode>,
+    /// Monitor name descriptors (tag `0xFC`)
+    pub monitor_names: Vec<String>,
+    /// Serial number string descriptors (tag `0xFF`)
+    pub serial_strings: Vec<String>,
+    /// Unspecified text descriptors (tag `0xFE`), often used for asset tags
+    pub text_strings: Vec<String>,
+}
+
+/// Resolution and refresh rate of a display mode.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DisplayMode {
+    /// Horizontal resolution in pixels
+    pub width: u32,
+    /// Vertical resolution in pixels
+    pub height: u32,
+    /// Refresh rate in Hz
+    pub refresh_rate: f64,
+}
+
+impl EdidInfo {
+    /// Parses the base EDID block.
+    ///
+    /// Returns `None` if the data is truncated, does not start with the EDID header or fails the
+    /// checksum.
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        let block = bytes.get(..BLOCK_LEN)?;
+        if block[..HEADER.len()] != HEADER
+            || block.iter().fold(0u8, |sum, &b| sum.wrapping_add(b)) != 0
+        {
+            return None;
+        }
+        let id = u16::from_be_bytes([block[8], block[9]]);
+        let manufacturer_id = [10, 5, 0]
+            .iter()
+            .map(|shift| char::from(b'A' - 1 + ((id >> shift) & 0x1F) as u8))
+            .collect();
+        Some(EdidInfo {
+            manufacturer_id,
+            product_code: u16::from_le_bytes([block[10], block[11]]),
+            serial_number: u32::from_le_bytes([block[12], block[13], block[14], block[15]]),
+            // The first detailed timing descriptor holds the preferred timing.
+            native_mode: parse_detailed_timing(descriptor(block, DESCRIPTOR_OFFSETS[0])),
+            monitor_names: string_descriptors(block, MONITOR_NAME_TAG),
+            serial_strings: string_descriptors(block, SERIAL_NUMBER_TAG),
+            text_strings: string_descriptors(block, TEXT_TAG),
+        })
+    }
+
+    /// Returns the first monitor name descriptor, if any.
+    pub fn monitor_name(&self) -> Option<&str> {
+        self.monitor_names.first().map(String::as_str)
+    }
+
+    /// Returns the first serial number string descriptor, if any.
+    pub fn serial_string(&self) -> Option<&str> {
+        self.serial_strings.first().map(String::as_str)
+    }
+}
+
+fn descriptor(block: &[u8], offset: usize) -> &[u8] {
+    &block[offset..offset + DESCRIPTOR_LEN]
+}
+
+/// Returns the text of the display descriptors with the given tag, in order.
+///
+/// Descriptors holding non-ASCII or empty text are skipped.
+fn string_descriptors(block: &[u8], tag: u8) -> Vec<String> {
+    DESCRIPTOR_OFFSETS
+        .iter()
+        .map(|&offset| descriptor(block, offset))
+        // Display descriptors start with a zero pixel clock, followed by a reserved byte and the tag.
+        .filter(|d| d[..3] == [0, 0, 0] && d[3] == tag)
+        .filter_map(|d| {
+            // The text is terminated by a line feed if shorter than 13 bytes, and padded with spaces.
+            let text = d[5..].split(|&b| b == b'\n').next()?;
+            let text = std::str::from_utf8(text).ok()?.trim_end();
+            if text.is_empty() || !text.bytes().all(|b| b.is_ascii_graphic() || b == b' ') {
+                return None;
+            }
+            Some(text.to_owned())
+        })
+        .collect()
+}
+
+/// Parses a detailed timing descriptor, returning `None` if the descriptor holds something else
+/// or describes an impossible mode.
+fn parse_detailed_timing(d: &[u8]) -> Option<DisplayMode> {
+    let pixel_clock_hz = u64::from(u16::from_le_bytes([d[0], d[1]])) * 10_000;
+    if pixel_clock_hz == 0 {
+        return None;
+    }
+    let width = u32::from(d[2]) | (u32::from(d[4] & 0xF0) << 4);
+    let h_blank = u32::from(d[3]) | (u32::from(d[4] & 0x0F) << 8);
+    let height = u32::from(d[5]) | (u32::from(d[7] & 0xF0) << 4);
+    let v_blank = u32::from(d[6]) | (u32::from(d[7] & 0x0F) << 8);
+    let interlaced = d[17] & 0x80 != 0;
+    let total = u64::from(width + h_blank) * u64::from(height + v_blank);
+    if width == 0 || height == 0 {
+        return None;
+    }
+    Some(DisplayMode {
+        width,
+        // Interlaced timings describe a single field.
+        height: if interlaced { height * 2 } else { height },
+        refresh_rate: pixel_clock_hz as f64 / total as f64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Base EDID block of a 24-inch 1920x1080 monitor.
+    const EDID: [u8; BLOCK_LEN] = [
+        0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x10, 0xAC, 0xA0, 0x40, 0x53, 0x31, 0x4A,
+        0x4C, 0x1C, 0x16, 0x01, 0x04, 0xA5, 0x34, 0x20, 0x78, 0x3A, 0xEE, 0x95, 0xA3, 0x54, 0x4C,
+        0x99, 0x26, 0x0F, 0x50, 0x54, 0xA5, 0x4B, 0x00, 0x71, 0x4F, 0x81, 0x80, 0xD1, 0xC0, 0x01,
+        0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x02, 0x3A, 0x80, 0x18, 0x71, 0x38,
+        0x2D, 0x40, 0x58, 0x2C, 0x45, 0x00, 0x09, 0x25, 0x21, 0x00, 0x00, 0x1E, 0x00, 0x00, 0x00,
+        0xFF, 0x00, 0x59, 0x34, 0x56, 0x48, 0x38, 0x32, 0x41, 0x38, 0x53, 0x31, 0x4A, 0x4C, 0x0A,
+        0x00, 0x00, 0x00, 0xFC, 0x00, 0x44, 0x45, 0x4C, 0x4C, 0x20, 0x55, 0x32, 0x34, 0x31, 0x32,
+        0x4D, 0x0A, 0x20, 0x00, 0x00, 0x00, 0xFD, 0x00, 0x32, 0x3D, 0x1E, 0x53, 0x11, 0x00, 0x0A,
+        0x20, 0x20, 0x20, 0x20, 0x20, 0x20, 0x00, 0x0F,
+    ];
+
+    /// Returns `block` with its checksum updated.
+    fn with_checksum(mut block: [u8; BLOCK_LEN]) -> [u8; BLOCK_LEN] {
+        let sum = block[..BLOCK_LEN - 1]
+            .iter()
+            .fold(0u8, |sum, &b| sum.wrapping_add(b));
+        block[BLOCK_LEN - 1] = sum.wrapping_neg();
+        block
+    }
+
+    #[test]
+    fn parses_base_block() {
+        let edid = EdidInfo::parse(&EDID).unwrap();
+        assert_eq!(edid.manufacturer_id, "DEL");
+        assert_eq!(edid.product_code, 0x40A0);
+        assert_eq!(edid.serial_number, 0x4C4A_3153);
+        assert_eq!(
+            edid.native_mode,
+            Some(DisplayMode {
+                width: 1920,
+                height: 1080,
+                refresh_rate: 60.0,
+            })
+        );
+    }
+
+    #[test]
+    fn ignores_extension_blocks() {
+        let mut bytes = EDID.to_vec();
+        bytes.extend_from_slice(&[0xFF; BLOCK_LEN]);
+        assert_eq!(EdidInfo::parse(&bytes), EdidInfo::parse(&EDID));
+    }
+
+    #[test]
+    fn rejects_truncated_block() {
+        assert_eq!(EdidInfo::parse(&EDID[..BLOCK_LEN - 1]), None);
+        assert_eq!(EdidInfo::parse(&[]), None);
+    }
+
+    #[test]
+    fn rejects_bad_header() {
+        let mut block = EDID;
+        block[7] = 0xFF;
+        assert_eq!(EdidInfo::parse(&with_checksum(block)), None);
+    }
+
+    #[test]
+    fn rejects_bad_checksum() {
+        let mut block = EDID;
+        block[BLOCK_LEN - 1] ^= 1;
+        assert_eq!(EdidInfo::parse(&block), None);
+        let mut block = EDID;
+        block[20] ^= 0x80;
+        assert_eq!(EdidInfo::parse(&block), None);
+    }
+
+    #[test]
+    fn decodes_five_bit_manufacturer_letters() {
+        for (bytes, id) in [
+            ([0x04, 0x72], "ACR"),
+            ([0x4C, 0x2D], "SAM"),
+            ([0x6B, 0x5A], "ZZZ"),
+        ] {
+            let mut block = EDID;
+            block[8..10].copy_from_slice(&bytes);
+            let edid = EdidInfo::parse(&with_checksum(block)).unwrap();
+            assert_eq!(edid.manufacturer_id, id);
+        }
+    }
+
+    /// Returns `block` with a display descriptor holding `text` at `offset`.
+    fn with_descriptor(
+        mut block: [u8; BLOCK_LEN],
+        offset: usize,
+        tag: u8,
+        text: &[u8],
+    ) -> [u8; BLOCK_LEN] {
+        let d = &mut block[offset..offset + DESCRIPTOR_LEN];
+        d[..5].copy_from_slice(&[0, 0, 0, tag, 0]);
+        d[5..].fill(b' ');
+        d[5..5 + text.len()].copy_from_slice(text);
+        block
+    }
+
+    #[test]
+    fn reads_string_descriptors() {
+        let edid = EdidInfo::parse(&EDID).unwrap();
+        assert_eq!(edid.monitor_names, ["DELL U2412M"]);
+        assert_eq!(edid.monitor_name(), Some("DELL U2412M"));
+        assert_eq!(edid.serial_strings, ["Y4VH82A8S1JL"]);
+        assert_eq!(edid.serial_string(), Some("Y4VH82A8S1JL"));
+        assert!(edid.text_strings.is_empty());
+    }
+
+    #[test]
+    fn reads_several_descriptors_with_same_tag_in_order() {
+        let block = with_descriptor(EDID, 54, TEXT_TAG, b"ASSET 42\n");
+        let block = with_descriptor(block, 72, MONITOR_NAME_TAG, b"U2412M\n");
+        let block = with_descriptor(block, 108, MONITOR_NAME_TAG, b"LEFT SCREEN");
+        let edid = EdidInfo::parse(&with_checksum(block)).unwrap();
+        assert_eq!(edid.monitor_names, ["U2412M", "DELL U2412M", "LEFT SCREEN"]);
+        assert_eq!(edid.text_strings, ["ASSET 42"]);
+        assert!(edid.serial_strings.is_empty());
+        // The preferred timing was replaced by a display descriptor.
+        assert_eq!(edid.native_mode, None);
+    }
+
+    #[test]
+    fn stops_text_at_line_feed() {
+        let block = with_descriptor(EDID, 90, MONITOR_NAME_TAG, b"DELL\nU2412M");
+        let edid = EdidInfo::parse(&with_checksum(block)).unwrap();
+        assert_eq!(edid.monitor_names, ["DELL"]);
+    }
+
+    #[test]
+    fn skips_non_ascii_and_empty_text() {
+        let block = with_descriptor(EDID, 72, SERIAL_NUMBER_TAG, b"\n");
+        let block = with_descriptor(block, 90, MONITOR_NAME_TAG, "\u{c9}cran".as_bytes());
+        let block = with_descriptor(block, 108, MONITOR_NAME_TAG, b"A\tB\n");
+        let edid = EdidInfo::parse(&with_checksum(block)).unwrap();
+        assert!(edid.monitor_names.is_empty());
+        assert!(edid.serial_strings.is_empty());
+    }
+
+    #[test]
+    fn unpacks_high_bits_of_detailed_timing() {
+        // 3840x2160 with 160 pixels and 62 lines of blanking at 533.25 MHz.
+        let d = [
+            0x4D, 0xD0, 0x00, 0xA0, 0xF0, 0x70, 0x3E, 0x80, 0x30, 0x20, 0x35, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x1A,
+        ];
+        let mode = parse_detailed_timing(&d).unwrap();
+        assert_eq!((mode.width, mode.height), (3840, 2160));
+        assert!(
+            (mode.refresh_rate - 59.997).abs() < 0.001,
+            "{}",
+            mode.refresh_rate
+        );
+    }
+
+    #[test]
+    fn doubles_height_of_interlaced_timing() {
+        // 1920x1080i, described by a field of 540 lines with 22 lines of blanking.
+        let d = [
+            0x01, 0x1D, 0x80, 0x18, 0x71, 0x1C, 0x16, 0x20, 0x58, 0x2C, 0x25, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x9E,
+        ];
+        let mode = parse_detailed_timing(&d).unwrap();
+        assert_eq!((mode.width, mode.height), (1920, 1080));
+        assert!(
+            (mode.refresh_rate - 60.05).abs() < 0.01,
+            "{}",
+            mode.refresh_rate
+        );
+    }
+
+    #[test]
+    fn skips_display_descriptor_and_empty_timing() {
+        assert_eq!(
+            parse_detailed_timing(descriptor(&EDID, DESCRIPTOR_OFFSETS[1])),
+            None
+        );
+        let mut d = [0; DESCRIPTOR_LEN];
+        d[0] = 1;
+        assert_eq!(parse_detailed_timing(&d), None);
+    }
+}
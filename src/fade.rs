@@ -0,0 +1,264 @@
+// Copyright (C) 2022 The brightness project authors. Distributed under the 0BSD license.
+
+//! Coordinated brightness fades.
+
+use crate::{Brightness, Error};
+use async_io::Timer;
+use futures::future::{join_all, try_join_all};
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+/// Drives brightness fades of several devices from a single timer.
+///
+/// All devices are updated concurrently at the same tick boundaries, which keeps simultaneous
+/// fades visually synchronized.
+pub struct FadeScheduler<'a> {
+    interval: Duration,
+    fades: Vec<Fade<'a>>,
+}
+
+struct Fade<'a> {
+    device: &'a mut (dyn Brightness + Send + Sync),
+    target: u32,
+    duration: Duration,
+}
+
+/// Progress of a fade that is still running.
+#[derive(Clone, Copy)]
+struct Running {
+    start: u32,
+    last: u32,
+}
+
+impl<'a> FadeScheduler<'a> {
+    /// Creates a scheduler updating brightness every `interval`.
+    pub fn new(interval: Duration) -> Self {
+        FadeScheduler {
+            interval,
+            fades: Vec::new(),
+        }
+    }
+
+    /// Adds a fade of `device` from its current brightness to `target` over `duration`.
+    pub fn add(
+        &mut self,
+        device: &'a mut (dyn Brightness + Send + Sync),
+        target: u32,
+        duration: Duration,
+    ) -> &mut Self {
+        self.fades.push(Fade {
+            device,
+            target,
+            duration,
+        });
+        self
+    }
+
+    /// Runs all fades to completion.
+    ///
+    /// The result of each fade is returned in the order the fades were added. A device that fails
+    /// stops fading but does not interrupt the other fades.
+    pub async fn run(self) -> Vec<Result<(), Error>> {
+        let starts = join_all(self.fades.iter().map(|fade| fade.device.get())).await;
+        self.run_from(starts, false).await
+    }
+
+    /// Runs all fades from the given starting brightness of each device, stopping all of them at
+    /// the first failure if `abort_on_error` is set.
+    ///
+    /// A fade whose starting brightness is an error fails with it. When aborting, a failed write is
+    /// retried once before being considered a failure.
+    async fn run_from(
+        self,
+        starts: Vec<Result<u32, Error>>,
+        abort_on_error: bool,
+    ) -> Vec<Result<(), Error>> {
+        // A zero interval would never make progress.
+        let interval = self.interval.max(Duration::from_millis(1));
+        let mut fades = self
+            .fades
+            .into_iter()
+            .zip(starts)
+            .map(|(fade, start)| {
+                (
+                    fade,
+                    start.map(|start| Some(Running { start, last: start })),
+                )
+            })
+            .collect::<Vec<_>>();
+        let start = Instant::now();
+        let mut tick = 0;
+        while fades.iter().any(|(_, state)| matches!(state, Ok(Some(_))))
+            && !(abort_on_error && fades.iter().any(|(_, state)| state.is_err()))
+        {
+            tick += 1;
+            let elapsed = interval * tick;
+            Timer::at(start + elapsed).await;
+            join_all(fades.iter_mut().filter_map(|(fade, state)| {
+                let running = match state {
+                    Ok(Some(running)) => *running,
+                    _ => return None,
+                };
+                Some(async move {
+                    let value = fade_value(running.start, fade.target, elapsed, fade.duration);
+                    let result = if value == running.last {
+                        Ok(())
+                    } else {
+                        match fade.device.set(value).await {
+                            Err(_) if abort_on_error => fade.device.set(value).await,
+                            result => result,
+                        }
+                    };
+                    *state = result.map(|_| {
+                        Some(Running {
+                            last: value,
+                            ..running
+                        })
+                        .filter(|_| elapsed < fade.duration)
+                    });
+                })
+            }))
+            .await;
+        }
+        fades
+            .into_iter()
+            .map(|(_, state)| state.map(|_| ()))
+            .collect()
+    }
+}
+
+impl fmt::Debug for FadeScheduler<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FadeScheduler")
+            .field("interval", &self.interval)
+            .field("fades", &self.fades.len())
+            .finish()
+    }
+}
+
+/// Fades several devices to their targets together, with all-or-nothing semantics.
+///
+/// Each entry of `fades` is a device and its target brightness. The starting brightness of all
+/// devices is read first, and nothing is changed if a read fails. The devices are then faded
+/// from it together like with [`FadeScheduler`], updating brightness every `interval` for `duration`. A
+/// write that fails is retried once. If it fails again, all fades stop and every device is set
+/// back to its starting brightness, and the error is returned. Setting devices back is best
+/// effort, so a device that keeps failing may be left at an intermediate brightness.
+pub async fn transition_all(
+    fades: &mut [(&mut (dyn Brightness + Send + Sync), u32)],
+    duration: Duration,
+    interval: Duration,
+) -> Result<(), Error> {
+    let starts = try_join_all(fades.iter().map(|(device, _)| device.get())).await?;
+    let mut scheduler = FadeScheduler::new(interval);
+    for (device, target) in fades.iter_mut() {
+        scheduler.add(&mut **device, *target, duration);
+    }
+    let error = match scheduler
+        .run_from(starts.iter().copied().map(Ok).collect(), true)
+        .await
+        .into_iter()
+        .find_map(Result::err)
+    {
+        Some(error) => error,
+        None => return Ok(()),
+    };
+    join_all(
+        fades
+            .iter_mut()
+            .zip(starts)
+            .map(|((device, _), start)| device.set(start)),
+    )
+    .await;
+    Err(error)
+}
+
+/// Returns the brightness of a linear fade from `start` to `target` after `elapsed`.
+fn fade_value(start: u32, target: u32, elapsed: Duration, duration: Duration) -> u32 {
+    if elapsed >= duration {
+        return target;
+    }
+    let progress = elapsed.as_secs_f64() / duration.as_secs_f64();
+    (f64::from(start) + (f64::from(target) - f64::from(start)) * progress).round() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockDevice;
+    use async_trait::async_trait;
+    use futures::executor::block_on;
+
+    /// Device whose writes below `min` fail.
+    struct Flaky {
+        device: MockDevice,
+        min: u32,
+    }
+
+    #[async_trait]
+    impl Brightness for Flaky {
+        async fn device_name(&self) -> Result<String, Error> {
+            self.device.device_name().await
+        }
+
+        async fn get(&self) -> Result<u32, Error> {
+            self.device.get().await
+        }
+
+        async fn set(&mut self, percentage: u32) -> Result<(), Error> {
+            self.device.set_failing(percentage < self.min);
+            let result = self.device.set(percentage).await;
+            self.device.set_failing(false);
+            result
+        }
+    }
+
+    const DURATION: Duration = Duration::from_millis(100);
+    const INTERVAL: Duration = Duration::from_millis(10);
+
+    #[test]
+    fn fade_value_interpolates_and_reaches_target() {
+        let quarter = DURATION / 4;
+        assert_eq!(fade_value(20, 60, Duration::ZERO, DURATION), 20);
+        assert_eq!(fade_value(20, 60, quarter, DURATION), 30);
+        assert_eq!(fade_value(60, 20, quarter, DURATION), 50);
+        assert_eq!(fade_value(20, 60, DURATION, DURATION), 60);
+        assert_eq!(fade_value(20, 60, Duration::ZERO, Duration::ZERO), 60);
+    }
+
+    #[test]
+    fn transition_all_reaches_targets() {
+        let (mut first, mut second) = (MockDevice::new("first"), MockDevice::new("second"));
+        let mut fades: [(&mut (dyn Brightness + Send + Sync), u32); 2] =
+            [(&mut first, 0), (&mut second, 100)];
+        block_on(transition_all(&mut fades, DURATION, INTERVAL)).unwrap();
+        assert_eq!(first.percentage(), 0);
+        assert_eq!(second.percentage(), 100);
+    }
+
+    #[test]
+    fn transition_all_changes_nothing_if_a_read_fails() {
+        let (mut first, mut second) = (MockDevice::new("first"), MockDevice::new("second"));
+        second.set_failing(true);
+        let mut fades: [(&mut (dyn Brightness + Send + Sync), u32); 2] =
+            [(&mut first, 0), (&mut second, 100)];
+        assert!(block_on(transition_all(&mut fades, DURATION, INTERVAL)).is_err());
+        assert_eq!(first.percentage(), 50);
+    }
+
+    #[test]
+    fn transition_all_reverts_every_device_on_failure() {
+        let mut dimmed = Flaky {
+            device: MockDevice::new("dimmed"),
+            min: 30,
+        };
+        let brightened = MockDevice::new("brightened");
+        let mut fades: [(&mut (dyn Brightness + Send + Sync), u32); 2] =
+            [(&mut dimmed, 0), (&mut brightened.clone(), 100)];
+        assert!(block_on(transition_all(&mut fades, DURATION, INTERVAL)).is_err());
+        assert_eq!(dimmed.device.percentage(), 50);
+        assert_eq!(brightened.percentage(), 50);
+    }
+}
@@ -0,0 +1,189 @@
+// Copyright (C) 2022 The brightness project authors. Distributed under the 0BSD license.
+
+//! C-compatible interface.
+//!
+//! Devices are opaque handles obtained from [`brightness_devices`] and released with
+//! [`brightness_devices_free`]. All functions are blocking and return one of the `BRIGHTNESS_*`
+//! status codes, with results written to out-parameters. The library can be built for C
+//! consumers with `cargo rustc --release --features ffi --crate-type cdylib` (or `staticlib`).
+//!
+//! The equivalent C declarations are:
+//!
+//! ```c
+//! typedef struct BrightnessDevice BrightnessDevice;
+//!
+//! int32_t brightness_devices(BrightnessDevice ***devices, size_t *count);
+//! void brightness_devices_free(BrightnessDevice **devices, size_t count);
+//! int32_t brightness_get(const BrightnessDevice *device, uint32_t *percentage);
+//! int32_t brightness_set(const BrightnessDevice *device, uint32_t percentage);
+//! int32_t brightness_device_name(const BrightnessDevice *device, char *buffer, size_t size,
+//!                                size_t *required_size);
+//! ```
+
+use crate::{
+    blocking::{brightness_devices as blocking_brightness_devices, Brightness, BrightnessDevice},
+    Error,
+};
+use std::ptr;
+
+/// The operation succeeded
+pub const BRIGHTNESS_OK: i32 = 0;
+/// A required pointer was null
+pub const BRIGHTNESS_NULL_POINTER: i32 = 1;
+/// The buffer is too small for the result
+pub const BRIGHTNESS_BUFFER_TOO_SMALL: i32 = 2;
+/// Getting a list of brightness devices failed
+pub const BRIGHTNESS_LISTING_DEVICES_FAILED: i32 = 3;
+/// Getting device information failed
+pub const BRIGHTNESS_GETTING_DEVICE_INFO_FAILED: i32 = 4;
+/// Setting brightness failed
+pub const BRIGHTNESS_SETTING_BRIGHTNESS_FAILED: i32 = 5;
+/// Another error occurred
+pub const BRIGHTNESS_OTHER_ERROR: i32 = 6;
+/// The device was disconnected
+pub const BRIGHTNESS_DEVICE_DISCONNECTED: i32 = 7;
+
+fn status(e: Error) -> i32 {
+    match e {
+        Error::ListingDevicesFailed(_)
+        | Error::NoDevices
+        | Error::NoPrimaryDevice
+        | Error::DeviceNotFound { .. } => BRIGHTNESS_LISTING_DEVICES_FAILED,
+        Error::GettingDeviceInfoFailed { .. } => BRIGHTNESS_GETTING_DEVICE_INFO_FAILED,
+        Error::SettingBrightnessFailed { .. }
+        | Error::SettingClamped { .. }
+        | Error::BrightnessNotAdjustable { .. } => BRIGHTNESS_SETTING_BRIGHTNESS_FAILED,
+        Error::DeviceDisconnected { .. } => BRIGHTNESS_DEVICE_DISCONNECTED,
+        Error::Cancelled | Error::DevicesDiverged { .. } => BRIGHTNESS_OTHER_ERROR,
+    }
+}
+
+/// Lists the brightness devices of the running system.
+///
+/// On success, `*devices` points to an array of `*count` device handles, which must be released
+/// with [`brightness_devices_free`]. On failure, `*devices` is null and `*count` is 0.
+///
+/// # Safety
+///
+/// `devices` and `count` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn brightness_devices(
+    devices: *mut *mut *mut BrightnessDevice,
+    count: *mut usize,
+) -> i32 {
+    if devices.is_null() || count.is_null() {
+        return BRIGHTNESS_NULL_POINTER;
+    }
+    *devices = ptr::null_mut();
+    *count = 0;
+    let list = match blocking_brightness_devices().collect::<Result<Vec<_>, _>>() {
+        Ok(list) => list,
+        Err(e) => return status(e),
+    };
+    let handles = list
+        .into_iter()
+        .map(|device| Box::into_raw(Box::new(device)))
+        .collect::<Box<[_]>>();
+    *count = handles.len();
+    *devices = Box::into_raw(handles) as *mut *mut BrightnessDevice;
+    BRIGHTNESS_OK
+}
+
+/// Releases devices returned by [`brightness_devices`].
+///
+/// # Safety
+///
+/// `devices` and `count` must be null or have been returned by [`brightness_devices`], and must not
+/// be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn brightness_devices_free(
+    devices: *mut *mut BrightnessDevice,
+    count: usize,
+) {
+    if devices.is_null() {
+        return;
+    }
+    let handles = Box::from_raw(ptr::slice_from_raw_parts_mut(devices, count));
+    for &device in handles.iter() {
+        drop(Box::from_raw(device));
+    }
+}
+
+/// Gets the brightness of a device as a percentage.
+///
+/// # Safety
+///
+/// `device` must be null or a live handle returned by [`brightness_devices`]. `percentage` must be
+/// null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn brightness_get(
+    device: *const BrightnessDevice,
+    percentage: *mut u32,
+) -> i32 {
+    let (device, percentage) = match (device.as_ref(), percentage.as_mut()) {
+        (Some(device), Some(percentage)) => (device, percentage),
+        _ => return BRIGHTNESS_NULL_POINTER,
+    };
+    match device.get() {
+        Ok(value) => {
+            *percentage = value;
+            BRIGHTNESS_OK
+        }
+        Err(e) => status(e),
+    }
+}
+
+/// Sets the brightness of a device as a percentage.
+///
+/// # Safety
+///
+/// `device` must be null or a live handle returned by [`brightness_devices`].
+#[no_mangle]
+pub unsafe extern "C" fn brightness_set(device: *const BrightnessDevice, percentage: u32) -> i32 {
+    match device.as_ref() {
+        Some(device) => device
+            .set(percentage)
+            .map_or_else(status, |_| BRIGHTNESS_OK),
+        None => BRIGHTNESS_NULL_POINTER,
+    }
+}
+
+/// Copies the name of a device to a buffer as a NUL-terminated UTF-8 string.
+///
+/// If `required_size` is not null, the size needed for the name including the NUL terminator is
+/// written to it, which allows querying the size with an empty buffer. If `size` is too small,
+/// [`BRIGHTNESS_BUFFER_TOO_SMALL`] is returned and the buffer is left untouched.
+///
+/// # Safety
+///
+/// `device` must be null or a live handle returned by [`brightness_devices`]. `buffer` must be
+/// valid for writes of `size` bytes, or null if `size` is 0. `required_size` must be null or valid
+/// for writes.
+#[no_mangle]
+pub unsafe extern "C" fn brightness_device_name(
+    device: *const BrightnessDevice,
+    buffer: *mut u8,
+    size: usize,
+    required_size: *mut usize,
+) -> i32 {
+    let device = match device.as_ref() {
+        Some(device) => device,
+        None => return BRIGHTNESS_NULL_POINTER,
+    };
+    let name = match device.device_name() {
+        Ok(name) => name,
+        Err(e) => return status(e),
+    };
+    if let Some(required_size) = required_size.as_mut() {
+        *required_size = name.len() + 1;
+    }
+    if size < name.len() + 1 {
+        return BRIGHTNESS_BUFFER_TOO_SMALL;
+    }
+    if buffer.is_null() {
+        return BRIGHTNESS_NULL_POINTER;
+    }
+    ptr::copy_nonoverlapping(name.as_ptr(), buffer, name.len());
+    *buffer.add(name.len()) = 0;
+    BRIGHTNESS_OK
+}
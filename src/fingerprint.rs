@@ -0,0 +1,194 @@
+// Copyright (C) 2022 The brightness project authors. Distributed under the 0BSD license.
+
+//! Detection of monitors being connected, disconnected or replaced.
+
+use crate::{brightness_devices, edid::EdidInfo, Brightness, Error};
+use futures::TryStreamExt;
+use std::collections::BTreeMap;
+
+/// Identity of a device and of the monitor connected to it.
+///
+/// The connector is identified by the device name, as returned by `device_name`, and the monitor by
+/// its EDID. With the `serde` feature, fingerprints can be serialized to be compared with the
+/// configuration found on a later run.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[non_exhaustive]
+pub struct ConfigFingerprint {
+    /// Device name
+    pub connector: String,
+    /// Monitor connected, `None` if it does not report an EDID
+    pub monitor: Option<MonitorIdentity>,
+}
+
+/// Identity of a monitor, as described by its EDID.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[non_exhaustive]
+pub struct MonitorIdentity {
+    /// Three-letter PNP manufacturer ID (e.g. `DEL`)
+    pub manufacturer_id: String,
+    /// Manufacturer product code
+    pub product_code: u16,
+    /// Numeric serial number, `0` if unused by the manufacturer
+    pub serial_number: u32,
+    /// Serial number string descriptor, if any
+    pub serial_string: Option<String>,
+}
+
+impl From<&EdidInfo> for MonitorIdentity {
+    fn from(edid: &EdidInfo) -> Self {
+        MonitorIdentity {
+            manufacturer_id: edid.manufacturer_id.clone(),
+            product_code: edid.product_code,
+            serial_number: edid.serial_number,
+            serial_string: edid.serial_strings.first().cloned(),
+        }
+    }
+}
+
+impl ConfigFingerprint {
+    /// Returns the fingerprint of a device.
+    pub async fn of(device: &(dyn Brightness + Send + Sync)) -> Result<Self, Error> {
+        Ok(ConfigFingerprint {
+            connector: device.device_name().await?,
+            monitor: device.edid().await?.as_ref().map(Into::into),
+        })
+    }
+}
+
+/// Change between two monitor configurations, see [`diff_config`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[non_exhaustive]
+pub enum ConfigChange {
+    /// A device appeared
+    Added(ConfigFingerprint),
+    /// A device disappeared
+    Removed(ConfigFingerprint),
+    /// The monitor connected to a device changed
+    Replaced {
+        /// Fingerprint in the previous configuration
+        previous: ConfigFingerprint,
+        /// Fingerprint in the current configuration
+        current: ConfigFingerprint,
+    },
+}
+
+/// Returns the fingerprints of all devices of the running system.
+///
+/// Any error listing a device or reading its EDID is returned, as a missing device would be
+/// reported as removed.
+pub async fn config_fingerprints() -> Result<Vec<ConfigFingerprint>, Error> {
+    let mut fingerprints = Vec::new();
+    let mut devices = Box::pin(brightness_devices());
+    while let Some(device) = devices.try_next().await? {
+        fingerprints.push(ConfigFingerprint::of(&device).await?);
+    }
+    Ok(fingerprints)
+}
+
+/// Compares two monitor configurations and returns the changes, sorted by connector.
+///
+/// A connector present in both configurations with a different monitor is reported as
+/// [`ConfigChange::Replaced`], including when only one of them reports an EDID. If a connector
+/// appears several times in a configuration, only its last fingerprint is considered.
+pub fn diff_config(
+    previous: &[ConfigFingerprint],
+    current: &[ConfigFingerprint],
+) -> Vec<ConfigChange> {
+    let by_connector = |fingerprints: &[ConfigFingerprint]| {
+        fingerprints
+            .iter()
+            .map(|f| (f.connector.clone(), f.clone()))
+            .collect::<BTreeMap<_, _>>()
+    };
+    let mut previous = by_connector(previous);
+    let mut changes = Vec::new();
+    for (connector, current) in by_connector(current) {
+        match previous.remove(&connector) {
+            None => changes.push((connector, ConfigChange::Added(current))),
+            Some(previous) if previous.monitor != current.monitor => {
+                changes.push((connector, ConfigChange::Replaced { previous, current }))
+            }
+            Some(_) => {}
+        }
+    }
+    changes.extend(
+        previous
+            .into_iter()
+            .map(|(connector, previous)| (connector, ConfigChange::Removed(previous))),
+    );
+    changes.sort_by(|(a, _), (b, _)| a.cmp(b));
+    changes.into_iter().map(|(_, change)| change).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fingerprint(connector: &str, serial_number: Option<u32>) -> ConfigFingerprint {
+        ConfigFingerprint {
+            connector: connector.to_owned(),
+            monitor: serial_number.map(|serial_number| MonitorIdentity {
+                manufacturer_id: "DEL".to_owned(),
+                product_code: 0x40A0,
+                serial_number,
+                serial_string: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn reports_no_change_for_same_configuration() {
+        let config = [fingerprint("DP-1", Some(1)), fingerprint("eDP-1", None)];
+        assert!(diff_config(&config, &config).is_empty());
+        assert!(diff_config(&[], &[]).is_empty());
+    }
+
+    #[test]
+    fn reports_added_and_removed_devices_by_connector() {
+        let previous = [fingerprint("DP-2", Some(2)), fingerprint("DP-1", Some(1))];
+        let current = [fingerprint("HDMI-1", Some(3)), fingerprint("DP-1", Some(1))];
+        assert_eq!(
+            diff_config(&previous, &current),
+            [
+                ConfigChange::Removed(fingerprint("DP-2", Some(2))),
+                ConfigChange::Added(fingerprint("HDMI-1", Some(3))),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_replaced_monitors() {
+        let previous = [
+            fingerprint("DP-1", Some(1)),
+            fingerprint("DP-2", None),
+            fingerprint("DP-3", Some(3)),
+        ];
+        let current = [
+            fingerprint("DP-1", Some(4)),
+            fingerprint("DP-2", Some(2)),
+            fingerprint("DP-3", None),
+        ];
+        let replaced = |connector, previous, current| ConfigChange::Replaced {
+            previous: fingerprint(connector, previous),
+            current: fingerprint(connector, current),
+        };
+        assert_eq!(
+            diff_config(&previous, &current),
+            [
+                replaced("DP-1", Some(1), Some(4)),
+                replaced("DP-2", None, Some(2)),
+                replaced("DP-3", Some(3), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn considers_last_fingerprint_of_repeated_connector() {
+        let previous = [fingerprint("DP-1", Some(1)), fingerprint("DP-1", Some(2))];
+        let current = [fingerprint("DP-1", Some(2))];
+        assert!(diff_config(&previous, &current).is_empty());
+    }
+}
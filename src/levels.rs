@@ -0,0 +1,126 @@
+// Copyright (C) 2022 The brightness project authors. Distributed under the 0BSD license.
+
+//! Selection of brightness levels for displays that only support some percentages.
+
+/// Strategy to pick a brightness level when the requested percentage is not supported.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(u8)]
+pub enum Rounding {
+    /// Closest level
+    #[default]
+    Nearest,
+    /// Highest level that does not exceed the requested percentage, or the lowest level if they
+    /// all do
+    Floor,
+    /// Lowest level that is not below the requested percentage, or the highest level if they are
+    /// all below
+    Ceil,
+}
+
+impl Rounding {
+    pub(crate) fn from_u8(value: u8) -> Self {
+        match value {
+            v if v == Rounding::Floor as u8 => Rounding::Floor,
+            v if v == Rounding::Ceil as u8 => Rounding::Ceil,
+            _ => Rounding::Nearest,
+        }
+    }
+}
+
+/// Returns the level to use for a percentage among the supported `levels`, each from 0 to 100.\
+/// With `nonzero`, 0 is never returned unless it is the only supported level. 0 is returned if no
+/// level is supported.
+pub(crate) fn select_level(
+    levels: &[u8],
+    percentage: u32,
+    rounding: Rounding,
+    nonzero: bool,
+) -> u8 {
+    let mut candidates = levels
+        .iter()
+        .copied()
+        .filter(|&level| !nonzero || level != 0)
+        .collect::<Vec<_>>();
+    if candidates.is_empty() {
+        candidates = levels.to_vec();
+    }
+    let candidates = candidates.into_iter();
+    match rounding {
+        Rounding::Nearest => {
+            candidates.min_by_key(|&level| (i64::from(level) - i64::from(percentage)).abs())
+        }
+        Rounding::Floor => candidates
+            .clone()
+            .filter(|&level| u32::from(level) <= percentage)
+            .max()
+            .or_else(|| candidates.min()),
+        Rounding::Ceil => candidates
+            .clone()
+            .filter(|&level| u32::from(level) >= percentage)
+            .min()
+            .or_else(|| candidates.max()),
+    }
+    .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEVELS: [u8; 5] = [0, 10, 40, 50, 100];
+
+    #[test]
+    fn rounding_survives_u8_conversion() {
+        for rounding in [Rounding::Nearest, Rounding::Floor, Rounding::Ceil] {
+            assert_eq!(Rounding::from_u8(rounding as u8), rounding);
+        }
+        assert_eq!(Rounding::from_u8(u8::MAX), Rounding::Nearest);
+    }
+
+    #[test]
+    fn selects_nearest_level() {
+        let select = |percentage| select_level(&LEVELS, percentage, Rounding::Nearest, false);
+        assert_eq!(select(44), 40);
+        assert_eq!(select(47), 50);
+        assert_eq!(select(50), 50);
+        assert_eq!(select(3), 0);
+        assert_eq!(select(200), 100);
+        // Ties go to the lower level.
+        assert_eq!(select(45), 40);
+    }
+
+    #[test]
+    fn selects_floor_level() {
+        let select = |percentage| select_level(&LEVELS, percentage, Rounding::Floor, false);
+        assert_eq!(select(47), 40);
+        assert_eq!(select(50), 50);
+        assert_eq!(select(99), 50);
+        assert_eq!(select(100), 100);
+        let select = |percentage| select_level(&[20, 60], percentage, Rounding::Floor, false);
+        assert_eq!(select(10), 20);
+    }
+
+    #[test]
+    fn selects_ceil_level() {
+        let select = |percentage| select_level(&LEVELS, percentage, Rounding::Ceil, false);
+        assert_eq!(select(41), 50);
+        assert_eq!(select(40), 40);
+        assert_eq!(select(1), 10);
+        assert_eq!(select(200), 100);
+    }
+
+    #[test]
+    fn skips_zero_level_when_nonzero() {
+        for rounding in [Rounding::Nearest, Rounding::Floor, Rounding::Ceil] {
+            assert_eq!(select_level(&LEVELS, 0, rounding, true), 10);
+            assert_eq!(select_level(&LEVELS, 0, rounding, false), 0);
+        }
+        assert_eq!(select_level(&[0], 50, Rounding::Nearest, true), 0);
+    }
+
+    #[test]
+    fn selects_zero_without_levels() {
+        assert_eq!(select_level(&[], 50, Rounding::Nearest, false), 0);
+        assert_eq!(select_level(&[], 50, Rounding::Ceil, true), 0);
+    }
+}
@@ -38,7 +38,10 @@
 //! [systemd 243 or newer](https://github.com/systemd/systemd/blob/877aa0bdcc2900712b02dac90856f181b93c4e40/NEWS#L262).
 //! If this fails because the method is not available, the desired brightness is written to
 //! `/sys/class/backlight/$DEVICE/brightness`, which requires permission (`udev` rules can help with
-//! that).
+//! that). Applications can opt into writing it through `pkexec` instead, which spawns a privileged
+//! helper after the user authenticates with polkit. On GNOME, applications can also opt into setting
+//! the brightness of the built-in panel through the settings daemon, which keeps the brightness
+//! indicator of the desktop in sync.
 //!
 //! # Contribute
 //!
@@ -50,12 +53,27 @@ use std::error::Error as StdError;
 use thiserror::Error;
 
 pub mod blocking;
+pub mod capabilities;
+mod curve;
+#[cfg(all(windows, feature = "tracing"))]
+mod ddc;
+pub mod edid;
+#[cfg(feature = "ffi")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "ffi")))]
+pub mod ffi;
+#[cfg(any(windows, test))]
+mod levels;
+mod mapping;
+#[cfg(any(test, feature = "mock"))]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "mock")))]
+pub mod mock;
+mod options;
 
 #[cfg(feature = "async")]
 #[cfg_attr(doc_cfg, doc(cfg(feature = "async")))]
 cfg_if::cfg_if! {
     if #[cfg(target_os = "linux")] {
-        mod linux;
+        pub mod linux;
         use self::linux as platform;
     } else if #[cfg(windows)] {
         pub mod windows;
@@ -68,14 +86,24 @@ cfg_if::cfg_if! {
 #[cfg(feature = "async")]
 #[cfg_attr(doc_cfg, doc(cfg(feature = "async")))]
 mod r#async {
-    use super::{platform, Error};
+    use super::{
+        capabilities::Capabilities,
+        edid::{DisplayMode, EdidInfo},
+        platform, BrightnessCurve, DeviceKind, Error,
+    };
+    use async_io::Timer;
     use async_trait::async_trait;
-    use futures::{Stream, StreamExt};
+    use futures::{Stream, StreamExt, TryStreamExt};
+    use std::time::Duration;
 
     /// Async interface to get and set brightness.
     #[async_trait]
     pub trait Brightness {
         /// Returns the device name.
+        ///
+        /// This identifies the device on the running system: the backlight device name on Linux
+        /// (e.g. `intel_backlight`) and the GDI name of the monitor device on Windows (e.g.
+        /// `\\.\DISPLAY1\Monitor0`). Windows also provides a friendly name to show to users.
         async fn device_name(&self) -> Result<String, Error>;
 
         /// Returns the current brightness as a percentage.
@@ -83,6 +111,157 @@ mod r#async {
 
         /// Sets the brightness as a percentage.
         async fn set(&mut self, percentage: u32) -> Result<(), Error>;
+
+        /// Returns the EDID information reported by the monitor, if available.
+        ///
+        /// The default implementation returns `None`.
+        async fn edid(&self) -> Result<Option<EdidInfo>, Error> {
+            Ok(None)
+        }
+
+        /// Returns whether the device is the primary display.
+        ///
+        /// On Linux, there is no notion of a primary display outside of the display server, so the
+        /// built-in panel of a laptop is considered to be the primary display. If the panel has
+        /// several devices, the raw device of its eDP, LVDS or DSI connector is primary (e.g.
+        /// `intel_backlight` rather than `acpi_video0`). No device is primary without a built-in
+        /// panel (e.g. on a desktop with ddcci-backlight monitors) or if the choice is ambiguous.
+        /// The primary display is found once per enumeration of devices.
+        ///
+        /// The default implementation returns `false`.
+        async fn is_primary(&self) -> Result<bool, Error> {
+            Ok(false)
+        }
+
+        /// Returns the kind of device.
+        ///
+        /// The default implementation returns [`DeviceKind::Other`].
+        async fn kind(&self) -> Result<DeviceKind, Error> {
+            Ok(DeviceKind::Other)
+        }
+
+        /// Returns the DDC/CI capabilities string reported by the monitor, if available.
+        ///
+        /// Internal displays have no capabilities string. On Linux, it is only available for
+        /// devices of the ddcci-backlight driver. On Windows, it is read from the monitor on first
+        /// use and cached until the device is reopened.
+        ///
+        /// The default implementation returns `None`.
+        async fn raw_capabilities(&self) -> Result<Option<String>, Error> {
+            Ok(None)
+        }
+
+        /// Returns the raw value of the highest brightness, in the native units of the device.
+        ///
+        /// On Linux, this is `max_brightness`. On Windows, this is the maximum reported by DDC/CI
+        /// monitors for VCP code 0x10, and 100 for internal displays whose brightness levels are
+        /// percentages.
+        ///
+        /// The default implementation returns 100, i.e. raw values are assumed to be percentages.
+        async fn brightness_raw_max(&self) -> Result<u32, Error> {
+            Ok(100)
+        }
+
+        /// Returns the parsed DDC/CI capabilities of the monitor, if available.
+        async fn capabilities(&self) -> Result<Option<Capabilities>, Error> {
+            Ok(self
+                .raw_capabilities()
+                .await?
+                .map(|raw| Capabilities::parse(&raw)))
+        }
+
+        /// Returns the preferred resolution and refresh rate of the monitor, as described by its
+        /// EDID.
+        async fn native_mode(&self) -> Result<Option<DisplayMode>, Error> {
+            Ok(self.edid().await?.and_then(|edid| edid.native_mode))
+        }
+
+        /// Toggles between two brightness percentages and returns the one that was applied.
+        ///
+        /// If the current brightness is closer to `low` than to `high`, the brightness is set to
+        /// `high`, otherwise it is set to `low`. A brightness exactly at the midpoint is considered
+        /// closer to `low`.
+        async fn toggle(&mut self, low: u32, high: u32) -> Result<u32, Error> {
+            let current = self.get().await?;
+            let target = crate::toggle_target(current, low, high);
+            self.set(target).await?;
+            Ok(target)
+        }
+
+        /// Sets the brightness for an ambient light level in lux according to a curve, and returns
+        /// the resulting brightness as a percentage.
+        ///
+        /// The brightness is left unchanged if the curve has no points, or if the change would be
+        /// smaller than the hysteresis of the curve.
+        async fn auto_adjust(&mut self, lux: f64, curve: &BrightnessCurve) -> Result<u32, Error> {
+            let current = self.get().await?;
+            match curve.target(current, lux) {
+                Some(target) => {
+                    self.set(target).await?;
+                    Ok(target)
+                }
+                None => Ok(current),
+            }
+        }
+
+        /// Sets the brightness to reach a luminance in nits, given the maximum luminance of the
+        /// display, and returns whether the target is achievable.
+        ///
+        /// See [`crate::percentage_for_nits`] for how the percentage is computed. If `nits` exceeds
+        /// `max_nits`, the brightness is set to 100% and `false` is returned.
+        async fn set_nits(&mut self, nits: f64, max_nits: f64) -> Result<bool, Error> {
+            self.set(crate::percentage_for_nits(nits, max_nits)).await?;
+            Ok(nits <= max_nits)
+        }
+
+        /// Sets the brightness to `level` as a percentage for `duration`, then restores the
+        /// brightness it had before.
+        ///
+        /// The brightness read before the boost is restored even if it was changed in the meantime.
+        /// If the future is dropped before it completes, the brightness is not restored, as this
+        /// requires awaiting the device.
+        async fn boost(&mut self, level: u32, duration: Duration) -> Result<(), Error> {
+            let previous = self.get().await?;
+            self.set(level).await?;
+            Timer::after(duration).await;
+            self.set(previous).await
+        }
+
+        /// Flashes the device for `duration` so that the user can tell which screen it is, then
+        /// restores its brightness.
+        ///
+        /// The brightness alternates a couple of times between its current value and a distant
+        /// one. [`Error::BrightnessNotAdjustable`] is returned if the device still has its
+        /// previous brightness once the first flash has lasted its share of `duration`. The
+        /// brightness is restored if flashing fails midway, but not if the future
+        /// is dropped before it completes, as this requires awaiting the device.
+        async fn identify(&mut self, duration: Duration) -> Result<(), Error> {
+            let previous = self.get().await?;
+            let flash = crate::blocking::identify_level(previous);
+            let step = duration / (2 * crate::blocking::IDENTIFY_FLASHES);
+            let flashed = async {
+                for i in 0..crate::blocking::IDENTIFY_FLASHES {
+                    self.set(flash).await?;
+                    Timer::after(step).await;
+                    // Monitors may apply writes with a delay, so the brightness is read back after
+                    // waiting.
+                    if i == 0 && self.get().await? == previous {
+                        return Err(Error::BrightnessNotAdjustable {
+                            device: self.device_name().await?,
+                        });
+                    }
+                    self.set(previous).await?;
+                    Timer::after(step).await;
+                }
+                Ok(())
+            }
+            .await;
+            if flashed.is_err() {
+                // Best effort, the error that interrupted flashing is the one reported.
+                let _ = self.set(previous).await;
+            }
+            flashed
+        }
     }
 
     /// Async brightness device.
@@ -102,16 +281,250 @@ mod r#async {
         async fn set(&mut self, percentage: u32) -> Result<(), Error> {
             self.0.set(percentage).await
         }
+
+        async fn edid(&self) -> Result<Option<EdidInfo>, Error> {
+            self.0.edid().await
+        }
+
+        async fn is_primary(&self) -> Result<bool, Error> {
+            self.0.is_primary().await
+        }
+
+        async fn kind(&self) -> Result<DeviceKind, Error> {
+            self.0.kind().await
+        }
+
+        async fn raw_capabilities(&self) -> Result<Option<String>, Error> {
+            self.0.raw_capabilities().await
+        }
+
+        async fn brightness_raw_max(&self) -> Result<u32, Error> {
+            self.0.brightness_raw_max().await
+        }
+    }
+
+    impl BrightnessDevice {
+        /// Resets all options of the device to their defaults.
+        ///
+        /// See [`crate::blocking::BrightnessDevice::reset_options`].
+        pub fn reset_options(&mut self) -> Result<(), Error> {
+            crate::DeviceOptions::new().apply(self)
+        }
     }
 
     /// Returns all brightness devices on the running system.
+    ///
+    /// See [`crate::blocking::brightness_devices`] for how an incomplete enumeration is reported.
     pub fn brightness_devices() -> impl Stream<Item = Result<BrightnessDevice, Error>> {
         platform::brightness_devices().map(|r| r.map(BrightnessDevice).map_err(Into::into))
     }
+
+    /// Returns all brightness devices on the running system, listing them again if monitors are
+    /// connected or disconnected while they are listed.
+    ///
+    /// See [`crate::blocking::brightness_devices_retry`].
+    pub async fn brightness_devices_retry(
+        attempts: usize,
+        delay: Duration,
+    ) -> Result<Vec<BrightnessDevice>, Error> {
+        let mut attempt = 1;
+        loop {
+            match platform::brightness_devices_for_retry()
+                .try_collect::<Vec<_>>()
+                .await
+            {
+                Ok(devices) => return Ok(devices.into_iter().map(BrightnessDevice).collect()),
+                Err(e) if e.is_transient() && attempt < attempts => {
+                    attempt += 1;
+                    Timer::after(delay).await;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
 }
 
+/// Returns the brightness percentage needed for a display to emit `target_nits`, given its maximum
+/// luminance `max_nits` (e.g. from the display specifications or a measurement).
+///
+/// Luminance is assumed to be proportional to the brightness percentage, which is a simplification
+/// for most displays. The result is clamped between 0 and 100, and 0 is returned if either value is
+/// not a positive number.
+///
+/// ```
+/// assert_eq!(brightness::percentage_for_nits(120.0, 400.0), 30);
+/// assert_eq!(brightness::percentage_for_nits(500.0, 400.0), 100);
+/// ```
+pub fn percentage_for_nits(target_nits: f64, max_nits: f64) -> u32 {
+    if !(target_nits > 0.0 && max_nits > 0.0) {
+        return 0;
+    }
+    (target_nits / max_nits * 100.0).round().min(100.0) as u32
+}
+
+/// Returns the brightness to apply when toggling away from `current` between `low` and `high`.
+pub(crate) fn toggle_target(current: u32, low: u32, high: u32) -> u32 {
+    if current.abs_diff(low) <= current.abs_diff(high) {
+        high
+    } else {
+        low
+    }
+}
+
+pub use curve::BrightnessCurve;
+pub use mapping::BrightnessMapping;
+pub use options::DeviceOptions;
+
+#[cfg(feature = "async")]
+pub use r#async::{brightness_devices, brightness_devices_retry, Brightness, BrightnessDevice};
+
+#[cfg(feature = "async")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "async")))]
+mod auto_restore;
+
+#[cfg(feature = "async")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "async")))]
+mod batch;
+
+#[cfg(feature = "async")]
+pub use batch::{
+    devices_with_brightness, get_all, get_primary_brightness, set_all, set_primary_brightness,
+    swap_brightness, working_devices,
+};
+
+#[cfg(feature = "async")]
+pub use auto_restore::AutoRestore;
+
 #[cfg(feature = "async")]
-pub use r#async::{brightness_devices, Brightness, BrightnessDevice};
+#[cfg_attr(doc_cfg, doc(cfg(feature = "async")))]
+mod capped;
+
+#[cfg(feature = "async")]
+pub use capped::CappedBrightness;
+
+#[cfg(feature = "async")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "async")))]
+mod command;
+
+#[cfg(feature = "async")]
+pub use command::{execute, BrightnessCommand, BrightnessResponse};
+
+#[cfg(feature = "async")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "async")))]
+mod conflict;
+
+#[cfg(feature = "async")]
+pub use conflict::{set_detecting_conflict, PossibleConflict};
+
+#[cfg(feature = "async")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "async")))]
+mod dim_on_idle;
+
+#[cfg(feature = "async")]
+pub use dim_on_idle::DimOnIdle;
+
+#[cfg(feature = "async")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "async")))]
+mod fade;
+
+#[cfg(feature = "async")]
+pub use fade::{transition_all, FadeScheduler};
+
+#[cfg(feature = "async")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "async")))]
+mod fingerprint;
+
+#[cfg(feature = "async")]
+pub use fingerprint::{
+    config_fingerprints, diff_config, ConfigChange, ConfigFingerprint, MonitorIdentity,
+};
+
+#[cfg(feature = "async")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "async")))]
+mod observer;
+
+#[cfg(feature = "async")]
+pub use observer::BrightnessObserver;
+
+#[cfg(feature = "async")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "async")))]
+mod power_aware;
+
+#[cfg(feature = "async")]
+pub use power_aware::PowerAwareBrightness;
+
+#[cfg(feature = "async")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "async")))]
+mod power_budget;
+
+#[cfg(feature = "async")]
+pub use power_budget::PowerBudget;
+
+#[cfg(feature = "async")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "async")))]
+mod schedule;
+
+#[cfg(feature = "async")]
+pub use schedule::ScheduledRamp;
+
+#[cfg(feature = "async")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "async")))]
+mod snapshot;
+
+#[cfg(feature = "async")]
+pub use snapshot::{diff_since, BrightnessSnapshotSet};
+
+#[cfg(feature = "async")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "async")))]
+mod sync_group;
+
+#[cfg(feature = "async")]
+pub use sync_group::SyncGroup;
+
+#[cfg(feature = "async")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "async")))]
+mod write_latency;
+
+#[cfg(feature = "async")]
+pub use write_latency::WriteLatency;
+
+/// Returns the identity of all brightness devices on the running system.
+///
+/// Unlike `brightness_devices`, this neither opens control handles nor communicates with the
+/// devices, which makes it cheap enough to call synchronously (e.g. while loading a configuration).
+pub fn list_devices_blocking() -> Result<Vec<DeviceInfo>, Error> {
+    blocking::list_devices()
+}
+
+/// Identity of a brightness device.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct DeviceInfo {
+    /// Device name, as returned by `device_name`
+    pub name: String,
+    /// Platform path of the device
+    ///
+    /// On Linux, this is the sysfs directory of the backlight device. On Windows, this is the
+    /// device interface path.
+    pub path: String,
+    /// Kind of device
+    pub kind: DeviceKind,
+}
+
+/// Kind of brightness device.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum DeviceKind {
+    /// Built-in panel of a laptop or all-in-one computer
+    InternalPanel,
+    /// External monitor controlled through DDC/CI
+    ExternalDdc,
+    /// Virtual display (e.g. of a remote desktop session or a virtual machine), whose brightness
+    /// usually cannot be controlled
+    Virtual,
+    /// Device of another kind
+    Other,
+}
 
 /// Errors used in this API
 #[derive(Debug, Error)]
@@ -138,4 +551,91 @@ pub enum Error {
         /// Cause
         source: Box<dyn StdError + Send + Sync>,
     },
+
+    /// No brightness device was found
+    #[error("No brightness device found")]
+    NoDevices,
+
+    /// No brightness device is the primary display
+    #[error("No primary brightness device found")]
+    NoPrimaryDevice,
+
+    /// No brightness device has the requested name
+    #[error("Brightness device {device} not found")]
+    DeviceNotFound {
+        /// Device name
+        device: String,
+    },
+
+    /// The operation was cancelled
+    #[error("Operation was cancelled")]
+    Cancelled,
+
+    /// Devices expected to have the same brightness did not
+    #[error("Brightness of devices diverged between {min}% and {max}%")]
+    DevicesDiverged {
+        /// Lowest brightness percentage
+        min: u32,
+        /// Highest brightness percentage
+        max: u32,
+    },
+
+    /// The device was disconnected
+    #[error("Brightness device {device} is disconnected")]
+    DeviceDisconnected {
+        /// Device name
+        device: String,
+    },
+
+    /// Brightness was set, but the device applied a different value than requested
+    #[error("Brightness device {device} applied {applied} instead of {requested}")]
+    SettingClamped {
+        /// Device name
+        device: String,
+        /// Raw value requested
+        requested: u32,
+        /// Raw value read back from the device
+        applied: u32,
+    },
+
+    /// The brightness of the device did not change when set
+    #[error("Brightness of device {device} cannot be adjusted")]
+    BrightnessNotAdjustable {
+        /// Device name
+        device: String,
+    },
+}
+
+impl Error {
+    /// Returns the category of the error, which tells how it may be handled.
+    ///
+    /// The category is derived from the platform error causing it (e.g. its `HRESULT` on Windows),
+    /// so that callers do not need to know platform error codes.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::ListingDevicesFailed(source)
+            | Error::GettingDeviceInfoFailed { source, .. }
+            | Error::SettingBrightnessFailed { source, .. } => {
+                blocking::error_kind(source.as_ref())
+            }
+            Error::BrightnessNotAdjustable { .. } => ErrorKind::Unsupported,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+/// Category of an [`Error`], telling how callers may handle it.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The handle to the device is no longer valid, e.g. after the system slept or the monitor was
+    /// power-cycled. Listing devices again (or enabling `set_reopen_on_stale` on Windows) may help.
+    StaleHandle,
+    /// The device does not support the operation, which should not be attempted again.
+    Unsupported,
+    /// The failure is likely temporary, e.g. a DDC/CI communication glitch or monitors being
+    /// connected while devices are listed, so retrying may succeed.
+    Transient,
+    /// Any other failure
+    Other,
 }
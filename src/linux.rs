@@ -2,20 +2,208 @@
 
 //! Platform-specific implementation for Linux.
 
+pub use crate::blocking::linux::{
+    diagnose_devices, logind_has_set_brightness, BacklightDiagnostic, BacklightScale,
+    BrightnessChange, Exclusion, SetMechanism,
+};
+
 use crate::{
     blocking::linux::{
-        read_value, SysError, Value, BACKLIGHT_DIR, SESSION_INTERFACE, SESSION_OBJECT_PATH,
-        SET_BRIGHTNESS_METHOD, USER_DBUS_NAME,
+        device_info, device_kind, gsd_percentage, is_adaptive, is_internal_panel, is_powered,
+        is_primary, missing_values, percentage_to_raw, preview, probe_logind, raw_to_percentage,
+        read_capabilities, read_edid, read_percentage, read_scale, read_value, record_write,
+        set_mechanism, trusted_value, verify_value, watch_all_brightness, Coalescer, LastWrite,
+        SharedPrimary, SysError, Value, BACKLIGHT_DIR, BRIGHTNESS_PROPERTY, GSD_POWER_DBUS_NAME,
+        GSD_POWER_OBJECT_PATH, GSD_SCREEN_INTERFACE, LOGIND_TIMEOUT, PROPERTIES_INTERFACE,
+        SESSION_INTERFACE, SESSION_OBJECT_PATH, SET_BRIGHTNESS_METHOD, SET_PROPERTY_METHOD,
+        USER_DBUS_NAME,
     },
-    Error,
+    edid::EdidInfo,
+    BrightnessDevice, BrightnessMapping, DeviceInfo, DeviceKind, Error,
 };
+use async_io::{Async, Timer};
 use async_trait::async_trait;
 use blocking::unblock;
-use futures::{future::ready, Stream, StreamExt};
+use futures::{
+    future::{ready, select, Either},
+    stream, Stream, StreamExt,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+    time::Duration,
+};
+
+/// Linux-specific async brightness functionality.
+#[async_trait]
+pub trait BrightnessExt {
+    /// Sets whether `get` returns the brightness last set through this device instead of reading
+    /// `actual_brightness`.
+    ///
+    /// See [`crate::blocking::linux::BrightnessExt::set_trust_last_write`].
+    fn set_trust_last_write(&mut self, trust: bool) -> Result<(), Error>;
+
+    /// Sets whether `set` writes the brightness through `pkexec` when writing to the brightness
+    /// file is not permitted.
+    ///
+    /// See [`crate::blocking::linux::BrightnessExt::set_use_pkexec`].
+    fn set_use_pkexec(&mut self, enabled: bool);
+
+    /// Sets whether `set` goes through the GNOME settings daemon for built-in panels.
+    ///
+    /// See [`crate::blocking::linux::BrightnessExt::set_use_gnome_settings_daemon`].
+    fn set_use_gnome_settings_daemon(&mut self, enabled: bool);
+
+    /// Sets whether the device is read-only.
+    ///
+    /// See [`crate::blocking::linux::BrightnessExt::set_read_only`].
+    fn set_read_only(&mut self, read_only: bool);
+
+    /// Sets whether `set` reads `actual_brightness` back after writing the brightness file.
+    ///
+    /// See [`crate::blocking::linux::BrightnessExt::set_verify_writes`].
+    fn set_verify_writes(&mut self, enabled: bool);
+
+    /// Sets the system bus connection used by `set` to call logind.
+    ///
+    /// See [`crate::blocking::linux::BrightnessExt::set_system_connection`].
+    fn set_system_connection(&mut self, connection: zbus::Connection);
+
+    /// Sets how long `set` waits for logind to answer a `SetBrightness` call.
+    ///
+    /// See [`crate::blocking::linux::BrightnessExt::set_logind_timeout`].
+    fn set_logind_timeout(&mut self, timeout: Duration);
+
+    /// Sets a custom mapping between percentages and raw values used by `get` and `set`, or
+    /// restores the default linear mapping with `None`.
+    ///
+    /// See [`crate::blocking::linux::BrightnessExt::set_mapping`].
+    fn set_mapping(&mut self, mapping: Option<BrightnessMapping>);
+
+    /// Returns the percentage that `set(requested)` applies, without accessing the device.
+    ///
+    /// See [`crate::blocking::linux::BrightnessExt::preview`].
+    fn preview(&self, requested: u32) -> u32;
+
+    /// Returns whether the panel adjusts its backlight to the displayed content.
+    ///
+    /// See [`crate::blocking::linux::BrightnessExt::is_adaptive`].
+    async fn is_adaptive(&self) -> Result<bool, Error>;
+
+    /// Returns whether the display of the device is powered on, or `None` if this is unknown.
+    ///
+    /// See [`crate::blocking::linux::BrightnessExt::is_powered`].
+    async fn is_powered(&self) -> Result<Option<bool>, Error>;
+
+    /// Returns how raw brightness values relate to the light emitted by the panel.
+    ///
+    /// See [`crate::blocking::linux::BrightnessExt::scale`].
+    async fn scale(&self) -> Result<BacklightScale, Error>;
+
+    /// Returns how `set` changes the brightness of the device.
+    ///
+    /// See [`crate::blocking::linux::BrightnessExt::set_mechanism`].
+    async fn set_mechanism(&self) -> Result<SetMechanism, Error>;
+}
+
+/// Returns a stream of the brightness changes of all backlight devices.
+///
+/// logind does not signal brightness changes on D-Bus. However, brightness set through logind, by
+/// desktop environments (e.g. the GNOME brightness slider) or by tools like `brightnessctl` ends up
+/// written to sysfs, so changes are detected with inotify on the brightness files of the devices.
+/// Changes made by the hardware (e.g. brightness keys handled by the firmware) are detected if the
+/// driver signals them on `actual_brightness` or on a `brightness_hw_changed` attribute. Changes
+/// made through this crate are reported too. Percentages are computed with the default linear
+/// mapping, see [`brightness_changes_with_mappings`] for devices with a custom mapping.
+///
+/// Devices added after the stream is created are not watched. The stream ends after an error
+/// reading inotify events.
+pub fn brightness_changes() -> Result<impl Stream<Item = Result<BrightnessChange, Error>>, Error> {
+    brightness_changes_coalesced(Duration::ZERO)
+}
+
+/// Returns a stream of the brightness changes of all backlight devices, reported once they settle.
+///
+/// A change is only reported once the brightness of the device has not changed for `window`, so
+/// that a fade made by another application results in a single change with the final brightness.
+/// Other than that, this behaves like [`brightness_changes`].
+pub fn brightness_changes_coalesced(
+    window: Duration,
+) -> Result<impl Stream<Item = Result<BrightnessChange, Error>>, Error> {
+    brightness_changes_with_mappings(window, HashMap::new())
+}
+
+/// Returns a stream of the brightness changes of all backlight devices, reported once they settle
+/// and converted to percentages with the custom mappings of the devices.
+///
+/// `mappings` holds the mappings set with [`BrightnessExt::set_mapping`], by device name, so that
+/// reported percentages agree with `get`. Devices without an entry use the default linear mapping.
+/// Other than that, this behaves like [`brightness_changes_coalesced`].
+pub fn brightness_changes_with_mappings(
+    window: Duration,
+    mappings: HashMap<String, BrightnessMapping>,
+) -> Result<impl Stream<Item = Result<BrightnessChange, Error>>, Error> {
+    let (inotify, devices) = watch_all_brightness()?;
+    let inotify = Async::new(inotify).map_err(SysError::WatchingBacklightDevicesFailed)?;
+    let state = (
+        Some(inotify),
+        devices,
+        mappings,
+        Coalescer::new(window),
+        VecDeque::<String>::new(),
+    );
+    Ok(stream::unfold(
+        state,
+        |(mut inotify, devices, mappings, mut coalescer, mut pending)| async move {
+            loop {
+                if let Some(device) = pending.pop_front() {
+                    let change = read_percentage(&device, mappings.get(&device))
+                        .map(|percentage| BrightnessChange { device, percentage })
+                        .map_err(Into::into);
+                    return Some((change, (inotify, devices, mappings, coalescer, pending)));
+                }
+                coalescer.flush(&mut pending);
+                if !pending.is_empty() {
+                    continue;
+                }
+                let read = inotify.as_mut()?.read_with_mut(|i| i.read_events());
+                let events = match coalescer.next_deadline() {
+                    Some(deadline) => match select(Box::pin(read), Timer::at(deadline)).await {
+                        Either::Left((events, _)) => events,
+                        Either::Right(_) => continue,
+                    },
+                    None => read.await,
+                };
+                match events {
+                    Ok(descriptors) => coalescer.record(&descriptors, &devices),
+                    Err(e) => {
+                        let e = SysError::WatchingBacklightDevicesFailed(e).into();
+                        return Some((Err(e), (None, devices, mappings, coalescer, pending)));
+                    }
+                }
+            }
+        },
+    ))
+}
 
 #[derive(Debug)]
 pub(crate) struct AsyncDeviceImpl {
     device: String,
+    last_write: Mutex<Option<LastWrite>>,
+    use_pkexec: bool,
+    use_gnome_settings_daemon: bool,
+    read_only: bool,
+    verify_writes: bool,
+    logind_timeout: Duration,
+    system_connection: Option<zbus::Connection>,
+    mapping: Option<BrightnessMapping>,
+    primary: SharedPrimary,
+}
+
+impl AsyncDeviceImpl {
+    pub(crate) fn info(&self) -> Result<DeviceInfo, Error> {
+        Ok(device_info(&self.device)?)
+    }
 }
 
 #[async_trait]
@@ -26,66 +214,102 @@ impl crate::Brightness for AsyncDeviceImpl {
 
     async fn get(&self) -> Result<u32, Error> {
         let max = read_value(&self.device, Value::Max)?;
-        let actual = read_value(&self.device, Value::Actual)?;
-        let percentage = if max == 0 {
-            0
-        } else {
-            (actual * 100 / max) as u32
+        let actual = match trusted_value(&self.last_write) {
+            Some(value) => value,
+            None => read_value(&self.device, Value::Actual)?,
         };
-        Ok(percentage)
+        Ok(raw_to_percentage(actual, max, self.mapping.as_ref()))
     }
 
     async fn set(&mut self, percentage: u32) -> Result<(), Error> {
+        if self.read_only {
+            return Err(SysError::DeviceReadOnly {
+                device: self.device.clone(),
+            }
+            .into());
+        }
         let percentage = percentage.min(100);
         let max = read_value(&self.device, Value::Max)?;
-        let desired_value = (u64::from(percentage) * u64::from(max) / 100) as u32;
-        let desired = ("backlight", &self.device, desired_value);
-        let bus = zbus::Connection::system()
+        let desired_value = percentage_to_raw(percentage, max, self.mapping.as_ref());
+        if self.use_gnome_settings_daemon
+            && is_internal_panel(&self.device)?
+            && set_with_gnome_settings_daemon(gsd_percentage(
+                percentage,
+                desired_value,
+                max,
+                self.mapping.as_ref(),
+            ))
             .await
-            .map_err(|e| Error::SettingBrightnessFailed {
-                device: self.device.clone(),
-                source: e.into(),
-            })?;
-        let response = bus
-            .call_method(
-                Some(USER_DBUS_NAME),
-                SESSION_OBJECT_PATH,
-                Some(SESSION_INTERFACE),
-                SET_BRIGHTNESS_METHOD,
-                &desired,
-            )
-            .await;
-        match response {
-            Ok(_) => Ok(()),
-            Err(zbus::Error::MethodError(..)) => {
-                // Setting brightness through dbus may not work on older systems that don't have
-                // the `SetBrightness` method. Fall back to writing to the brightness file (which
-                // requires permission).
-                set_value(self.device.clone(), desired_value).await?;
-                Ok(())
+            .is_ok()
+        {
+            record_write(&self.last_write, desired_value);
+            return Ok(());
+        }
+        if !set_with_logind(
+            &self.device,
+            desired_value,
+            self.system_connection.as_ref(),
+            self.logind_timeout,
+        )
+        .await?
+        {
+            // Setting brightness through dbus may not work on older systems that don't have the
+            // `SetBrightness` method. Fall back to writing to the brightness file (which requires
+            // permission).
+            set_value(self.device.clone(), desired_value, self.use_pkexec).await?;
+            if self.verify_writes {
+                verify_value(&self.device, desired_value)?;
             }
-            Err(e) => Err(Error::SettingBrightnessFailed {
-                device: self.device.clone(),
-                source: e.into(),
-            }),
         }
+        record_write(&self.last_write, desired_value);
+        Ok(())
+    }
+
+    async fn edid(&self) -> Result<Option<EdidInfo>, Error> {
+        Ok(read_edid(&self.device)?.and_then(|edid| EdidInfo::parse(&edid)))
+    }
+
+    async fn is_primary(&self) -> Result<bool, Error> {
+        Ok(is_primary(&self.device, &self.primary)?)
+    }
+
+    async fn kind(&self) -> Result<DeviceKind, Error> {
+        Ok(device_kind(&self.device)?)
+    }
+
+    async fn raw_capabilities(&self) -> Result<Option<String>, Error> {
+        Ok(read_capabilities(&self.device)?)
+    }
+
+    async fn brightness_raw_max(&self) -> Result<u32, Error> {
+        Ok(read_value(&self.device, Value::Max)?)
     }
 }
 
 pub(crate) fn brightness_devices() -> impl Stream<Item = Result<AsyncDeviceImpl, SysError>> {
+    let primary = SharedPrimary::default();
     match std::fs::read_dir(BACKLIGHT_DIR) {
         Ok(devices) => futures::stream::iter(
             devices
-                .map(|device| {
+                .map(move |device| {
                     let device = device.map_err(SysError::ReadingBacklightDirFailed)?;
-                    let path = device.path();
-                    let keep = path.join(Value::Actual.as_str()).exists()
-                        && path.join(Value::Max.as_str()).exists();
+                    let keep = missing_values(&device.path()).is_empty();
                     Ok(device
                         .file_name()
                         .into_string()
                         .ok()
-                        .map(|device| AsyncDeviceImpl { device })
+                        .map(|device| AsyncDeviceImpl {
+                            device,
+                            last_write: Default::default(),
+                            use_pkexec: false,
+                            use_gnome_settings_daemon: false,
+                            read_only: false,
+                            verify_writes: false,
+                            logind_timeout: LOGIND_TIMEOUT,
+                            system_connection: None,
+                            mapping: None,
+                            primary: primary.clone(),
+                        })
                         .filter(|_| keep))
                 })
                 .filter_map(Result::transpose),
@@ -97,6 +321,159 @@ pub(crate) fn brightness_devices() -> impl Stream<Item = Result<AsyncDeviceImpl,
     }
 }
 
-async fn set_value(device: String, value: u32) -> Result<(), SysError> {
-    unblock(move || crate::blocking::linux::set_value(&device, value)).await
+/// Returns the backlight devices for `brightness_devices_retry`, which are listed as usual.
+pub(crate) fn brightness_devices_for_retry() -> impl Stream<Item = Result<AsyncDeviceImpl, SysError>>
+{
+    brightness_devices()
+}
+
+/// Sets the brightness of a backlight device through logind.
+///
+/// See [`crate::blocking::linux::logind_has_set_brightness`].
+async fn set_with_logind(
+    device: &str,
+    value: u32,
+    connection: Option<&zbus::Connection>,
+    timeout: Duration,
+) -> Result<bool, Error> {
+    if logind_has_set_brightness() == Some(false) {
+        return Ok(false);
+    }
+    let to_error = |e: zbus::Error| Error::SettingBrightnessFailed {
+        device: device.to_owned(),
+        source: e.into(),
+    };
+    let bus = match connection {
+        Some(connection) => connection.clone(),
+        None => zbus::Connection::system().await.map_err(to_error)?,
+    };
+    if logind_has_set_brightness().is_none() {
+        let probe_bus = zbus::blocking::Connection::from(bus.clone());
+        // If introspection fails, `SetBrightness` is attempted and will be probed again next time.
+        if unblock(move || probe_logind(&probe_bus)).await == Some(false) {
+            return Ok(false);
+        }
+    }
+    let body = ("backlight", device, value);
+    let call = bus.call_method(
+        Some(USER_DBUS_NAME),
+        SESSION_OBJECT_PATH,
+        Some(SESSION_INTERFACE),
+        SET_BRIGHTNESS_METHOD,
+        &body,
+    );
+    let response = match select(Box::pin(call), Timer::after(timeout)).await {
+        Either::Left((response, _)) => response,
+        Either::Right(_) => {
+            return Err(SysError::LogindTimedOut {
+                device: device.to_owned(),
+                timeout,
+            }
+            .into())
+        }
+    };
+    match response {
+        Ok(_) => Ok(true),
+        Err(zbus::Error::MethodError(..)) => Ok(false),
+        Err(e) => Err(to_error(e)),
+    }
+}
+
+async fn set_with_gnome_settings_daemon(percentage: u32) -> zbus::Result<()> {
+    let bus = zbus::Connection::session().await?;
+    bus.call_method(
+        Some(GSD_POWER_DBUS_NAME),
+        GSD_POWER_OBJECT_PATH,
+        Some(PROPERTIES_INTERFACE),
+        SET_PROPERTY_METHOD,
+        &(
+            GSD_SCREEN_INTERFACE,
+            BRIGHTNESS_PROPERTY,
+            zbus::zvariant::Value::from(percentage as i32),
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+async fn set_value(device: String, value: u32, use_pkexec: bool) -> Result<(), SysError> {
+    unblock(move || crate::blocking::linux::set_value(&device, value, use_pkexec)).await
+}
+
+#[async_trait]
+impl BrightnessExt for BrightnessDevice {
+    fn set_trust_last_write(&mut self, trust: bool) -> Result<(), Error> {
+        let last_write = if trust {
+            Some(LastWrite::new(&self.0.device)?)
+        } else {
+            None
+        };
+        *self.0.last_write.get_mut().unwrap() = last_write;
+        Ok(())
+    }
+
+    fn set_use_pkexec(&mut self, enabled: bool) {
+        self.0.use_pkexec = enabled;
+    }
+
+    fn set_use_gnome_settings_daemon(&mut self, enabled: bool) {
+        self.0.use_gnome_settings_daemon = enabled;
+    }
+
+    fn set_read_only(&mut self, read_only: bool) {
+        self.0.read_only = read_only;
+    }
+
+    fn set_verify_writes(&mut self, enabled: bool) {
+        self.0.verify_writes = enabled;
+    }
+
+    fn set_logind_timeout(&mut self, timeout: Duration) {
+        self.0.logind_timeout = timeout;
+    }
+
+    fn set_system_connection(&mut self, connection: zbus::Connection) {
+        self.0.system_connection = Some(connection);
+    }
+
+    fn set_mapping(&mut self, mapping: Option<BrightnessMapping>) {
+        self.0.mapping = mapping;
+    }
+
+    fn preview(&self, requested: u32) -> u32 {
+        preview(requested, self.0.mapping.as_ref())
+    }
+
+    async fn is_adaptive(&self) -> Result<bool, Error> {
+        let device = self.0.device.clone();
+        unblock(move || Ok(is_adaptive(&device)?)).await
+    }
+
+    async fn is_powered(&self) -> Result<Option<bool>, Error> {
+        let device = self.0.device.clone();
+        unblock(move || Ok(is_powered(&device)?)).await
+    }
+
+    async fn scale(&self) -> Result<BacklightScale, Error> {
+        let device = self.0.device.clone();
+        unblock(move || Ok(read_scale(&device)?)).await
+    }
+
+    async fn set_mechanism(&self) -> Result<SetMechanism, Error> {
+        let device = self.0.device.clone();
+        let use_pkexec = self.0.use_pkexec;
+        let use_gnome_settings_daemon = self.0.use_gnome_settings_daemon;
+        let read_only = self.0.read_only;
+        let connection = self.0.system_connection.clone().map(Into::into);
+        unblock(move || {
+            set_mechanism(
+                &device,
+                use_pkexec,
+                use_gnome_settings_daemon,
+                read_only,
+                connection,
+            )
+        })
+        .await
+    }
 }
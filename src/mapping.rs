@@ -0,0 +1,166 @@
+// Copyright (C) 2022 The brightness project authors. Distributed under the 0BSD license.
+
+//! Custom mapping between brightness percentages and raw values.
+
+/// Mapping between brightness percentages and raw values, for devices whose response is not
+/// linear.
+///
+/// The mapping is defined by `(percentage, raw value)` control points, between which raw values
+/// are linearly interpolated. `set` maps the requested percentage to a raw value, and `get` maps
+/// the raw value back to a percentage. If there is no point for 0% or 100%, the lowest or highest
+/// raw value of the device is used for it, as with the default linear mapping.
+///
+/// Raw values are in the native units of the device: the sysfs value on Linux, the brightness
+/// level between 0 and 100 for internal displays on Windows, and the DDC/CI value for external
+/// monitors on Windows.
+///
+/// ```
+/// use brightness::BrightnessMapping;
+///
+/// // A measured table with the raw value to use for each percentage.
+/// let table = (0..=100).map(|p| p * p / 100).collect::<Vec<u32>>();
+/// let mapping = BrightnessMapping::new((0..=100).zip(table)).unwrap();
+/// assert_eq!(mapping.points().len(), 101);
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct BrightnessMapping {
+    points: Vec<(u32, u32)>,
+}
+
+impl BrightnessMapping {
+    /// Creates a mapping from `(percentage, raw value)` control points.
+    ///
+    /// Points do not need to be sorted. `None` is returned if there are no points, if a percentage
+    /// is above 100 or given twice, or if raw values decrease as the percentage increases, as raw
+    /// values could then not be mapped back to a single percentage.
+    pub fn new<I>(points: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = (u32, u32)>,
+    {
+        let mut points = points.into_iter().collect::<Vec<_>>();
+        points.sort_unstable();
+        let valid = points.last()?.0 <= 100
+            && points
+                .windows(2)
+                .all(|w| w[0].0 < w[1].0 && w[0].1 <= w[1].1);
+        valid.then_some(BrightnessMapping { points })
+    }
+
+    /// Returns the control points, sorted by percentage.
+    pub fn points(&self) -> &[(u32, u32)] {
+        &self.points
+    }
+
+    /// Returns the raw value for a percentage, given the raw range of the device.
+    pub(crate) fn to_raw(&self, percentage: u32, min: u32, max: u32) -> u32 {
+        interpolate(&self.complete(min, max), percentage.min(100))
+    }
+
+    /// Returns the percentage for a raw value, given the raw range of the device.
+    pub(crate) fn to_percentage(&self, value: u32, min: u32, max: u32) -> u32 {
+        let points = self
+            .complete(min, max)
+            .into_iter()
+            .map(|(percentage, raw)| (raw, percentage))
+            .collect::<Vec<_>>();
+        interpolate(&points, value)
+    }
+
+    /// Returns the percentage reported by `get` after `set` applies `percentage`, given the raw
+    /// range of the device. Without a range, the range of the control points is used.
+    pub(crate) fn round_trip(&self, percentage: u32, range: Option<(u32, u32)>) -> u32 {
+        let (min, max) = range.unwrap_or((self.points[0].1, self.points[self.points.len() - 1].1));
+        self.to_percentage(self.to_raw(percentage, min, max), min, max)
+    }
+
+    /// Returns the control points with points for 0% and 100% added from the range of the device
+    /// if missing, keeping raw values increasing.
+    fn complete(&self, min: u32, max: u32) -> Vec<(u32, u32)> {
+        let (first, last) = (self.points[0], self.points[self.points.len() - 1]);
+        let mut points = Vec::with_capacity(self.points.len() + 2);
+        if first.0 > 0 {
+            points.push((0, min.min(first.1)));
+        }
+        points.extend_from_slice(&self.points);
+        if last.0 < 100 {
+            points.push((100, max.max(last.1)));
+        }
+        points
+    }
+}
+
+/// Linearly interpolates between points sorted by `x`, using the closest point outside of them.
+fn interpolate(points: &[(u32, u32)], x: u32) -> u32 {
+    let upper = points.partition_point(|&(px, _)| px < x);
+    let (x1, y1) = match points.get(upper) {
+        Some(&point) => point,
+        None => return points[points.len() - 1].1,
+    };
+    if upper == 0 || x1 == x {
+        return y1;
+    }
+    let (x0, y0) = points[upper - 1];
+    let fraction = f64::from(x - x0) / f64::from(x1 - x0);
+    (f64::from(y0) + fraction * (f64::from(y1) - f64::from(y0))).round() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_invalid_points() {
+        assert_eq!(BrightnessMapping::new(None), None);
+        assert_eq!(BrightnessMapping::new([(101, 10)]), None);
+        assert_eq!(BrightnessMapping::new([(50, 10), (50, 20)]), None);
+        assert_eq!(BrightnessMapping::new([(20, 30), (80, 10)]), None);
+    }
+
+    #[test]
+    fn sorts_points_and_accepts_flat_segments() {
+        let mapping = BrightnessMapping::new([(80, 40), (20, 10), (50, 40)]).unwrap();
+        assert_eq!(mapping.points(), [(20, 10), (50, 40), (80, 40)]);
+    }
+
+    #[test]
+    fn implies_endpoints_from_device_range() {
+        let mapping = BrightnessMapping::new([(50, 100)]).unwrap();
+        assert_eq!(mapping.to_raw(0, 0, 1000), 0);
+        assert_eq!(mapping.to_raw(25, 0, 1000), 50);
+        assert_eq!(mapping.to_raw(50, 0, 1000), 100);
+        assert_eq!(mapping.to_raw(100, 0, 1000), 1000);
+        assert_eq!(mapping.to_raw(150, 0, 1000), 1000);
+        // Explicit endpoints outside of the range of the device are kept.
+        let mapping = BrightnessMapping::new([(0, 20), (100, 80)]).unwrap();
+        assert_eq!(mapping.to_raw(0, 0, 1000), 20);
+        assert_eq!(mapping.to_raw(100, 0, 1000), 80);
+    }
+
+    #[test]
+    fn inverts_raw_values() {
+        let mapping = BrightnessMapping::new([(0, 0), (50, 10), (100, 100)]).unwrap();
+        assert_eq!(mapping.to_percentage(0, 0, 100), 0);
+        assert_eq!(mapping.to_percentage(5, 0, 100), 25);
+        assert_eq!(mapping.to_percentage(10, 0, 100), 50);
+        assert_eq!(mapping.to_percentage(55, 0, 100), 75);
+        assert_eq!(mapping.to_percentage(200, 0, 100), 100);
+    }
+
+    #[test]
+    fn inverts_flat_segments_to_their_lowest_percentage() {
+        let mapping = BrightnessMapping::new([(0, 0), (40, 50), (60, 50), (100, 100)]).unwrap();
+        assert_eq!(mapping.to_raw(50, 0, 100), 50);
+        assert_eq!(mapping.to_percentage(50, 0, 100), 40);
+        assert_eq!(mapping.round_trip(50, None), 40);
+        assert_eq!(mapping.to_percentage(75, 0, 100), 80);
+    }
+
+    #[test]
+    fn round_trips_through_the_closest_raw_value() {
+        let mapping = BrightnessMapping::new([(0, 0), (100, 10)]).unwrap();
+        assert_eq!(mapping.round_trip(100, None), 100);
+        assert_eq!(mapping.round_trip(33, None), 30);
+        assert_eq!(mapping.round_trip(36, None), 40);
+        assert_eq!(mapping.round_trip(33, Some((0, 1000))), 30);
+    }
+}
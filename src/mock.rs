@@ -0,0 +1,230 @@
+// Copyright (C) 2022 The brightness project authors. Distributed under the 0BSD license.
+
+//! In-memory devices for testing code that uses this crate without real monitors.
+//!
+//! [`MockDevice`] implements the async and blocking `Brightness` traits, so it can be used wherever
+//! a trait object or a generic device is expected. Clones of a mock device share their state, which
+//! lets tests inspect a device after handing it to the code under test.
+
+use crate::{edid::EdidInfo, DeviceKind, Error};
+use std::sync::{Arc, Mutex};
+
+/// In-memory brightness device.
+#[derive(Clone, Debug)]
+pub struct MockDevice(Arc<Mutex<MockState>>);
+
+#[derive(Debug)]
+struct MockState {
+    name: String,
+    percentage: u32,
+    kind: DeviceKind,
+    is_primary: bool,
+    levels: Vec<u8>,
+    edid: Option<EdidInfo>,
+    capabilities: Option<String>,
+    raw_max: u32,
+    failing: bool,
+}
+
+/// Error returned by failing mock devices.
+#[derive(Debug, thiserror::Error)]
+#[error("Simulated failure of mock device")]
+struct MockError;
+
+impl MockDevice {
+    /// Creates an external DDC/CI device with the given name and a brightness of 50%.
+    pub fn new(name: &str) -> Self {
+        MockDevice(Arc::new(Mutex::new(MockState {
+            name: name.to_owned(),
+            percentage: 50,
+            kind: DeviceKind::ExternalDdc,
+            is_primary: false,
+            levels: Vec::new(),
+            edid: None,
+            capabilities: None,
+            raw_max: 100,
+            failing: false,
+        })))
+    }
+
+    /// Sets the brightness percentage of the device.
+    pub fn with_percentage(self, percentage: u32) -> Self {
+        self.state().percentage = percentage.min(100);
+        self
+    }
+
+    /// Sets the kind of the device.
+    pub fn with_kind(self, kind: DeviceKind) -> Self {
+        self.state().kind = kind;
+        self
+    }
+
+    /// Sets whether the device is the primary display.
+    pub fn with_primary(self, is_primary: bool) -> Self {
+        self.state().is_primary = is_primary;
+        self
+    }
+
+    /// Sets the brightness levels supported by the device.
+    ///
+    /// `set` then applies the level closest to the requested percentage, like quantized internal
+    /// displays do. Any percentage is supported if no level is given.
+    pub fn with_levels(self, levels: Vec<u8>) -> Self {
+        self.state().levels = levels;
+        self
+    }
+
+    /// Sets the EDID information reported by the device.
+    pub fn with_edid(self, edid: EdidInfo) -> Self {
+        self.state().edid = Some(edid);
+        self
+    }
+
+    /// Sets the DDC/CI capabilities string reported by the device.
+    pub fn with_capabilities(self, capabilities: &str) -> Self {
+        self.state().capabilities = Some(capabilities.to_owned());
+        self
+    }
+
+    /// Sets the raw value of the highest brightness of the device.
+    pub fn with_raw_max(self, raw_max: u32) -> Self {
+        self.state().raw_max = raw_max;
+        self
+    }
+
+    /// Sets whether getting and setting brightness fail, to simulate an unresponsive monitor.
+    pub fn set_failing(&self, failing: bool) {
+        self.state().failing = failing;
+    }
+
+    /// Returns the current brightness percentage of the device.
+    pub fn percentage(&self) -> u32 {
+        self.state().percentage
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.0.lock().unwrap()
+    }
+
+    fn get_percentage(&self) -> Result<u32, Error> {
+        let state = self.state();
+        if state.failing {
+            return Err(Error::GettingDeviceInfoFailed {
+                device: state.name.clone(),
+                source: MockError.into(),
+            });
+        }
+        Ok(state.percentage)
+    }
+
+    fn set_percentage(&self, percentage: u32) -> Result<(), Error> {
+        let mut state = self.state();
+        if state.failing {
+            return Err(Error::SettingBrightnessFailed {
+                device: state.name.clone(),
+                source: MockError.into(),
+            });
+        }
+        let percentage = percentage.min(100);
+        state.percentage = state
+            .levels
+            .iter()
+            .map(|&level| u32::from(level))
+            .min_by_key(|&level| level.abs_diff(percentage))
+            .unwrap_or(percentage);
+        Ok(())
+    }
+}
+
+impl crate::blocking::Brightness for MockDevice {
+    fn device_name(&self) -> Result<String, Error> {
+        Ok(self.state().name.clone())
+    }
+
+    fn get(&self) -> Result<u32, Error> {
+        self.get_percentage()
+    }
+
+    fn set(&self, percentage: u32) -> Result<(), Error> {
+        self.set_percentage(percentage)
+    }
+
+    fn edid(&self) -> Result<Option<EdidInfo>, Error> {
+        Ok(self.state().edid.clone())
+    }
+
+    fn is_primary(&self) -> Result<bool, Error> {
+        Ok(self.state().is_primary)
+    }
+
+    fn kind(&self) -> Result<DeviceKind, Error> {
+        Ok(self.state().kind)
+    }
+
+    fn raw_capabilities(&self) -> Result<Option<String>, Error> {
+        Ok(self.state().capabilities.clone())
+    }
+
+    fn brightness_raw_max(&self) -> Result<u32, Error> {
+        Ok(self.state().raw_max)
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait::async_trait]
+impl crate::Brightness for MockDevice {
+    async fn device_name(&self) -> Result<String, Error> {
+        Ok(self.state().name.clone())
+    }
+
+    async fn get(&self) -> Result<u32, Error> {
+        self.get_percentage()
+    }
+
+    async fn set(&mut self, percentage: u32) -> Result<(), Error> {
+        self.set_percentage(percentage)
+    }
+
+    async fn edid(&self) -> Result<Option<EdidInfo>, Error> {
+        Ok(self.state().edid.clone())
+    }
+
+    async fn is_primary(&self) -> Result<bool, Error> {
+        Ok(self.state().is_primary)
+    }
+
+    async fn kind(&self) -> Result<DeviceKind, Error> {
+        Ok(self.state().kind)
+    }
+
+    async fn raw_capabilities(&self) -> Result<Option<String>, Error> {
+        Ok(self.state().capabilities.clone())
+    }
+
+    async fn brightness_raw_max(&self) -> Result<u32, Error> {
+        Ok(self.state().raw_max)
+    }
+}
+
+/// Returns a stream of the given mock devices, mirroring [`crate::brightness_devices`].
+///
+/// Clones of the devices can be kept to inspect them later.
+#[cfg(feature = "async")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "async")))]
+pub fn brightness_devices<I>(devices: I) -> impl futures::Stream<Item = Result<MockDevice, Error>>
+where
+    I: IntoIterator<Item = MockDevice>,
+{
+    futures::stream::iter(devices.into_iter().map(Ok))
+}
+
+/// Returns an iterator over the given mock devices, mirroring
+/// [`crate::blocking::brightness_devices`].
+///
+/// Clones of the devices can be kept to inspect them later.
+pub fn brightness_devices_blocking<I>(devices: I) -> impl Iterator<Item = Result<MockDevice, Error>>
+where
+    I: IntoIterator<Item = MockDevice>,
+{
+    devices.into_iter().map(Ok)
+}
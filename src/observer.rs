@@ -0,0 +1,100 @@
+// Copyright (C) 2022 The brightness project authors. Distributed under the 0BSD license.
+
+//! Sharing of brightness changes between several consumers.
+
+use crate::{Brightness, Error};
+use async_io::Timer;
+use futures::{channel::mpsc, Stream};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Polls the brightness of a device once and fans changes out to any number of subscribers.
+///
+/// Several components reacting to the brightness of the same device can each subscribe, instead of
+/// each polling the device, which would multiply DDC/CI traffic. Polling happens while
+/// [`BrightnessObserver::run`] is awaited, which the application drives with its executor. Clones
+/// share the same subscribers.
+///
+/// ```rust
+/// # #[cfg(feature = "async")]
+/// # mod doctest {
+/// use brightness::{Brightness, BrightnessObserver};
+/// use futures::{future::join, StreamExt};
+/// use std::time::Duration;
+///
+/// async fn observe(device: &(dyn Brightness + Send + Sync)) {
+///     let observer = BrightnessObserver::new();
+///     let mut changes = observer.subscribe();
+///     let print = async {
+///         while let Some(percentage) = changes.next().await {
+///             println!("Brightness is {percentage}%");
+///         }
+///     };
+///     let _ = join(observer.run(device, Duration::from_secs(1)), print).await;
+/// }
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct BrightnessObserver(Arc<Mutex<Shared>>);
+
+#[derive(Debug, Default)]
+struct Shared {
+    current: Option<u32>,
+    subscribers: Vec<mpsc::UnboundedSender<u32>>,
+}
+
+impl BrightnessObserver {
+    /// Creates an observer without subscribers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a stream of the brightness percentages of the device, starting with the current one
+    /// if it was already read.
+    ///
+    /// The stream ends when all clones of the observer are dropped.
+    pub fn subscribe(&self) -> impl Stream<Item = u32> {
+        let (sender, receiver) = mpsc::unbounded();
+        let mut shared = self.0.lock().unwrap();
+        if let Some(current) = shared.current {
+            let _ = sender.unbounded_send(current);
+        }
+        shared.subscribers.push(sender);
+        receiver
+    }
+
+    /// Returns the brightness percentage last read, if any.
+    pub fn current(&self) -> Option<u32> {
+        self.0.lock().unwrap().current
+    }
+
+    /// Reads the brightness of the device every `interval` and sends changes to the subscribers.
+    ///
+    /// This only returns if reading the brightness fails. Subscribers are kept, so polling can be
+    /// resumed by calling this again.
+    pub async fn run(
+        &self,
+        device: &(dyn Brightness + Send + Sync),
+        interval: Duration,
+    ) -> Result<(), Error> {
+        loop {
+            let percentage = device.get().await?;
+            self.publish(percentage);
+            Timer::after(interval).await;
+        }
+    }
+
+    fn publish(&self, percentage: u32) {
+        let mut shared = self.0.lock().unwrap();
+        if shared.current == Some(percentage) {
+            return;
+        }
+        shared.current = Some(percentage);
+        // Subscribers whose stream was dropped are forgotten.
+        shared
+            .subscribers
+            .retain(|sender| sender.unbounded_send(percentage).is_ok());
+    }
+}
@@ -0,0 +1,272 @@
+// Copyright (C) 2022 The brightness project authors. Distributed under the 0BSD license.
+
+//! Configuration of devices in one place.
+
+use crate::{BrightnessMapping, Error};
+
+#[cfg(target_os = "linux")]
+use crate::blocking::linux::LOGIND_TIMEOUT;
+#[cfg(windows)]
+use crate::blocking::windows::Rounding;
+#[cfg(target_os = "linux")]
+use std::time::Duration;
+
+/// Options applied to brightness devices.
+///
+/// This gathers the behaviors otherwise configured through the platform `BrightnessExt` traits.
+/// Options left unset keep their default, so `DeviceOptions::new()` leaves devices unchanged.
+///
+/// ```rust
+/// # #[cfg(all(feature = "async", target_os = "linux"))]
+/// # mod doctest {
+/// use brightness::DeviceOptions;
+/// use futures::{future::ready, TryStreamExt};
+///
+/// async fn open_all() -> Result<Vec<brightness::BrightnessDevice>, brightness::Error> {
+///     let options = DeviceOptions::new().use_pkexec(true);
+///     brightness::brightness_devices()
+///         .and_then(|dev| ready(options.open(dev)))
+///         .try_collect()
+///         .await
+/// }
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DeviceOptions {
+    mapping: Option<BrightnessMapping>,
+    #[cfg(target_os = "linux")]
+    trust_last_write: bool,
+    #[cfg(target_os = "linux")]
+    use_pkexec: bool,
+    #[cfg(target_os = "linux")]
+    use_gnome_settings_daemon: bool,
+    #[cfg(target_os = "linux")]
+    read_only: bool,
+    #[cfg(target_os = "linux")]
+    logind_timeout: Option<Duration>,
+    #[cfg(target_os = "linux")]
+    system_connection: Option<SystemConnection>,
+    #[cfg(windows)]
+    nonzero_floor: bool,
+    #[cfg(windows)]
+    rounding: Rounding,
+    #[cfg(windows)]
+    min_percentage: u32,
+    #[cfg(windows)]
+    reopen_on_stale: bool,
+    #[cfg(windows)]
+    skip_unchanged: Option<u32>,
+    #[cfg(any(target_os = "linux", windows))]
+    verify_writes: bool,
+}
+
+impl DeviceOptions {
+    /// Creates options with all defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets a custom mapping between percentages and raw values.
+    ///
+    /// See [`BrightnessMapping`].
+    pub fn mapping(mut self, mapping: BrightnessMapping) -> Self {
+        self.mapping = Some(mapping);
+        self
+    }
+
+    /// See [`crate::blocking::linux::BrightnessExt::set_trust_last_write`].
+    #[cfg(target_os = "linux")]
+    #[cfg_attr(doc_cfg, doc(cfg(target_os = "linux")))]
+    pub fn trust_last_write(mut self, trust: bool) -> Self {
+        self.trust_last_write = trust;
+        self
+    }
+
+    /// See [`crate::blocking::linux::BrightnessExt::set_use_pkexec`].
+    #[cfg(target_os = "linux")]
+    #[cfg_attr(doc_cfg, doc(cfg(target_os = "linux")))]
+    pub fn use_pkexec(mut self, enabled: bool) -> Self {
+        self.use_pkexec = enabled;
+        self
+    }
+
+    /// See [`crate::blocking::linux::BrightnessExt::set_use_gnome_settings_daemon`].
+    #[cfg(target_os = "linux")]
+    #[cfg_attr(doc_cfg, doc(cfg(target_os = "linux")))]
+    pub fn use_gnome_settings_daemon(mut self, enabled: bool) -> Self {
+        self.use_gnome_settings_daemon = enabled;
+        self
+    }
+
+    /// See [`crate::blocking::linux::BrightnessExt::set_read_only`].
+    #[cfg(target_os = "linux")]
+    #[cfg_attr(doc_cfg, doc(cfg(target_os = "linux")))]
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// See [`crate::blocking::linux::BrightnessExt::set_logind_timeout`].
+    #[cfg(target_os = "linux")]
+    #[cfg_attr(doc_cfg, doc(cfg(target_os = "linux")))]
+    pub fn logind_timeout(mut self, timeout: Duration) -> Self {
+        self.logind_timeout = Some(timeout);
+        self
+    }
+
+    /// See [`crate::blocking::linux::BrightnessExt::set_system_connection`].
+    ///
+    /// Unlike other options, the connection of a device is kept if this is not set.
+    #[cfg(target_os = "linux")]
+    #[cfg_attr(doc_cfg, doc(cfg(target_os = "linux")))]
+    pub fn system_connection(mut self, connection: zbus::Connection) -> Self {
+        self.system_connection = Some(SystemConnection(connection));
+        self
+    }
+
+    /// See [`crate::blocking::windows::BrightnessExt::set_nonzero_floor`].
+    #[cfg(windows)]
+    #[cfg_attr(doc_cfg, doc(cfg(windows)))]
+    pub fn nonzero_floor(mut self, enabled: bool) -> Self {
+        self.nonzero_floor = enabled;
+        self
+    }
+
+    /// See [`crate::blocking::windows::BrightnessExt::set_rounding`].
+    #[cfg(windows)]
+    #[cfg_attr(doc_cfg, doc(cfg(windows)))]
+    pub fn rounding(mut self, rounding: Rounding) -> Self {
+        self.rounding = rounding;
+        self
+    }
+
+    /// See [`crate::blocking::windows::BrightnessExt::set_min_percentage`].
+    #[cfg(windows)]
+    #[cfg_attr(doc_cfg, doc(cfg(windows)))]
+    pub fn min_percentage(mut self, percentage: u32) -> Self {
+        self.min_percentage = percentage;
+        self
+    }
+
+    /// See [`crate::blocking::windows::BrightnessExt::set_reopen_on_stale`].
+    #[cfg(windows)]
+    #[cfg_attr(doc_cfg, doc(cfg(windows)))]
+    pub fn reopen_on_stale(mut self, enabled: bool) -> Self {
+        self.reopen_on_stale = enabled;
+        self
+    }
+
+    /// See [`crate::blocking::windows::BrightnessExt::set_skip_unchanged`].
+    #[cfg(windows)]
+    #[cfg_attr(doc_cfg, doc(cfg(windows)))]
+    pub fn skip_unchanged(mut self, tolerance: u32) -> Self {
+        self.skip_unchanged = Some(tolerance);
+        self
+    }
+
+    /// See [`crate::blocking::windows::BrightnessExt::set_verify_writes`] and
+    /// [`crate::blocking::linux::BrightnessExt::set_verify_writes`].
+    #[cfg(any(target_os = "linux", windows))]
+    #[cfg_attr(doc_cfg, doc(cfg(any(target_os = "linux", windows))))]
+    pub fn verify_writes(mut self, enabled: bool) -> Self {
+        self.verify_writes = enabled;
+        self
+    }
+
+    /// Applies the options to an async device and returns it.
+    #[cfg(feature = "async")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "async")))]
+    pub fn open(
+        &self,
+        mut device: crate::BrightnessDevice,
+    ) -> Result<crate::BrightnessDevice, Error> {
+        self.apply(&mut device)?;
+        Ok(device)
+    }
+
+    /// Applies the options to a blocking device and returns it.
+    pub fn open_blocking(
+        &self,
+        mut device: crate::blocking::BrightnessDevice,
+    ) -> Result<crate::blocking::BrightnessDevice, Error> {
+        self.apply_blocking(&mut device)?;
+        Ok(device)
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) fn apply(&self, device: &mut crate::BrightnessDevice) -> Result<(), Error> {
+        #[cfg(target_os = "linux")]
+        {
+            use crate::linux::BrightnessExt;
+            device.set_trust_last_write(self.trust_last_write)?;
+            device.set_mapping(self.mapping.clone());
+            device.set_use_pkexec(self.use_pkexec);
+            device.set_use_gnome_settings_daemon(self.use_gnome_settings_daemon);
+            device.set_read_only(self.read_only);
+            device.set_verify_writes(self.verify_writes);
+            device.set_logind_timeout(self.logind_timeout.unwrap_or(LOGIND_TIMEOUT));
+            if let Some(SystemConnection(connection)) = &self.system_connection {
+                device.set_system_connection(connection.clone());
+            }
+        }
+        #[cfg(windows)]
+        {
+            use crate::windows::BrightnessExt;
+            device.set_nonzero_floor(self.nonzero_floor);
+            device.set_rounding(self.rounding);
+            device.set_min_percentage(self.min_percentage);
+            device.set_reopen_on_stale(self.reopen_on_stale);
+            device.set_mapping(self.mapping.clone());
+            device.set_skip_unchanged(self.skip_unchanged);
+            device.set_verify_writes(self.verify_writes);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn apply_blocking(
+        &self,
+        device: &mut crate::blocking::BrightnessDevice,
+    ) -> Result<(), Error> {
+        #[cfg(target_os = "linux")]
+        {
+            use crate::blocking::linux::BrightnessExt;
+            device.set_trust_last_write(self.trust_last_write)?;
+            device.set_mapping(self.mapping.clone());
+            device.set_use_pkexec(self.use_pkexec);
+            device.set_use_gnome_settings_daemon(self.use_gnome_settings_daemon);
+            device.set_read_only(self.read_only);
+            device.set_verify_writes(self.verify_writes);
+            device.set_logind_timeout(self.logind_timeout.unwrap_or(LOGIND_TIMEOUT));
+            if let Some(SystemConnection(connection)) = &self.system_connection {
+                device.set_system_connection(connection.clone());
+            }
+        }
+        #[cfg(windows)]
+        {
+            use crate::blocking::windows::BrightnessExt;
+            device.set_nonzero_floor(self.nonzero_floor);
+            device.set_rounding(self.rounding);
+            device.set_min_percentage(self.min_percentage);
+            device.set_reopen_on_stale(self.reopen_on_stale);
+            device.set_mapping(self.mapping.clone());
+            device.set_skip_unchanged(self.skip_unchanged);
+            device.set_verify_writes(self.verify_writes);
+        }
+        Ok(())
+    }
+}
+
+/// System bus connection of [`DeviceOptions`], compared by its unique bus name.
+#[cfg(target_os = "linux")]
+#[derive(Clone, Debug)]
+struct SystemConnection(zbus::Connection);
+
+#[cfg(target_os = "linux")]
+impl PartialEq for SystemConnection {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.unique_name() == other.0.unique_name()
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Eq for SystemConnection {}
@@ -0,0 +1,86 @@
+// Copyright (C) 2022 The brightness project authors. Distributed under the 0BSD license.
+
+//! Brightness depending on the power source.
+
+use crate::{Brightness, Error};
+
+/// Brightness targets on AC power and on battery (DC) power.
+///
+/// Windows stores separate AC and DC brightness for internal displays, but other platforms only
+/// have a single value. This keeps both targets and applies the one matching the power source,
+/// which is detected by the application.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct PowerAwareBrightness {
+    ac: u32,
+    dc: u32,
+}
+
+impl PowerAwareBrightness {
+    /// Creates targets with the given brightness percentages on AC and DC power.
+    pub fn new(ac: u32, dc: u32) -> Self {
+        PowerAwareBrightness {
+            ac: ac.min(100),
+            dc: dc.min(100),
+        }
+    }
+
+    /// Returns the brightness percentage on AC power.
+    pub fn ac(&self) -> u32 {
+        self.ac
+    }
+
+    /// Returns the brightness percentage on battery power.
+    pub fn dc(&self) -> u32 {
+        self.dc
+    }
+
+    /// Returns the brightness percentage for the given power source.
+    pub fn target(&self, on_ac: bool) -> u32 {
+        if on_ac {
+            self.ac
+        } else {
+            self.dc
+        }
+    }
+
+    /// Sets the brightness percentage on AC power, without changing any device.
+    pub fn set_ac(&mut self, percentage: u32) {
+        self.ac = percentage.min(100);
+    }
+
+    /// Sets the brightness percentage on battery power, without changing any device.
+    pub fn set_dc(&mut self, percentage: u32) {
+        self.dc = percentage.min(100);
+    }
+
+    /// Sets the brightness of the device to the target for the given power source.
+    ///
+    /// This is meant to be called when the power source changes.
+    pub async fn apply(
+        &self,
+        device: &mut (dyn Brightness + Send + Sync),
+        on_ac: bool,
+    ) -> Result<(), Error> {
+        device.set(self.target(on_ac)).await
+    }
+
+    /// Sets the brightness of the device and remembers it as the target for the given power
+    /// source.
+    ///
+    /// This is meant to be called when the user adjusts the brightness, so that it is restored
+    /// the next time the device runs on the same power source.
+    pub async fn set(
+        &mut self,
+        device: &mut (dyn Brightness + Send + Sync),
+        on_ac: bool,
+        percentage: u32,
+    ) -> Result<(), Error> {
+        device.set(percentage).await?;
+        if on_ac {
+            self.set_ac(percentage);
+        } else {
+            self.set_dc(percentage);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+// Copyright (C) 2022 The brightness project authors. Distributed under the 0BSD license.
+
+//! Limiting the combined brightness of several devices.
+
+use crate::{Brightness, BrightnessDevice, Error};
+
+/// Devices whose combined brightness is kept within a budget, e.g. to limit power draw.
+///
+/// Each device has a weight, which is its cost at 100% brightness (e.g. its power draw in watts).
+/// The cost of a device is its weight multiplied by its brightness as a fraction, and the costs of
+/// all devices must not exceed the budget.
+///
+/// The brightness requested for each device is remembered as its target. When a device is set, it
+/// gets its target (as long as it fits the budget on its own), and the targets of the other devices
+/// are scaled down proportionally to fit the rest of the budget. Devices return to their targets
+/// when the budget allows it again.
+#[derive(Debug)]
+pub struct PowerBudget<D = BrightnessDevice> {
+    devices: Vec<Member<D>>,
+    budget: f64,
+}
+
+#[derive(Debug)]
+struct Member<D> {
+    device: D,
+    weight: f64,
+    target: Option<u32>,
+    applied: Option<u32>,
+}
+
+impl<D> PowerBudget<D>
+where
+    D: Brightness + Send + Sync,
+{
+    /// Creates a budget from `(device, weight)` pairs.
+    ///
+    /// The targets of devices are initialized with their current brightness the first time one of
+    /// them is set. Negative or NaN weights are treated as 0.
+    pub fn new(devices: Vec<(D, f64)>, budget: f64) -> Self {
+        PowerBudget {
+            devices: devices
+                .into_iter()
+                .map(|(device, weight)| Member {
+                    device,
+                    weight: weight.max(0.0),
+                    target: None,
+                    applied: None,
+                })
+                .collect(),
+            budget: budget.max(0.0),
+        }
+    }
+
+    /// Returns the devices of the budget, in the order they were given.
+    pub fn devices(&self) -> impl Iterator<Item = &D> {
+        self.devices.iter().map(|member| &member.device)
+    }
+
+    /// Returns the budget.
+    pub fn budget(&self) -> f64 {
+        self.budget
+    }
+
+    /// Sets the brightness of the device at `index` as a percentage, adjusts the other devices to
+    /// stay within the budget, and returns the resulting brightness of all devices.
+    ///
+    /// Only devices whose brightness changes are written. If the device at `index` cannot reach
+    /// `percentage` within the budget on its own, it gets the highest brightness that fits and the
+    /// other devices are turned down to 0%.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub async fn set(&mut self, index: usize, percentage: u32) -> Result<Vec<u32>, Error> {
+        self.devices[index].target = Some(percentage.min(100));
+        for member in &mut self.devices {
+            if member.target.is_none() {
+                let current = member.device.get().await?;
+                member.target = Some(current);
+                member.applied = Some(current);
+            }
+        }
+        let values = self.allocate(index);
+        for (member, &value) in self.devices.iter_mut().zip(&values) {
+            if member.applied != Some(value) {
+                member.device.set(value).await?;
+                member.applied = Some(value);
+            }
+        }
+        Ok(values)
+    }
+
+    /// Returns the brightness of each device, giving priority to the device at `index`.
+    fn allocate(&self, index: usize) -> Vec<u32> {
+        let target = |member: &Member<D>| f64::from(member.target.unwrap_or(0));
+        let cost = |member: &Member<D>, percentage: f64| member.weight * percentage / 100.0;
+        let priority = &self.devices[index];
+        let mut percentage = target(priority);
+        if cost(priority, percentage) > self.budget {
+            percentage = (self.budget * 100.0 / priority.weight).floor();
+        }
+        let remaining = self.budget - cost(priority, percentage);
+        let demand = self
+            .devices
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i != index)
+            .map(|(_, member)| cost(member, target(member)))
+            .sum::<f64>();
+        let factor = if demand > remaining {
+            remaining / demand
+        } else {
+            1.0
+        };
+        self.devices
+            .iter()
+            .enumerate()
+            .map(|(i, member)| {
+                if i == index {
+                    percentage as u32
+                } else {
+                    (target(member) * factor).floor() as u32
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockDevice;
+    use futures::executor::block_on;
+
+    fn new_budget(weights: &[f64], budget: f64) -> (PowerBudget<MockDevice>, Vec<MockDevice>) {
+        let devices = (0..weights.len())
+            .map(|i| MockDevice::new(&format!("mock{}", i)))
+            .collect::<Vec<_>>();
+        let members = devices
+            .iter()
+            .cloned()
+            .zip(weights.iter().copied())
+            .collect();
+        (PowerBudget::new(members, budget), devices)
+    }
+
+    #[test]
+    fn keeps_targets_within_budget() {
+        let (mut budget, devices) = new_budget(&[10.0, 10.0], 20.0);
+        assert_eq!(block_on(budget.set(0, 80)).unwrap(), [80, 50]);
+        assert_eq!(devices[0].percentage(), 80);
+        assert_eq!(devices[1].percentage(), 50);
+    }
+
+    #[test]
+    fn scales_other_devices_proportionally() {
+        let (mut budget, devices) = new_budget(&[10.0, 10.0, 20.0], 14.0);
+        // 8 is left for a demand of 5 + 10.
+        assert_eq!(block_on(budget.set(0, 60)).unwrap(), [60, 26, 26]);
+        assert_eq!(devices[1].percentage(), 26);
+        assert_eq!(devices[2].percentage(), 26);
+    }
+
+    #[test]
+    fn caps_priority_device_alone_over_budget() {
+        let (mut budget, devices) = new_budget(&[10.0, 10.0], 5.0);
+        assert_eq!(block_on(budget.set(0, 80)).unwrap(), [50, 0]);
+        assert_eq!(devices[0].percentage(), 50);
+        assert_eq!(devices[1].percentage(), 0);
+    }
+
+    #[test]
+    fn ignores_devices_without_weight() {
+        let (mut budget, _) = new_budget(&[0.0, 0.0, -1.0, f64::NAN], 0.0);
+        assert_eq!(block_on(budget.set(0, 100)).unwrap(), [100, 50, 50, 50]);
+        let (mut budget, _) = new_budget(&[0.0, 10.0], 0.0);
+        assert_eq!(block_on(budget.set(0, 100)).unwrap(), [100, 0]);
+    }
+
+    #[test]
+    fn returns_to_targets_when_budget_allows() {
+        let (mut budget, devices) = new_budget(&[10.0, 10.0], 10.0);
+        assert_eq!(block_on(budget.set(0, 80)).unwrap(), [80, 20]);
+        assert_eq!(block_on(budget.set(0, 20)).unwrap(), [20, 50]);
+        assert_eq!(devices[1].percentage(), 50);
+    }
+
+    #[test]
+    fn writes_only_changed_devices() {
+        let (mut budget, devices) = new_budget(&[10.0, 10.0], 20.0);
+        block_on(budget.set(0, 80)).unwrap();
+        devices[1].set_failing(true);
+        assert_eq!(block_on(budget.set(0, 90)).unwrap(), [90, 50]);
+        assert!(block_on(budget.set(1, 60)).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright (C) 2022 The brightness project authors. Distributed under the 0BSD license.
+
+//! Brightness following a daily schedule.
+
+use crate::{Brightness, Error};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Number of seconds in a day.
+const DAY: u64 = 24 * 60 * 60;
+
+/// Daily brightness schedule, defined by keyframes between which brightness is interpolated.
+///
+/// Keyframes give the brightness percentage at a time of day, e.g. computed from sunrise and sunset
+/// times. The brightness is linearly interpolated between consecutive keyframes, and between the
+/// last keyframe of a day and the first keyframe of the next day across midnight. Callers apply the
+/// schedule periodically, e.g. every minute with [`ScheduledRamp::apply_now`].
+///
+/// ```
+/// use brightness::ScheduledRamp;
+/// use std::time::Duration;
+///
+/// let hours = |h: u64| Duration::from_secs(h * 60 * 60);
+/// let ramp = ScheduledRamp::new([(hours(8), 100), (hours(20), 40)]);
+/// assert_eq!(ramp.target(hours(14)), Some(70));
+/// // Across midnight, from 40% at 20:00 to 100% at 08:00.
+/// assert_eq!(ramp.target(hours(2)), Some(70));
+/// ```
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct ScheduledRamp {
+    keyframes: Vec<(u64, u32)>,
+    utc_offset: i64,
+}
+
+impl ScheduledRamp {
+    /// Creates a schedule from `(time of day, percentage)` keyframes.
+    ///
+    /// The time of day is the time elapsed since midnight, and times of a day or more wrap around.
+    /// Keyframes do not need to be sorted. Percentages above 100 are clamped, and only the last of
+    /// several keyframes at the same second is kept.
+    pub fn new<I>(keyframes: I) -> Self
+    where
+        I: IntoIterator<Item = (Duration, u32)>,
+    {
+        let mut keyframes = keyframes
+            .into_iter()
+            .map(|(time, percentage)| (time.as_secs() % DAY, percentage.min(100)))
+            .collect::<Vec<_>>();
+        keyframes.reverse();
+        keyframes.sort_by_key(|&(time, _)| time);
+        keyframes.dedup_by_key(|&mut (time, _)| time);
+        ScheduledRamp {
+            keyframes,
+            utc_offset: 0,
+        }
+    }
+
+    /// Sets the offset of the local time zone from UTC in seconds, used by
+    /// [`ScheduledRamp::apply_now`].
+    ///
+    /// The standard library does not provide the local time zone, so it is left to the caller. The
+    /// offset is 0 by default, which means that keyframes are in UTC.
+    pub fn with_utc_offset(mut self, seconds: i32) -> Self {
+        self.utc_offset = seconds.into();
+        self
+    }
+
+    /// Returns the brightness percentage at a time of day.
+    ///
+    /// `None` is returned if the schedule has no keyframes.
+    pub fn target(&self, time_of_day: Duration) -> Option<u32> {
+        let time = time_of_day.as_secs() % DAY;
+        let (&first, &last) = (self.keyframes.first()?, self.keyframes.last()?);
+        let upper = self.keyframes.partition_point(|&(t, _)| t < time);
+        // Before the first keyframe and after the last one, the brightness is interpolated between
+        // the last keyframe and the first keyframe of the next day.
+        let ((t0, p0), (t1, p1), time) = match upper {
+            0 => (last, (first.0 + DAY, first.1), time + DAY),
+            _ if upper == self.keyframes.len() => (last, (first.0 + DAY, first.1), time),
+            _ => (self.keyframes[upper - 1], self.keyframes[upper], time),
+        };
+        let fraction = (time - t0) as f64 / (t1 - t0) as f64;
+        let percentage = f64::from(p0) + fraction * (f64::from(p1) - f64::from(p0));
+        Some(percentage.round() as u32)
+    }
+
+    /// Sets the brightness of the device to the target at a time of day.
+    ///
+    /// Nothing is done if the schedule has no keyframes.
+    pub async fn apply_at(
+        &self,
+        device: &mut (dyn Brightness + Send + Sync),
+        time_of_day: Duration,
+    ) -> Result<(), Error> {
+        match self.target(time_of_day) {
+            Some(target) => device.set(target).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Sets the brightness of the device to the target at the current time.
+    ///
+    /// The time of day is computed from the system clock and the offset set with
+    /// [`ScheduledRamp::with_utc_offset`]. Nothing is done if the schedule has no keyframes.
+    pub async fn apply_now(
+        &self,
+        device: &mut (dyn Brightness + Send + Sync),
+    ) -> Result<(), Error> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        let time_of_day = (now as i64 + self.utc_offset).rem_euclid(DAY as i64) as u64;
+        self.apply_at(device, Duration::from_secs(time_of_day))
+            .await
+    }
+}
@@ -0,0 +1,89 @@
+// Copyright (C) 2022 The brightness project authors. Distributed under the 0BSD license.
+
+//! Change detection by comparing snapshots of brightness.
+
+use crate::devices_with_brightness;
+use futures::{future::ready, StreamExt};
+use std::collections::BTreeMap;
+
+/// Brightness of all devices of the running system at some point, as percentages.
+///
+/// Devices are identified by name, as returned by `device_name`. With the `serde` feature, a
+/// snapshot can be serialized, e.g. to be kept across restarts of a daemon.
+///
+/// This allows polling for changes where watching brightness is not available:
+///
+/// ```rust
+/// # #[cfg(feature = "async")]
+/// # mod doctest {
+/// use brightness::{diff_since, BrightnessSnapshotSet};
+/// use std::time::Duration;
+///
+/// async fn poll() {
+///     let mut snapshot = BrightnessSnapshotSet::capture().await;
+///     loop {
+///         async_io::Timer::after(Duration::from_secs(5)).await;
+///         for (device, percentage) in diff_since(&snapshot).await {
+///             println!("{device} changed to {percentage}%");
+///         }
+///         snapshot = BrightnessSnapshotSet::capture().await;
+///     }
+/// }
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct BrightnessSnapshotSet {
+    brightness: BTreeMap<String, u32>,
+}
+
+impl BrightnessSnapshotSet {
+    /// Reads the brightness of all devices of the running system concurrently.
+    ///
+    /// Devices that fail to be listed or read are left out.
+    pub async fn capture() -> Self {
+        let brightness = read_all().await.into_iter().collect();
+        BrightnessSnapshotSet { brightness }
+    }
+
+    /// Returns the brightness of a device in the snapshot, as a percentage.
+    pub fn get(&self, device_name: &str) -> Option<u32> {
+        self.brightness.get(device_name).copied()
+    }
+
+    /// Returns the names and brightness of the devices in the snapshot, sorted by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, u32)> {
+        self.brightness
+            .iter()
+            .map(|(name, &percentage)| (name.as_str(), percentage))
+    }
+}
+
+/// Returns the name and current brightness of the devices whose brightness differs from a
+/// snapshot, as a percentage.
+///
+/// The brightness of all devices is read concurrently. Devices missing from the snapshot are
+/// returned as changed, and devices that fail to be listed or read are left out. Results are sorted
+/// by name.
+pub async fn diff_since(previous: &BrightnessSnapshotSet) -> Vec<(String, u32)> {
+    let mut changed = read_all()
+        .await
+        .into_iter()
+        .filter(|(name, percentage)| previous.get(name) != Some(*percentage))
+        .collect::<Vec<_>>();
+    changed.sort();
+    changed
+}
+
+async fn read_all() -> Vec<(String, u32)> {
+    devices_with_brightness()
+        .filter_map(|device| {
+            ready(
+                device
+                    .ok()
+                    .map(|(info, percentage)| (info.name, percentage)),
+            )
+        })
+        .collect()
+        .await
+}
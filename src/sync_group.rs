@@ -0,0 +1,81 @@
+// Copyright (C) 2022 The brightness project authors. Distributed under the 0BSD license.
+
+//! Groups of devices kept at the same brightness.
+
+use crate::{brightness_devices, Brightness, BrightnessDevice, Error};
+use futures::{
+    future::{join_all, try_join_all},
+    TryStreamExt,
+};
+
+/// A group of devices that are always set to the same brightness.
+#[derive(Debug)]
+pub struct SyncGroup<D = BrightnessDevice> {
+    devices: Vec<D>,
+    max_divergence: u32,
+}
+
+impl SyncGroup<BrightnessDevice> {
+    /// Creates a group from the devices of the running system whose names are in `names`.
+    ///
+    /// See [`SyncGroup::new`] for the meaning of `max_divergence`.
+    pub async fn from_names<S>(names: &[S], max_divergence: u32) -> Result<Self, Error>
+    where
+        S: AsRef<str> + Sync,
+    {
+        let devices = brightness_devices()
+            .try_filter_map(|dev| async move {
+                let name = dev.device_name().await?;
+                Ok(names.iter().any(|n| n.as_ref() == name).then_some(dev))
+            })
+            .try_collect()
+            .await?;
+        Ok(SyncGroup::new(devices, max_divergence))
+    }
+}
+
+impl<D> SyncGroup<D>
+where
+    D: Brightness + Send + Sync,
+{
+    /// Creates a group from the given devices.
+    ///
+    /// `max_divergence` is the largest difference in percentage points between the brightness of
+    /// members that [`SyncGroup::get`] tolerates.
+    pub fn new(devices: Vec<D>, max_divergence: u32) -> Self {
+        SyncGroup {
+            devices,
+            max_divergence,
+        }
+    }
+
+    /// Returns the members of the group.
+    pub fn devices(&self) -> &[D] {
+        &self.devices
+    }
+
+    /// Returns the average brightness of the members as a percentage.
+    ///
+    /// An error is returned if the group is empty or if the brightness of members differs by more
+    /// than the maximum divergence of the group.
+    pub async fn get(&self) -> Result<u32, Error> {
+        let values = try_join_all(self.devices.iter().map(|dev| dev.get())).await?;
+        let (min, max) = match (values.iter().min(), values.iter().max()) {
+            (Some(&min), Some(&max)) => (min, max),
+            _ => return Err(Error::NoDevices),
+        };
+        if max - min > self.max_divergence {
+            return Err(Error::DevicesDiverged { min, max });
+        }
+        let sum = values.iter().map(|&v| u64::from(v)).sum::<u64>();
+        Ok((sum / values.len() as u64) as u32)
+    }
+
+    /// Sets the brightness of all members concurrently, as a percentage.
+    ///
+    /// The result of each member is returned in order. A member failing does not prevent the other
+    /// members from being updated.
+    pub async fn set(&mut self, percentage: u32) -> Vec<Result<(), Error>> {
+        join_all(self.devices.iter_mut().map(|dev| dev.set(percentage))).await
+    }
+}
@@ -2,25 +2,234 @@
 
 //! Platform-specific implementation for Windows.
 
-pub use crate::blocking::windows::BrightnessExt;
-
 use crate::{
     blocking::{
-        windows::{BlockingDeviceImpl, SysError},
+        windows::{self, devices_from_hmonitors, BlockingDeviceImpl, Selection, SysError},
         Brightness,
     },
-    BrightnessDevice, Error,
+    edid::{DisplayMode, EdidInfo},
+    Brightness as _, BrightnessDevice, BrightnessMapping, DeviceInfo, DeviceKind, Error, SyncGroup,
 };
+use ::windows::Win32::Graphics::Gdi::HMONITOR;
 use async_trait::async_trait;
 use blocking::unblock;
-use futures::{stream, FutureExt, Stream, StreamExt};
-use std::sync::Arc;
+use futures::{stream, FutureExt, Stream, StreamExt, TryStreamExt};
+use std::{
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
+
+pub use crate::blocking::windows::{
+    ColorTemperature, DisplayBrightness, DisplayPreset, OutputTechnology, Rounding, ScreenRect,
+};
+
+/// Windows-specific async brightness functionality.
+#[async_trait]
+pub trait BrightnessExt {
+    /// Returns device description
+    fn device_description(&self) -> Result<String, Error>;
+
+    /// Returns the device registry key
+    fn device_registry_key(&self) -> Result<String, Error>;
+
+    /// Returns the device path
+    fn device_path(&self) -> Result<String, Error>;
+
+    /// Returns the device instance ID of the monitor.
+    ///
+    /// See [`crate::blocking::windows::BrightnessExt::instance_id`].
+    fn instance_id(&self) -> Result<String, Error>;
+
+    /// Returns the friendly name of the monitor (e.g. "DELL U2720Q"), which is usually empty for
+    /// internal displays.
+    ///
+    /// This is meant to be shown to users. Unlike `device_name`, it is not unique and may change
+    /// with driver updates, so it should not be used to identify a device.
+    fn friendly_name(&self) -> Result<String, Error>;
+
+    /// Returns the GDI name of the display the monitor is attached to (e.g. `\\.\DISPLAY1`).
+    ///
+    /// This is the name used by GDI functions such as `ChangeDisplaySettingsExW` and found in
+    /// `MONITORINFOEXW`. `device_name` returns the name of the monitor device itself, which has
+    /// this name as a prefix (e.g. `\\.\DISPLAY1\Monitor0`).
+    fn gdi_device_name(&self) -> Result<String, Error>;
+
+    /// Returns the number of the display the monitor is attached to.
+    ///
+    /// See [`crate::blocking::windows::BrightnessExt::display_number`].
+    fn display_number(&self) -> Result<u32, Error>;
+
+    /// Returns the technology of the connection to the monitor
+    fn output_technology(&self) -> Result<OutputTechnology, Error>;
+
+    /// Returns the locally unique identifier (LUID) of the display adapter (GPU) driving the
+    /// monitor, as a 64-bit value.
+    ///
+    /// See [`crate::blocking::windows::BrightnessExt::adapter_luid`].
+    fn adapter_luid(&self) -> Result<u64, Error>;
+
+    /// Returns the raw `HMONITOR` handle value of the display monitor the device belongs to.
+    ///
+    /// See [`crate::blocking::windows::BrightnessExt::hmonitor`].
+    fn hmonitor(&self) -> Result<isize, Error>;
+
+    /// Returns whether the monitor responds to DDC/CI requests.
+    ///
+    /// See [`crate::blocking::windows::BrightnessExt::ddcci_enabled`].
+    async fn ddcci_enabled(&self) -> Result<bool, Error>;
+
+    /// Returns whether the device looks like a ghost monitor.
+    ///
+    /// See [`crate::blocking::windows::BrightnessExt::is_ghost`].
+    async fn is_ghost(&self) -> Result<bool, Error>;
+
+    /// Sets whether `set` avoids turning off the backlight of internal displays.
+    ///
+    /// See [`crate::blocking::windows::BrightnessExt::set_nonzero_floor`].
+    fn set_nonzero_floor(&mut self, enabled: bool);
+
+    /// Sets the lowest percentage that `set` applies, raising lower requests to it.
+    ///
+    /// See [`crate::blocking::windows::BrightnessExt::set_min_percentage`].
+    fn set_min_percentage(&mut self, percentage: u32);
+
+    /// Returns the raw value of the lowest brightness, in the native units of the device.
+    ///
+    /// See [`crate::blocking::windows::BrightnessExt::brightness_raw_min`].
+    async fn brightness_raw_min(&self) -> Result<u32, Error>;
+
+    /// Sets how `set` picks a brightness level on internal displays that only support a few
+    /// levels.
+    ///
+    /// See [`crate::blocking::windows::BrightnessExt::set_rounding`].
+    fn set_rounding(&mut self, rounding: Rounding);
+
+    /// Sets whether `get` and `set` reopen the monitor and retry once when its handles went stale.
+    ///
+    /// See [`crate::blocking::windows::BrightnessExt::set_reopen_on_stale`].
+    fn set_reopen_on_stale(&mut self, enabled: bool);
+
+    /// Sets a custom mapping between percentages and raw values used by `get` and `set`, or
+    /// restores the default linear mapping with `None`.
+    ///
+    /// See [`crate::blocking::windows::BrightnessExt::set_mapping`].
+    fn set_mapping(&mut self, mapping: Option<BrightnessMapping>);
+
+    /// Sets whether `set` skips writing the brightness of an external monitor when it already has
+    /// the requested value.
+    ///
+    /// See [`crate::blocking::windows::BrightnessExt::set_skip_unchanged`].
+    fn set_skip_unchanged(&mut self, tolerance: Option<u32>);
+
+    /// Sets whether `set` reads the brightness of an external monitor back after writing it.
+    ///
+    /// See [`crate::blocking::windows::BrightnessExt::set_verify_writes`].
+    fn set_verify_writes(&mut self, enabled: bool);
+
+    /// Returns the percentage that `set(requested)` applies, without accessing the device.
+    ///
+    /// See [`crate::blocking::windows::BrightnessExt::preview`].
+    fn preview(&self, requested: u32) -> u32;
+
+    /// Sets the brightness to a raw hardware value, bypassing the percentage mapping.
+    ///
+    /// See [`crate::blocking::windows::BrightnessExt::set_raw`].
+    async fn set_raw(&mut self, value: u32) -> Result<(), Error>;
+
+    /// Returns the color temperature presets supported by the monitor.
+    ///
+    /// See [`crate::blocking::windows::BrightnessExt::supported_color_temperatures`].
+    async fn supported_color_temperatures(&self) -> Result<Vec<ColorTemperature>, Error>;
+
+    /// Returns the current color temperature preset of the monitor.
+    ///
+    /// See [`crate::blocking::windows::BrightnessExt::color_temperature`].
+    async fn color_temperature(&self) -> Result<Option<ColorTemperature>, Error>;
+
+    /// Sets the color temperature preset of the monitor.
+    ///
+    /// See [`crate::blocking::windows::BrightnessExt::set_color_temperature`].
+    async fn set_color_temperature(&mut self, temperature: ColorTemperature) -> Result<(), Error>;
+
+    /// Returns the MCCS version implemented by the monitor as `(major, minor)`.
+    ///
+    /// See [`crate::blocking::windows::BrightnessExt::vcp_version`].
+    async fn vcp_version(&self) -> Result<(u8, u8), Error>;
+
+    /// Returns the display presets supported by the monitor.
+    ///
+    /// See [`crate::blocking::windows::BrightnessExt::supported_display_presets`].
+    async fn supported_display_presets(&self) -> Result<Vec<DisplayPreset>, Error>;
+
+    /// Returns the current display preset of the monitor.
+    ///
+    /// See [`crate::blocking::windows::BrightnessExt::display_preset`].
+    async fn display_preset(&self) -> Result<DisplayPreset, Error>;
+
+    /// Sets the display preset of the monitor, which may also change its brightness.
+    ///
+    /// See [`crate::blocking::windows::BrightnessExt::set_display_preset`].
+    async fn set_display_preset(&mut self, preset: DisplayPreset) -> Result<(), Error>;
+
+    /// Returns whether a control of the monitor was changed from its on-screen menu since the last
+    /// call, and resets the indication.
+    ///
+    /// See [`crate::blocking::windows::BrightnessExt::take_new_control_value`].
+    async fn take_new_control_value(&mut self) -> Result<Option<bool>, Error>;
+
+    /// Returns the brightness levels supported by an internal display, exactly as reported by the
+    /// driver.
+    ///
+    /// See [`crate::blocking::windows::BrightnessExt::supported_brightness_levels`].
+    async fn supported_brightness_levels(&self) -> Result<Vec<u8>, Error>;
+
+    /// Returns the brightness state of an internal display, exactly as reported by the driver.
+    ///
+    /// See [`crate::blocking::windows::BrightnessExt::display_brightness`].
+    async fn display_brightness(&self) -> Result<Option<DisplayBrightness>, Error>;
+
+    /// Returns whether the display is in HDR mode.
+    ///
+    /// See [`crate::blocking::windows::BrightnessExt::is_hdr_active`].
+    async fn is_hdr_active(&self) -> Result<bool, Error>;
+
+    /// Returns the number of bits per color channel the display is driven with.
+    ///
+    /// See [`crate::blocking::windows::BrightnessExt::bit_depth`].
+    async fn bit_depth(&self) -> Result<Option<u32>, Error>;
+
+    /// Returns the mode the display currently uses.
+    ///
+    /// See [`crate::blocking::windows::BrightnessExt::current_mode`].
+    async fn current_mode(&self) -> Result<DisplayMode, Error>;
+
+    /// Returns the area the monitor covers on the virtual desktop, in pixels.
+    ///
+    /// See [`crate::blocking::windows::BrightnessExt::screen_rect`].
+    async fn screen_rect(&self) -> Result<ScreenRect, Error>;
+
+    /// Returns how long the last brightness or VCP feature request to the device took, if any was
+    /// made.
+    ///
+    /// See [`crate::blocking::windows::BrightnessExt::last_io_duration`].
+    fn last_io_duration(&self) -> Option<Duration>;
+}
 
 #[derive(Debug)]
 pub(crate) struct AsyncDeviceImpl(Arc<BlockingDeviceImpl>);
 
 // Windows doesn't have an async C API for monitors, so we will instead spawn the blocking tasks on
 // background threads.
+impl AsyncDeviceImpl {
+    pub(crate) fn info(&self) -> Result<DeviceInfo, Error> {
+        Ok(DeviceInfo {
+            name: self.0.device_name.clone(),
+            path: self.0.device_path.clone(),
+            kind: self.0.kind()?,
+        })
+    }
+}
+
 #[async_trait]
 impl crate::Brightness for AsyncDeviceImpl {
     async fn device_name(&self) -> Result<String, Error> {
@@ -36,6 +245,29 @@ impl crate::Brightness for AsyncDeviceImpl {
         let cloned = Arc::clone(&self.0);
         unblock(move || cloned.set(percentage)).await
     }
+
+    async fn edid(&self) -> Result<Option<EdidInfo>, Error> {
+        let cloned = Arc::clone(&self.0);
+        unblock(move || cloned.edid()).await
+    }
+
+    async fn is_primary(&self) -> Result<bool, Error> {
+        self.0.is_primary()
+    }
+
+    async fn kind(&self) -> Result<DeviceKind, Error> {
+        self.0.kind()
+    }
+
+    async fn raw_capabilities(&self) -> Result<Option<String>, Error> {
+        let cloned = Arc::clone(&self.0);
+        unblock(move || cloned.raw_capabilities()).await
+    }
+
+    async fn brightness_raw_max(&self) -> Result<u32, Error> {
+        let cloned = Arc::clone(&self.0);
+        unblock(move || cloned.brightness_raw_max()).await
+    }
 }
 
 pub(crate) fn brightness_devices() -> impl Stream<Item = Result<AsyncDeviceImpl, SysError>> {
@@ -43,9 +275,170 @@ pub(crate) fn brightness_devices() -> impl Stream<Item = Result<AsyncDeviceImpl,
         .into_stream()
         .map(stream::iter)
         .flatten()
-        .map(|d| d.map(|d| AsyncDeviceImpl(Arc::new(d))).map_err(Into::into))
+        .map(|d| d.map(|d| AsyncDeviceImpl(Arc::new(d))))
+}
+
+/// Returns the brightness devices for `brightness_devices_retry`.
+///
+/// See [`crate::blocking::windows::brightness_devices_for_retry`].
+pub(crate) fn brightness_devices_for_retry() -> impl Stream<Item = Result<AsyncDeviceImpl, SysError>>
+{
+    unblock(crate::blocking::windows::brightness_devices_for_retry)
+        .into_stream()
+        .map(stream::iter)
+        .flatten()
+        .map(|d| d.map(|d| AsyncDeviceImpl(Arc::new(d))))
+}
+
+/// Returns the brightness devices of the running system grouped by `HMONITOR`.
+///
+/// Monitors in duplicate (mirror) mode share a `HMONITOR` and are expected to show the same
+/// brightness, but each of them is controlled separately through DDC/CI. Each group reads the
+/// average brightness of its members and sets all of them, so that mirrored monitors are handled
+/// as a unit. Groups are returned in enumeration order, most of them having a single member.
+///
+/// See [`SyncGroup::new`] for the meaning of `max_divergence`.
+pub async fn hmonitor_groups(max_divergence: u32) -> Result<Vec<SyncGroup>, Error> {
+    let devices = crate::brightness_devices().try_collect::<Vec<_>>().await?;
+    let mut groups = Vec::<(isize, Vec<BrightnessDevice>)>::new();
+    for device in devices {
+        let hmonitor = device.0 .0.hmonitor();
+        match groups.iter_mut().find(|(h, _)| *h == hmonitor) {
+            Some((_, members)) => members.push(device),
+            None => groups.push((hmonitor, vec![device])),
+        }
+    }
+    Ok(groups
+        .into_iter()
+        .map(|(_, members)| SyncGroup::new(members, max_divergence))
+        .collect())
+}
+
+/// Returns the brightness devices of internal displays only.
+///
+/// See [`crate::blocking::windows::internal_brightness_devices`].
+pub fn internal_brightness_devices() -> impl Stream<Item = Result<BrightnessDevice, Error>> {
+    unblock(|| windows::devices(Selection::InternalOnly, false))
+        .into_stream()
+        .map(stream::iter)
+        .flatten()
+        .map(|d| {
+            d.map(|d| BrightnessDevice(AsyncDeviceImpl(Arc::new(d))))
+                .map_err(Into::into)
+        })
+}
+
+/// Returns the brightness devices, including virtual displays.
+///
+/// See [`crate::blocking::windows::brightness_devices_including_virtual`].
+pub fn brightness_devices_including_virtual() -> impl Stream<Item = Result<BrightnessDevice, Error>>
+{
+    unblock(|| windows::devices(Selection::IncludingVirtual, false))
+        .into_stream()
+        .map(stream::iter)
+        .flatten()
+        .map(|d| {
+            d.map(|d| BrightnessDevice(AsyncDeviceImpl(Arc::new(d))))
+                .map_err(Into::into)
+        })
+}
+
+/// Returns the brightness devices of the monitors driven by a display adapter.
+///
+/// See [`crate::blocking::windows::brightness_devices_on_adapter`].
+pub fn brightness_devices_on_adapter(
+    adapter_luid: u64,
+) -> impl Stream<Item = Result<BrightnessDevice, Error>> {
+    unblock(move || windows::devices_on_adapter(adapter_luid))
+        .into_stream()
+        .map(stream::iter)
+        .flatten()
+        .map(|d| {
+            d.map(|d| BrightnessDevice(AsyncDeviceImpl(Arc::new(d))))
+                .map_err(Into::into)
+        })
 }
 
+/// Returns the name and brightness of all monitors driven by a display adapter, as a percentage.
+///
+/// See [`crate::blocking::windows::BrightnessExt::adapter_luid`]. Monitors are read concurrently,
+/// and their results are returned in the order they are found. A monitor failing does not prevent
+/// the other monitors from being read.
+pub async fn get_all_on_adapter(adapter_luid: u64) -> Vec<Result<(String, u32), Error>> {
+    brightness_devices_on_adapter(adapter_luid)
+        .map(|device| async move {
+            let device = device?;
+            Ok((device.device_name().await?, device.get().await?))
+        })
+        .buffered(usize::MAX)
+        .collect()
+        .await
+}
+
+/// Sets the brightness of all monitors driven by a display adapter as a percentage, returning their
+/// names.
+///
+/// See [`get_all_on_adapter`] for the handling of failures.
+pub async fn set_all_on_adapter(adapter_luid: u64, percentage: u32) -> Vec<Result<String, Error>> {
+    brightness_devices_on_adapter(adapter_luid)
+        .map(|device| async move {
+            let mut device = device?;
+            device.set(percentage).await?;
+            device.device_name().await
+        })
+        .buffered(usize::MAX)
+        .collect()
+        .await
+}
+
+/// Returns the brightness device of the monitor under the mouse cursor.
+///
+/// See [`crate::blocking::windows::device_under_cursor`].
+pub async fn device_under_cursor() -> Result<Option<BrightnessDevice>, Error> {
+    let device = unblock(windows::device_impl_under_cursor).await?;
+    Ok(device.map(|d| BrightnessDevice(AsyncDeviceImpl(Arc::new(d)))))
+}
+
+/// Returns the brightness devices arranged by the position of their monitor.
+///
+/// See [`crate::blocking::windows::brightness_devices_grid`].
+pub async fn brightness_devices_grid() -> Result<Vec<Vec<BrightnessDevice>>, Error> {
+    unblock(|| {
+        let devices = windows::brightness_devices()
+            .map(|device| {
+                let device = device?;
+                Ok((
+                    windows::screen_rect(&device)?,
+                    BrightnessDevice(AsyncDeviceImpl(Arc::new(device))),
+                ))
+            })
+            .collect::<Result<Vec<_>, SysError>>()?;
+        Ok(windows::arrange_in_grid(devices))
+    })
+    .await
+}
+
+/// Returns the brightness devices of the given monitors.
+///
+/// See [`crate::blocking::windows::brightness_devices_from_hmonitors`].
+pub fn brightness_devices_from_hmonitors<I>(
+    hmonitors: I,
+) -> impl Stream<Item = Result<BrightnessDevice, Error>>
+where
+    I: IntoIterator<Item = isize>,
+{
+    let hmonitors = hmonitors.into_iter().map(HMONITOR).collect();
+    unblock(move || devices_from_hmonitors(hmonitors, Selection::Controllable, false))
+        .into_stream()
+        .map(stream::iter)
+        .flatten()
+        .map(|d| {
+            d.map(|d| BrightnessDevice(AsyncDeviceImpl(Arc::new(d))))
+                .map_err(Into::into)
+        })
+}
+
+#[async_trait]
 impl BrightnessExt for BrightnessDevice {
     fn device_description(&self) -> Result<String, Error> {
         Ok(self.0 .0.device_description.clone())
@@ -58,4 +451,161 @@ impl BrightnessExt for BrightnessDevice {
     fn device_path(&self) -> Result<String, Error> {
         Ok(self.0 .0.device_path.clone())
     }
+
+    fn instance_id(&self) -> Result<String, Error> {
+        Ok(windows::instance_id(&self.0 .0)?)
+    }
+
+    fn friendly_name(&self) -> Result<String, Error> {
+        Ok(self.0 .0.friendly_name.clone())
+    }
+
+    fn gdi_device_name(&self) -> Result<String, Error> {
+        Ok(self.0 .0.gdi_device_name.clone())
+    }
+
+    fn display_number(&self) -> Result<u32, Error> {
+        Ok(windows::display_number(&self.0 .0)?)
+    }
+
+    fn output_technology(&self) -> Result<OutputTechnology, Error> {
+        Ok(self.0 .0.output_technology.into())
+    }
+
+    fn adapter_luid(&self) -> Result<u64, Error> {
+        Ok(self.0 .0.adapter_luid)
+    }
+
+    fn hmonitor(&self) -> Result<isize, Error> {
+        Ok(self.0 .0.hmonitor())
+    }
+
+    async fn ddcci_enabled(&self) -> Result<bool, Error> {
+        let cloned = Arc::clone(&self.0 .0);
+        unblock(move || Ok(windows::ddcci_probe(&cloned)?)).await
+    }
+
+    async fn is_ghost(&self) -> Result<bool, Error> {
+        let cloned = Arc::clone(&self.0 .0);
+        unblock(move || Ok(windows::is_ghost(&cloned)?)).await
+    }
+
+    fn set_nonzero_floor(&mut self, enabled: bool) {
+        self.0 .0.nonzero_floor.store(enabled, Ordering::Relaxed);
+    }
+
+    fn set_min_percentage(&mut self, percentage: u32) {
+        self.0
+             .0
+            .min_percentage
+            .store(percentage.min(100), Ordering::Relaxed);
+    }
+
+    async fn brightness_raw_min(&self) -> Result<u32, Error> {
+        let cloned = Arc::clone(&self.0 .0);
+        unblock(move || Ok(windows::brightness_raw_min(&cloned)?)).await
+    }
+
+    fn set_rounding(&mut self, rounding: Rounding) {
+        self.0 .0.rounding.store(rounding as u8, Ordering::Relaxed);
+    }
+
+    fn set_reopen_on_stale(&mut self, enabled: bool) {
+        self.0 .0.reopen_on_stale.store(enabled, Ordering::Relaxed);
+    }
+
+    fn set_mapping(&mut self, mapping: Option<BrightnessMapping>) {
+        *self.0 .0.mapping.write().unwrap() = mapping;
+    }
+
+    fn set_skip_unchanged(&mut self, tolerance: Option<u32>) {
+        *self.0 .0.skip_unchanged.write().unwrap() = tolerance;
+    }
+
+    fn set_verify_writes(&mut self, enabled: bool) {
+        self.0 .0.verify_writes.store(enabled, Ordering::Relaxed);
+    }
+
+    fn preview(&self, requested: u32) -> u32 {
+        windows::preview(&self.0 .0, requested)
+    }
+
+    async fn set_raw(&mut self, value: u32) -> Result<(), Error> {
+        let cloned = Arc::clone(&self.0 .0);
+        unblock(move || Ok(windows::set_raw_value(&cloned, value)?)).await
+    }
+
+    async fn supported_color_temperatures(&self) -> Result<Vec<ColorTemperature>, Error> {
+        let cloned = Arc::clone(&self.0 .0);
+        unblock(move || Ok(windows::ddcci_supported_color_temperatures(&cloned)?)).await
+    }
+
+    async fn color_temperature(&self) -> Result<Option<ColorTemperature>, Error> {
+        let cloned = Arc::clone(&self.0 .0);
+        unblock(move || Ok(windows::ddcci_get_color_temperature(&cloned)?)).await
+    }
+
+    async fn set_color_temperature(&mut self, temperature: ColorTemperature) -> Result<(), Error> {
+        let cloned = Arc::clone(&self.0 .0);
+        unblock(move || Ok(windows::ddcci_set_color_temperature(&cloned, temperature)?)).await
+    }
+
+    async fn vcp_version(&self) -> Result<(u8, u8), Error> {
+        let cloned = Arc::clone(&self.0 .0);
+        unblock(move || Ok(windows::ddcci_get_vcp_version(&cloned)?)).await
+    }
+
+    async fn supported_display_presets(&self) -> Result<Vec<DisplayPreset>, Error> {
+        let cloned = Arc::clone(&self.0 .0);
+        unblock(move || Ok(windows::ddcci_supported_display_presets(&cloned)?)).await
+    }
+
+    async fn display_preset(&self) -> Result<DisplayPreset, Error> {
+        let cloned = Arc::clone(&self.0 .0);
+        unblock(move || Ok(windows::ddcci_get_display_preset(&cloned)?)).await
+    }
+
+    async fn set_display_preset(&mut self, preset: DisplayPreset) -> Result<(), Error> {
+        let cloned = Arc::clone(&self.0 .0);
+        unblock(move || Ok(windows::ddcci_set_display_preset(&cloned, preset)?)).await
+    }
+
+    async fn take_new_control_value(&mut self) -> Result<Option<bool>, Error> {
+        let cloned = Arc::clone(&self.0 .0);
+        unblock(move || Ok(windows::ddcci_take_new_control_value(&cloned)?)).await
+    }
+
+    async fn supported_brightness_levels(&self) -> Result<Vec<u8>, Error> {
+        let cloned = Arc::clone(&self.0 .0);
+        unblock(move || Ok(windows::supported_brightness_levels(&cloned)?)).await
+    }
+
+    async fn display_brightness(&self) -> Result<Option<DisplayBrightness>, Error> {
+        let cloned = Arc::clone(&self.0 .0);
+        unblock(move || Ok(windows::display_brightness(&cloned)?)).await
+    }
+
+    async fn is_hdr_active(&self) -> Result<bool, Error> {
+        let cloned = Arc::clone(&self.0 .0);
+        unblock(move || Ok(windows::is_hdr_active(&cloned)?)).await
+    }
+
+    async fn bit_depth(&self) -> Result<Option<u32>, Error> {
+        let cloned = Arc::clone(&self.0 .0);
+        unblock(move || Ok(windows::bit_depth(&cloned)?)).await
+    }
+
+    async fn current_mode(&self) -> Result<DisplayMode, Error> {
+        let cloned = Arc::clone(&self.0 .0);
+        unblock(move || Ok(windows::current_mode(&cloned)?)).await
+    }
+
+    async fn screen_rect(&self) -> Result<ScreenRect, Error> {
+        let cloned = Arc::clone(&self.0 .0);
+        unblock(move || Ok(windows::screen_rect(&cloned)?)).await
+    }
+
+    fn last_io_duration(&self) -> Option<Duration> {
+        *self.0 .0.last_io_duration.read().unwrap()
+    }
 }
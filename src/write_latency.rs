@@ -0,0 +1,75 @@
+// Copyright (C) 2022 The brightness project authors. Distributed under the 0BSD license.
+
+//! Measurement of how fast devices apply brightness changes.
+
+use crate::{Brightness, Error};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// Measures and remembers how long devices take to set their brightness.
+///
+/// Writes to external monitors over DDC/CI usually take tens of milliseconds, while internal
+/// displays are much faster. Knowing the latency of a device allows transitions to use as many
+/// steps as the device can keep up with. Measurements are remembered by device name.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct WriteLatency {
+    latencies: HashMap<String, Duration>,
+}
+
+/// Number of writes timed when measuring the latency of a device.
+const SAMPLES: u32 = 3;
+
+impl WriteLatency {
+    /// Creates an instance that has not measured any device yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the write latency of a device, measuring it if it was not measured yet.
+    ///
+    /// The device is set a few times to its current brightness, so its brightness does not change,
+    /// and the average duration of these writes is returned. Options that skip writes of the
+    /// current brightness make the measurement meaningless and should be disabled while measuring.
+    pub async fn measure(
+        &mut self,
+        device: &mut (dyn Brightness + Send + Sync),
+    ) -> Result<Duration, Error> {
+        let name = device.device_name().await?;
+        if let Some(&latency) = self.latencies.get(&name) {
+            return Ok(latency);
+        }
+        let percentage = device.get().await?;
+        let start = Instant::now();
+        for _ in 0..SAMPLES {
+            device.set(percentage).await?;
+        }
+        let latency = start.elapsed() / SAMPLES;
+        self.latencies.insert(name, latency);
+        Ok(latency)
+    }
+
+    /// Returns the write latency measured for the device with the given name.
+    pub fn latency(&self, device_name: &str) -> Option<Duration> {
+        self.latencies.get(device_name).copied()
+    }
+
+    /// Forgets the write latency of the device with the given name, returning it.
+    ///
+    /// The latency is measured again the next time [`WriteLatency::measure`] is called for the
+    /// device.
+    pub fn forget(&mut self, device_name: &str) -> Option<Duration> {
+        self.latencies.remove(device_name)
+    }
+
+    /// Returns how many writes the device with the given name can apply within `duration`, which
+    /// is the largest useful number of steps for a transition of this duration.
+    ///
+    /// At least one step is returned. `None` is returned if the device was not measured.
+    pub fn max_steps(&self, device_name: &str, duration: Duration) -> Option<u32> {
+        let latency = self.latency(device_name)?;
+        let steps = duration.as_secs_f64() / latency.as_secs_f64().max(f64::MIN_POSITIVE);
+        Some(steps.min(f64::from(u32::MAX)).max(1.0) as u32)
+    }
+}